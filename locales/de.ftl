@@ -0,0 +1,349 @@
+# Die deutschen Meldungen von rust-keylock-shell.
+
+## Tastenkürzel
+key-quit = b
+key-return = z
+key-new = n
+key-bulk = m
+key-filter = f
+key-clear-filter = l
+key-rotation = r
+key-undo = u
+key-redo = w
+key-yes = j
+key-no = n
+key-accept = a
+key-cancel = x
+key-test = t
+key-all = a
+key-next-page = >
+key-previous-page = <
+key-goto-page = s
+key-keep-local = l
+key-take-remote = e
+key-merge = m
+key-add-folder = o
+key-add-webdav = w
+key-sync-now = s
+key-reveal = e
+key-keep-mine = m
+key-take-theirs = i
+key-keep-both = b
+key-sync-log = p
+
+## Allgemein
+return = Zurück
+selection-prompt = Auswahl:
+press-enter = Drücken Sie Enter, um fortzufahren.
+confirm = Sind Sie sicher? ({ $yes }/{ $no }):
+error = Fehler: { $error }
+wrong-input = Fehler: Ungültige Eingabe
+wrong-input-value = Fehler: Ungültige Eingabe '{ $input }'
+list-or = oder
+path-prompt = Bitte geben Sie den Pfad an:
+current-value = { $label } ({ $current }):
+internal-state-update-error = Warnung! Der interne Zustand konnte nicht aktualisiert werden. Grund: { $error }
+internal-state-retrieve-error = Warnung! Der interne Zustand konnte nicht gelesen werden. Grund: { $error }
+internal-state-access-error = Warnung! Auf den internen Zustand konnte nicht zugegriffen werden. Grund: { $error }
+
+## Meldungen
+severity-info = Info
+severity-warn = Warnung
+severity-error = Fehler
+message-press = Drücken Sie
+message-enter = Enter
+message-option = '{ $key }' für { $label }
+message-default-option = '{ $enter }' (oder '{ $key }') für { $label }
+message-dismissed-in = Diese Meldung schließt sich nach { $seconds } Sekunden von selbst.
+messages-title = Meldungen dieser Sitzung:
+messages-empty = In dieser Sitzung wurden keine Meldungen angezeigt.
+messages-selected = gewählt: { $label }
+
+## Entsperren und Ändern des Master-Passworts
+password-prompt = Bitte geben Sie Ihr Passwort ein:
+password-again-prompt = Bitte geben Sie Ihr Passwort noch einmal ein:
+password-empty = Das Passwort darf nicht leer sein!
+passwords-mismatch = Die Passwörter stimmen nicht überein! Drücken Sie eine Taste, um es erneut zu versuchen
+number-prompt = Was ist Ihre Lieblingszahl?:
+number-again-prompt = Bitte geben Sie Ihre Lieblingszahl noch einmal ein:
+numbers-mismatch = Die Zahlen stimmen nicht überein! Drücken Sie eine Taste, um es erneut zu versuchen
+import-password-prompt = Bitte geben Sie das Passwort ein:
+unlock-wait = Der letzte Entsperrversuch ist fehlgeschlagen. Bitte warten Sie { $seconds } Sekunden...
+unlock-attempts-left = Noch { $count } Versuche.
+unlock-no-attempts-left = Keine Versuche mehr. Das Programm wird beendet.
+unlock-failed-since-last-one = 1 fehlgeschlagener Entsperrversuch seit der letzten Anmeldung.
+unlock-failed-since-last = { $count } fehlgeschlagene Entsperrversuche seit der letzten Anmeldung.
+
+## Ändern des Master-Passworts
+change-pass-title = Master-Passwort
+change-pass-intro =
+    Das Master-Passwort und Ihre Lieblingszahl schützen die Passwortdatei gemeinsam.
+    Die Lieblingszahl ist ein zweites Geheimnis: Sie bestimmt, wo das Salz des Schlüssels abgelegt wird. Beide werden
+    zum Entsperren benötigt und keines von beiden wird gespeichert. Wählen Sie eine Zahl, die Sie sich merken können:
+    Ohne sie kann die Passwortdatei nicht entsperrt werden, auch nicht mit dem richtigen Passwort.
+change-pass-policy = Das neue Passwort braucht mindestens { $length } Zeichen und eine Stärke von mindestens { $score } von 4 ({ $label }).
+change-pass-policy-entries = Es darf nicht das Passwort eines Ihrer Einträge sein.
+change-pass-current-prompt = Bitte geben Sie Ihr aktuelles Passwort ein (leer zum Abbrechen):
+change-pass-current-wrong = Das aktuelle Passwort konnte nicht bestätigt werden: { $error }
+change-pass-new-prompt = Bitte geben Sie das neue Passwort ein:
+change-pass-new-prompt-cancel = Bitte geben Sie das neue Passwort ein (leer zum Abbrechen):
+change-pass-strength = Stärke: { $score } von 4 ({ $label })
+change-pass-summary =
+    Zusammenfassung:
+    Neues Passwort: { $length } Zeichen, Stärke { $score } von 4 ({ $label })
+    Lieblingszahl: bestätigt
+change-pass-summary-entries = Verschieden von den Passwörtern der { $count } gespeicherten Einträge
+change-pass-summary-save = Das neue Master-Passwort gilt für die Passwortdatei, sobald sie gespeichert wird.
+change-pass-confirm = Das neue Master-Passwort festlegen? ({ $yes }/{ $no }):
+change-pass-cancelled = Das Master-Passwort wurde nicht geändert.
+policy-violated = Das Passwort erfüllt die Richtlinie nicht:
+policy-too-short = es hat { $length } Zeichen, mindestens { $min } sind nötig
+policy-too-weak = seine Stärke ist { $score } von 4, mindestens { $min } ist nötig
+policy-same-as-entry = es ist das Passwort des Eintrags '{ $name }'
+strength-very-weak = sehr schwach
+strength-weak = schwach
+strength-fair = mittel
+strength-strong = stark
+strength-very-strong = sehr stark
+
+## Hauptmenü
+main-menu-title = Hauptmenü:
+main-entries = Einträge anzeigen
+main-save = Änderungen speichern
+main-change-pass = Master-Passwort ändern
+main-config = Konfiguration bearbeiten
+main-import = Verschlüsselte Einträge aus dem Dateisystem importieren
+main-export = Einträge in das Dateisystem exportieren
+main-check = Qualität der Passwörter prüfen
+main-messages = Meldungen dieser Sitzung anzeigen
+main-sync = Synchronisation mit Ordnern und WebDAV-Servern
+main-audit = Audit-Protokoll anzeigen
+main-quit = Beenden
+
+## Liste der Einträge
+entries-filtered = Einträge gefiltert nach '{ $filter }'
+entries-due-title = Einträge, deren Passwort erneuert werden sollte
+entries-select = Bitte wählen Sie einen der Einträge
+entries-new = drücken Sie '{ $key }', um einen neuen Eintrag zu erstellen
+entries-bulk = drücken Sie '{ $key }', um mehrere Einträge für eine Sammelaktion auszuwählen
+entries-show-all = drücken Sie '{ $key }', um alle Einträge anzuzeigen
+entries-show-due = drücken Sie '{ $key }', um nur die Einträge anzuzeigen, deren Passwort erneuert werden sollte
+entries-undo = drücken Sie '{ $key }', um { $mutation } rückgängig zu machen
+entries-redo = drücken Sie '{ $key }', um { $mutation } zu wiederholen
+entries-main-menu = drücken Sie '{ $key }', um zum Hauptmenü zurückzukehren
+entries-filter = drücken Sie '{ $key }', um die angezeigten Einträge zu filtern
+entries-clear-filter = drücken Sie '{ $key }', um den aktuellen Filter zu entfernen
+entries-next-page = drücken Sie '{ $key }' für die nächste Seite
+entries-previous-page = drücken Sie '{ $key }' für die vorherige Seite
+entries-goto-page = drücken Sie '{ $key }', um zu einer Seite zu springen
+entries-page = Seite { $page } von { $pages }
+page-prompt = Seite (1-{ $pages }):
+filter-prompt = Filtern nach:
+rotation-due-message = Die Passwörter von { $count } Einträgen sollten erneuert werden. Drücken Sie '{ $key }' in der Liste der Einträge, um sie anzuzeigen.
+
+## Markierungen der Einträge, im barrierefreien Modus oder ohne Farben
+marker-leaked = [GELEAKT]
+marker-due-for-rotation = [ERNEUERN]
+
+## Rückgängig
+mutation-added = die Erstellung von '{ $name }'
+mutation-deleted = das Löschen von '{ $name }'
+mutation-replaced = die Bearbeitung von '{ $name }'
+undo-not-found = Der Eintrag für { $mutation } wurde nicht gefunden
+
+## Eintrag
+field-name = Name
+field-url = URL
+field-user = Benutzername
+field-pass = Passwort
+field-desc = Beschreibung
+field-created = Erstellt
+field-modified = Geändert
+field-password-changed = Passwort geändert
+field-rotation = Passwort erneuern
+field-previous-passwords = Frühere Passwörter
+rotation-every = alle { $days } Tage
+rotation-every-due = alle { $days } Tage (fällig)
+entry-menu-title = Eintragsmenü:
+entry-edit = Bearbeiten
+entry-delete = Löschen
+entry-copy-url = URL kopieren
+entry-copy-user = Benutzername kopieren
+entry-copy-pass = Passwort kopieren
+entry-history = Passwortverlauf
+entry-open-url = URL öffnen
+open-url-copy = Jetzt den Benutzernamen und nach { $delay } Sekunden das Passwort kopieren? ({ $yes }/{ $no }):
+open-url-copied = Der Benutzername ist kopiert. Das Passwort wird in { $delay } Sekunden kopiert, sofern Sie den Eintrag nicht verlassen, und { $clear } Sekunden später aus der Zwischenablage gelöscht.
+open-url-opened = Die URL wurde in Ihrem Browser geöffnet.
+history-empty = Es gibt keine früheren Passwörter für diesen Eintrag.
+history-title = Frühere Passwörter von '{ $name }':
+history-item = { $pass } (ersetzt am { $at })
+history-select = Wählen Sie ein Passwort, um es wiederherzustellen, oder drücken Sie '{ $key }', um zurückzukehren:
+
+## Bearbeiten eines Eintrags
+edit-name = Name ändern
+edit-url = URL ändern
+edit-user = Benutzername ändern
+edit-pass = Passwort ändern
+edit-generate = Neue Passphrase erzeugen
+edit-desc = Beschreibung ändern
+edit-rotation = Passworterneuerung ändern
+edit-accept = Änderungen übernehmen
+edit-cancel = Abbrechen
+changing-field = { $field } ändern ({ $current }):
+rotation-prompt = Das Passwort alle wie viele Tage erneuern? 0 deaktiviert die Erneuerung ({ $current }):
+
+## Sammelaktionen
+bulk-select = Einträge auswählen (z.B. 1-5,8), '{ $key }' für alle angezeigten Einträge oder Enter zum Abbrechen:
+bulk-title = Sammelaktionen für { $count } Einträge:
+bulk-delete = Löschen
+bulk-tag = Markieren
+bulk-regenerate = Neue Passphrasen erzeugen
+bulk-confirm-list = { $action }: die folgenden { $count } Einträge:
+bulk-action-delete = Löschen
+bulk-action-tag = Mit '#{ $tag }' markieren
+bulk-action-regenerate = Neue Passphrase erzeugen
+bulk-summary = { $action }: { $processed } Einträge verarbeitet
+bulk-summary-not-found = { $action }: { $processed } Einträge verarbeitet, { $not_found } Einträge wurden nicht gefunden
+tag-prompt = Markierung:
+tag-invalid = Eine Markierung darf nicht leer sein und keine Leerzeichen enthalten.
+
+## Zusammenführen importierter Einträge
+merge-title = Der importierte Eintrag { $name } unterscheidet sich vom vorhandenen:
+merge-base = Gemeinsam
+merge-base-unknown = unbekannt
+merge-mine = Meiner
+merge-theirs = Importiert
+merge-choice = Ergebnis
+merge-keep-mine = Meinen behalten
+merge-take-theirs = Den importierten übernehmen
+merge-keep-both = Beide behalten
+merge-change-field = Für { $field } wählen
+merge-reveal = Die Passwörter anzeigen
+merge-hide = Die Passwörter verbergen
+merge-accept = Mit dieser Auswahl zusammenführen
+merge-skip = Beide Einträge unverändert behalten
+merge-summary = { $count } importierte Einträge wurden zusammengeführt. Speichern Sie, um die Änderungen zu behalten
+
+## Konfiguration
+config-nextcloud = Nextcloud-Konfiguration
+config-dropbox = Dropbox-Konfiguration
+config-clear = Alle Konfigurationen löschen
+config-return = Zurück zum Hauptmenü
+config-server-url = Server-URL
+config-username = Benutzername
+config-password = Passwort
+config-self-signed = Ein selbstsigniertes Zertifikat verwenden? ({ $yes }/{ $no })
+config-nextcloud-empty-url = Lassen Sie die Server-URL leer, um zurückzukehren.
+config-test = Verbindung testen
+config-accept = Konfiguration speichern
+config-cancel = Abbrechen
+config-testing = Die Verbindung zu { $url } wird getestet ...
+config-invalid = Die Konfiguration ist ungültig: { $error }
+webdav-url-empty = Die Server-URL darf nicht leer sein.
+webdav-url-invalid = Die Server-URL ist ungültig: { $error }. Sie sollte wie https://cloud.example.com/nextcloud aussehen
+webdav-url-scheme = Die Server-URL muss mit https:// oder http:// beginnen, nicht mit { $scheme }://
+webdav-url-host = Die Server-URL enthält keinen Servernamen.
+webdav-url-extra = Die Server-URL darf keine Zugangsdaten, keine Abfrage und kein Fragment enthalten.
+webdav-username-empty = Der Benutzername darf nicht leer sein.
+webdav-username-slash = Der Benutzername darf kein '/' enthalten.
+webdav-password-empty = Das Passwort darf nicht leer sein.
+webdav-ok = Die Verbindung funktioniert und die Zugangsdaten werden akzeptiert.
+webdav-dns = Der Servername '{ $host }' konnte nicht aufgelöst werden. Bitte prüfen Sie die Server-URL.
+webdav-connection = Der Server ist nicht erreichbar: { $error }
+webdav-tls = Die sichere Verbindung ist fehlgeschlagen: { $error }. Falls der Server ein selbstsigniertes Zertifikat verwendet, erlauben Sie es.
+webdav-unauthorized = Der Server hat den Benutzernamen oder das Passwort abgelehnt.
+webdav-not-found = Der Server hat keine WebDAV-Dateien für dieses Konto. Bitte prüfen Sie die Server-URL und den Benutzernamen.
+webdav-status = Der Server hat mit dem unerwarteten Status { $status } geantwortet.
+webdav-invalid-response = Der Server hat nicht wie ein WebDAV-Server geantwortet: { $error }
+config-sync-targets = Synchronisation mit Ordnern und WebDAV-Servern
+config-return-configuration = Zurück zur Konfiguration
+sync-targets-title = Die Passwortdatei wird synchronisiert mit:
+sync-targets-empty = Es sind keine Synchronisationsziele konfiguriert.
+sync-target-folder = { $name } (Ordner { $location })
+sync-target-webdav = { $name } (WebDAV { $location })
+sync-add-folder = Einen Ordner hinzufügen
+sync-add-webdav = Einen WebDAV-Server hinzufügen
+sync-remove = Die Synchronisation mit { $name } entfernen? ({ $yes }/{ $no })
+sync-target-name = Name (leer, um zurückzukehren)
+sync-target-name-taken = Es gibt bereits ein Ziel mit dem Namen { $name }.
+sync-folder-path = Ordner
+sync-folder-missing = Der Ordner '{ $path }' existiert nicht.
+sync-webdav-url = URL der WebDAV-Sammlung
+sync-password-prompt = Das Passwort von { $username } für { $name } (leer zum Überspringen):
+sync-uploaded = Die Passwortdatei wurde nach { $name } kopiert.
+sync-downloaded = Die Passwortdatei wurde von { $name } übernommen. Die bisherige wurde als Sicherung behalten.
+sync-target-newer = { $name } hat eine neuere Passwortdatei. Sie wird beim nächsten Entsperren übernommen.
+sync-failed = Die Synchronisation mit { $name } ist fehlgeschlagen: { $error }
+sync-conflict-title = Die Passwortdatei wurde hier und in { $name } geändert. Was soll geschehen?
+sync-conflict-since = Beide Kopien wurden seit der Synchronisation mit { $target } am { $time } geändert:
+sync-conflict-never = Die Kopie hier und die Kopie in { $target } wurden nie synchronisiert:
+sync-this-device = Dieses Gerät
+sync-bytes = { $size } Bytes
+sync-newer = Die Kopie von { $name } ist neuer.
+sync-keep-local = Die Kopie dieses Geräts behalten und die in { $name } ersetzen
+sync-take-remote = Die Kopie von { $name } übernehmen und die dieses Geräts als Sicherung behalten
+sync-merge = Zusammenführen: die Einträge von { $name } nach dem Entsperren importieren, dann speichern
+sync-skip = Jetzt nichts tun
+sync-now = Jetzt synchronisieren
+sync-show-log = Das Synchronisationsprotokoll anzeigen
+sync-return = Zurück zum Hauptmenü
+sync-done = Die Synchronisation ist abgeschlossen
+sync-status-title = Synchronisation der Passwortdatei:
+sync-status-synced = Zuletzt synchronisiert: { $time }
+sync-status-last = Letzter Versuch am { $time }: { $result }
+sync-status-pending = Die Passwortdatei wurde seitdem geändert.
+sync-log-title = Synchronisationen, die neueste zuerst:
+sync-log-empty = Es wurde noch nichts synchronisiert.
+sync-result-up-to-date = aktuell
+sync-result-uploaded = dorthin kopiert
+sync-result-downloaded = von dort übernommen
+sync-result-target-newer = eine neuere Kopie wartet auf das nächste Entsperren
+sync-result-merging = wird zusammengeführt
+sync-result-skipped = übersprungen
+sync-result-failed = fehlgeschlagen
+sync-no-modification-time = Der Server hat nicht mitgeteilt, wann die Passwortdatei geändert wurde.
+sync-local-missing = Die Passwortdatei dieses Geräts fehlt.
+sync-upload-missing = Die kopierte Passwortdatei wurde im Ziel nicht gefunden.
+sync-incomplete-download = Der Download ist unvollständig: { $received } von { $size } Bytes wurden empfangen. Die Passwortdatei wurde nicht geändert.
+dropbox-acquire = Ein Authentifizierungstoken anfordern? ({ $yes }/{ $no }):
+dropbox-renew = Ein Token ist vorhanden. Möchten Sie es erneuern? ({ $yes }/{ $no }):
+dropbox-headless =
+    Öffnen Sie die folgende URL in einem Browser auf einem beliebigen Gerät und erlauben Sie den Zugriff.
+    Dropbox leitet dann zu einer localhost-Adresse weiter, die nicht lädt. Kopieren Sie diese Adresse, oder den Code darin, und fügen Sie sie hier ein.
+dropbox-code-prompt = Code oder Weiterleitungsadresse (leer zum Abbrechen):
+dropbox-url-invalid = Die Dropbox-Autorisierungs-URL ist ungültig.
+dropbox-code-missing = Es wurde kein Code gefunden. Bitte fügen Sie den Code oder die ganze Adresse ein, zu der Dropbox weitergeleitet hat.
+dropbox-authorization-denied = Dropbox hat den Zugriff nicht autorisiert: { $error }
+dropbox-code-rejected = Dropbox hat den Code abgelehnt: { $error }. Bitte öffnen Sie die URL erneut für einen neuen Code.
+dropbox-request-failed = Das Token konnte nicht von Dropbox abgerufen werden: { $error }
+dropbox-browser-opened =
+    Eine URL wurde in Ihrem Browser geöffnet.
+    Bitte melden Sie sich bei Ihrem Dropbox-Konto an und führen Sie die nötigen Schritte aus, um ein Dropbox-Authentifizierungstoken zu erhalten.
+
+## Audit-Protokoll
+audit-title = Die letzten Vorgänge, die neuesten zuerst:
+audit-empty = Es wurde noch nichts aufgezeichnet.
+audit-older = { $count } ältere Einträge sind in der Datei gespeichert.
+audit-intact = Das Audit-Protokoll { $path } ist unverändert.
+audit-tampered = WARNUNG! Das Audit-Protokoll wurde verändert: { $error }
+audit-verified = Die { $count } Einträge des Audit-Protokolls { $path } sind unverändert.
+audit-invalid-record = Zeile { $line } ist kein gültiger Eintrag
+audit-broken-chain = der Eintrag vor Zeile { $line } wurde verändert oder entfernt
+audit-modified-record = der Eintrag in Zeile { $line } wurde verändert
+audit-event-unlocked = Entsperrt
+audit-event-unlock-failed = Entsperren fehlgeschlagen
+audit-event-master-password-changed = Master-Passwort festgelegt
+audit-event-entry-viewed = Angezeigt
+audit-event-entry-copied = Kopiert
+audit-event-entry-added = Hinzugefügt
+audit-event-entry-edited = Bearbeitet
+audit-event-entry-deleted = Gelöscht
+audit-event-exported = Exportiert nach
+audit-event-imported = Importiert aus
+audit-event-configuration-changed = Konfiguration geändert
+
+## Beenden
+exit-unsaved =
+    WARNUNG!
+    Es gibt ungespeicherte Änderungen! Möchten Sie wirklich beenden? ({ $yes }/{ $no })
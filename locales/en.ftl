@@ -0,0 +1,352 @@
+# The English messages of rust-keylock-shell. They are used for any message that a locale does not define.
+#
+# The messages whose id starts with `key-` are shortcut keys. The keys that are offered in the same prompt must be
+# different and they cannot be numbers, as the numbers select the options of the menus.
+
+## Shortcut keys
+key-quit = q
+key-return = r
+key-new = n
+key-bulk = m
+key-filter = f
+key-clear-filter = c
+key-rotation = d
+key-undo = z
+key-redo = y
+key-yes = y
+key-no = n
+key-accept = a
+key-cancel = c
+key-test = t
+key-all = a
+key-next-page = >
+key-previous-page = <
+key-goto-page = g
+key-keep-local = l
+key-take-remote = t
+key-merge = m
+key-add-folder = f
+key-add-webdav = w
+key-sync-now = s
+key-reveal = v
+key-keep-mine = m
+key-take-theirs = t
+key-keep-both = b
+key-sync-log = l
+
+## Common
+return = Return
+selection-prompt = Selection:
+press-enter = Press Enter to continue.
+confirm = Are you sure? ({ $yes }/{ $no }):
+error = Error: { $error }
+wrong-input = Error: Wrong input
+wrong-input-value = Error: Wrong input '{ $input }'
+list-or = or
+path-prompt = Please define the path:
+current-value = { $label } ({ $current }):
+internal-state-update-error = Warning! Could not update the internal state. Reason: { $error }
+internal-state-retrieve-error = Warning! Could not retrieve the internal state. Reason: { $error }
+internal-state-access-error = Warning! Could not access the internal state. Reason: { $error }
+
+## Messages
+severity-info = Info
+severity-warn = Warn
+severity-error = Error
+message-press = Press
+message-enter = Enter
+message-option = '{ $key }' for { $label }
+message-default-option = '{ $enter }' (or '{ $key }') for { $label }
+message-dismissed-in = This message closes by itself in { $seconds } seconds.
+messages-title = Messages of this session:
+messages-empty = No messages were shown in this session.
+messages-selected = selected: { $label }
+
+## Unlocking and changing the master password
+password-prompt = Please provide your password:
+password-again-prompt = Please provide your password once again:
+password-empty = Password cannot be empty!
+passwords-mismatch = The provided passwords did not match! Press any key to try again
+number-prompt = What is your favorite number?:
+number-again-prompt = Please provide your favorite number once again:
+numbers-mismatch = The provided numbers did not match! Press any key to try again
+import-password-prompt = Please provide the password:
+unlock-wait = The last attempt to unlock failed. Please wait { $seconds } seconds...
+unlock-attempts-left = { $count } attempts left.
+unlock-no-attempts-left = No attempts left. Exiting.
+unlock-failed-since-last-one = 1 failed attempt to unlock since the last login.
+unlock-failed-since-last = { $count } failed attempts to unlock since the last login.
+
+## Changing the master password
+change-pass-title = Master password
+change-pass-intro =
+    The master password and your favourite number protect the passwords file together.
+    The favourite number is a second secret: it decides where the salt of the encryption key is placed, so both are
+    needed to unlock and neither of them is stored anywhere. Choose a number that you will remember: without it,
+    the passwords file cannot be unlocked, even with the right password.
+change-pass-policy = The new password needs at least { $length } characters and a strength of at least { $score } of 4 ({ $label }).
+change-pass-policy-entries = It may not be the password of one of your Entries.
+change-pass-current-prompt = Please provide your current password (empty to cancel):
+change-pass-current-wrong = The current password could not be verified: { $error }
+change-pass-new-prompt = Please provide the new password:
+change-pass-new-prompt-cancel = Please provide the new password (empty to cancel):
+change-pass-strength = Strength: { $score } of 4 ({ $label })
+change-pass-summary =
+    Summary:
+    New password: { $length } characters, strength { $score } of 4 ({ $label })
+    Favourite number: confirmed
+change-pass-summary-entries = Different from the passwords of the { $count } saved Entries
+change-pass-summary-save = The new master password applies to the passwords file when it is saved.
+change-pass-confirm = Set the new master password? ({ $yes }/{ $no }):
+change-pass-cancelled = The master password is not changed.
+policy-violated = The password does not follow the policy:
+policy-too-short = it has { $length } characters, at least { $min } are needed
+policy-too-weak = its strength is { $score } of 4, at least { $min } is needed
+policy-same-as-entry = it is the password of the Entry '{ $name }'
+strength-very-weak = very weak
+strength-weak = weak
+strength-fair = fair
+strength-strong = strong
+strength-very-strong = very strong
+
+## Main Menu
+main-menu-title = Main Menu:
+main-entries = Show Existing Entries
+main-save = Save changes
+main-change-pass = Change Master Password
+main-config = Edit Configuration
+main-import = Import Encrypted Entries from the filesystem
+main-export = Export Entries to the filesystem
+main-check = Check the Passwords quality
+main-messages = Show the messages of this session
+main-sync = Synchronisation with folders and WebDAV servers
+main-audit = Show the audit log
+main-quit = Quit
+
+## Entries list
+entries-filtered = Entries filtered by '{ $filter }'
+entries-due-title = Entries that are due for rotation
+entries-select = Please select one of the Entries
+entries-new = press '{ $key }' to create a new Entry
+entries-bulk = press '{ $key }' to select multiple Entries for a bulk action
+entries-show-all = press '{ $key }' to present all the Entries
+entries-show-due = press '{ $key }' to present only the Entries that are due for rotation
+entries-undo = press '{ $key }' to undo the { $mutation }
+entries-redo = press '{ $key }' to redo the { $mutation }
+entries-main-menu = press '{ $key }' to return to the Main Menu
+entries-filter = press '{ $key }' to filter the presented Entries
+entries-clear-filter = press '{ $key }' to clear the currently applied filter
+entries-next-page = press '{ $key }' for the next page
+entries-previous-page = press '{ $key }' for the previous page
+entries-goto-page = press '{ $key }' to go to a page
+entries-page = Page { $page } of { $pages }
+page-prompt = Page (1-{ $pages }):
+filter-prompt = Filter by:
+rotation-due-message = The passwords of { $count } Entries are due for rotation. Press '{ $key }' in the Entries list to see them.
+
+## Markers of the Entries, in the accessible mode or without colours
+marker-leaked = [LEAKED]
+marker-due-for-rotation = [ROTATE]
+
+## Undo
+mutation-added = creation of '{ $name }'
+mutation-deleted = deletion of '{ $name }'
+mutation-replaced = edit of '{ $name }'
+undo-not-found = Could not find the Entry to apply the { $mutation }
+
+## Entry
+field-name = Name
+field-url = URL
+field-user = Username
+field-pass = Password
+field-desc = Description
+field-created = Created
+field-modified = Modified
+field-password-changed = Password changed
+field-rotation = Password rotation
+field-previous-passwords = Previous passwords
+rotation-every = every { $days } days
+rotation-every-due = every { $days } days (due for rotation)
+entry-menu-title = Entry Menu:
+entry-edit = Edit
+entry-delete = Delete
+entry-copy-url = Copy URL
+entry-copy-user = Copy username
+entry-copy-pass = Copy Password
+entry-history = Password history
+entry-open-url = Open URL
+open-url-copy = Copy the username now and the password after { $delay } seconds? ({ $yes }/{ $no }):
+open-url-copied = The username is copied. The password will be copied in { $delay } seconds, unless you leave the Entry, and cleared from the clipboard { $clear } seconds later.
+open-url-opened = The URL has been opened in your browser.
+history-empty = There are no previous passwords for this Entry.
+history-title = Previous passwords of '{ $name }':
+history-item = { $pass } (replaced at { $at })
+history-select = Select a password to restore it or press '{ $key }' to return:
+
+## Editing an Entry
+edit-name = Change Name
+edit-url = Change URL
+edit-user = Change Username
+edit-pass = Change Password
+edit-generate = Generate new passphrase
+edit-desc = Change Description
+edit-rotation = Change Password rotation
+edit-accept = Accept changes
+edit-cancel = Cancel
+changing-field = Changing { $field } ({ $current }):
+rotation-prompt = Rotate the Password every how many days? Use 0 to disable ({ $current }):
+
+## Bulk actions
+bulk-select = Select Entries (e.g. 1-5,8), '{ $key }' for all the presented Entries or press Enter to cancel:
+bulk-title = Bulk actions for { $count } Entries:
+bulk-delete = Delete
+bulk-tag = Tag
+bulk-regenerate = Generate new passphrases
+bulk-confirm-list = { $action } the following { $count } Entries:
+bulk-action-delete = Delete
+bulk-action-tag = Tag with '#{ $tag }'
+bulk-action-regenerate = Generate new passphrase for
+bulk-summary = { $action }: { $processed } Entries processed
+bulk-summary-not-found = { $action }: { $processed } Entries processed, { $not_found } Entries could not be found
+tag-prompt = Tag:
+tag-invalid = A tag cannot be empty or contain spaces.
+
+## Merging imported Entries
+merge-title = The imported Entry { $name } differs from the existing one:
+merge-base = Common
+merge-base-unknown = unknown
+merge-mine = Mine
+merge-theirs = Imported
+merge-choice = Result
+merge-keep-mine = Keep mine
+merge-take-theirs = Take the imported
+merge-keep-both = Keep both
+merge-change-field = Choose for { $field }
+merge-reveal = Reveal the passwords
+merge-hide = Hide the passwords
+merge-accept = Merge with these choices
+merge-skip = Keep both Entries as they are
+merge-summary = { $count } imported Entries were merged. Save to keep the changes
+
+## Configuration
+config-nextcloud = Nextcloud configuration
+config-dropbox = Dropbox configuration
+config-clear = Clear all configurations
+config-return = Return to Main Menu
+config-server-url = Server URL
+config-username = Username
+config-password = password
+config-self-signed = Use a self-signed certificate? ({ $yes }/{ $no })
+config-nextcloud-empty-url = Leave the Server URL empty to go back.
+config-test = Test the connection
+config-accept = Save the configuration
+config-cancel = Cancel
+config-testing = Testing the connection to { $url } ...
+config-invalid = The configuration is not valid: { $error }
+webdav-url-empty = The Server URL cannot be empty.
+webdav-url-invalid = The Server URL is not valid: { $error }. It should look like https://cloud.example.com/nextcloud
+webdav-url-scheme = The Server URL must start with https:// or http://, not { $scheme }://
+webdav-url-host = The Server URL has no server name.
+webdav-url-extra = The Server URL cannot contain credentials, a query or a fragment.
+webdav-username-empty = The Username cannot be empty.
+webdav-username-slash = The Username cannot contain '/'.
+webdav-password-empty = The password cannot be empty.
+webdav-ok = The connection works and the credentials are accepted.
+webdav-dns = The server name '{ $host }' could not be resolved. Please check the Server URL.
+webdav-connection = The server could not be reached: { $error }
+webdav-tls = The secure connection failed: { $error }. If the server uses a self-signed certificate, allow it.
+webdav-unauthorized = The server rejected the Username or the password.
+webdav-not-found = The server has no WebDAV files for this account. Please check the Server URL and the Username.
+webdav-status = The server answered with the unexpected status { $status }.
+webdav-invalid-response = The server did not answer like a WebDAV server: { $error }
+config-sync-targets = Synchronisation with folders and WebDAV servers
+config-return-configuration = Return to the configuration
+sync-targets-title = The passwords file is synchronised with:
+sync-targets-empty = No synchronisation targets are configured.
+sync-target-folder = { $name } (folder { $location })
+sync-target-webdav = { $name } (WebDAV { $location })
+sync-add-folder = Add a folder
+sync-add-webdav = Add a WebDAV server
+sync-remove = Remove the synchronisation with { $name }? ({ $yes }/{ $no })
+sync-target-name = Name (empty to go back)
+sync-target-name-taken = There is already a target with the name { $name }.
+sync-folder-path = Folder
+sync-folder-missing = The folder '{ $path }' does not exist.
+sync-webdav-url = WebDAV collection URL
+sync-password-prompt = The password of { $username } for { $name } (empty to skip):
+sync-uploaded = The passwords file was copied to { $name }.
+sync-downloaded = The passwords file was taken from { $name }. The previous one was kept as a backup.
+sync-target-newer = { $name } has a newer passwords file. It is taken the next time you unlock.
+sync-failed = The synchronisation with { $name } failed: { $error }
+sync-conflict-title = The passwords file changed both here and in { $name }. What should be done?
+sync-conflict-since = Both copies changed since the synchronisation with { $target } at { $time }:
+sync-conflict-never = The copy here and the copy in { $target } were never synchronised:
+sync-this-device = This device
+sync-bytes = { $size } bytes
+sync-newer = The copy of { $name } is newer.
+sync-keep-local = Keep the copy of this device and replace the one in { $name }
+sync-take-remote = Take the copy of { $name } and keep the one of this device as a backup
+sync-merge = Merge: import the Entries of { $name } after unlocking, then save
+sync-skip = Do nothing now
+sync-now = Synchronise now
+sync-show-log = Show the synchronisation log
+sync-return = Return to Main Menu
+sync-done = The synchronisation is finished
+sync-status-title = Synchronisation of the passwords file:
+sync-status-synced = Last synchronised: { $time }
+sync-status-last = Last attempt at { $time }: { $result }
+sync-status-pending = The passwords file changed since then.
+sync-log-title = Synchronisations, the latest first:
+sync-log-empty = Nothing was synchronised yet.
+sync-result-up-to-date = up to date
+sync-result-uploaded = copied there
+sync-result-downloaded = taken from there
+sync-result-target-newer = a newer copy is waiting for the next unlock
+sync-result-merging = merging
+sync-result-skipped = skipped
+sync-result-failed = failed
+sync-no-modification-time = The server did not tell when the passwords file was modified.
+sync-local-missing = The passwords file of this device is missing.
+sync-upload-missing = The copied passwords file cannot be found in the target.
+sync-incomplete-download = The download is incomplete: { $received } of { $size } bytes were received. The passwords file was not changed.
+dropbox-acquire = Acquire an authentication token? ({ $yes }/{ $no }):
+dropbox-renew = A token is acquired. Do you want to renew? ({ $yes }/{ $no }):
+dropbox-headless =
+    Open the following URL in a browser on any device and allow the access.
+    Dropbox then redirects to a localhost address that does not load. Copy that address, or the code in it, and paste it here.
+dropbox-code-prompt = Code or redirect address (empty to cancel):
+dropbox-url-invalid = The Dropbox authorization URL is not valid.
+dropbox-code-missing = No code was found. Please paste the code, or the whole address that Dropbox redirected to.
+dropbox-authorization-denied = Dropbox did not authorize the access: { $error }
+dropbox-code-rejected = Dropbox rejected the code: { $error }. Please open the URL again for a new code.
+dropbox-request-failed = The token could not be retrieved from Dropbox: { $error }
+dropbox-browser-opened =
+    A URL has been opened in your browser.
+    Please log in your Dropbox account and do the required actions to acquire a Dropbox authentication token.
+
+## Audit log
+audit-title = The last operations, newest first:
+audit-empty = Nothing was recorded yet.
+audit-older = { $count } older records are kept in the file.
+audit-intact = The audit log { $path } is intact.
+audit-tampered = WARNING! The audit log was modified: { $error }
+audit-verified = The { $count } records of the audit log { $path } are intact.
+audit-invalid-record = line { $line } is not a valid record
+audit-broken-chain = the record before line { $line } was changed or removed
+audit-modified-record = the record in line { $line } was changed
+audit-event-unlocked = Unlocked
+audit-event-unlock-failed = Failed to unlock
+audit-event-master-password-changed = Master password set
+audit-event-entry-viewed = Viewed
+audit-event-entry-copied = Copied
+audit-event-entry-added = Added
+audit-event-entry-edited = Edited
+audit-event-entry-deleted = Deleted
+audit-event-exported = Exported to
+audit-event-imported = Imported from
+audit-event-configuration-changed = Configuration changed
+
+## Exit
+exit-unsaved =
+    WARNING!
+    There are changes that are not saved! Are you sure you want to Exit? ({ $yes }/{ $no })
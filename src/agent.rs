@@ -0,0 +1,652 @@
+// Copyright 2017 astonbitecode
+// This file is part of rust-keylock password manager.
+//
+// rust-keylock is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// rust-keylock is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with rust-keylock.  If not, see <http://www.gnu.org/licenses/>.
+//! The agent mode: `rust-keylock agent`.
+//!
+//! Like the ssh-agent, the agent keeps the unlocked Entries in memory and serves them over a Unix domain socket, so that
+//! the non-interactive modes do not need to prompt for the password. Only processes of the same User may connect.
+//!
+//! The agent locks, forgetting the Entries and the password, when the `session_timeout_seconds` pass after the last
+//! request, when it receives SIGHUP, or when a client asks for it. A client may unlock it again. The clients get the
+//! Entries they ask for, but never the password of the passwords file.
+//!
+//! Each connection carries one request and one response, as single lines of json, and is served by its own thread.
+use std::env;
+use std::fs;
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::os::unix::io::AsRawFd;
+use std::os::unix::net::{UnixListener, UnixStream};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::thread;
+use std::time::Duration;
+
+use log::{debug, error, info, warn};
+use rust_keylock::Entry;
+use serde::{Deserialize, Serialize};
+use zeroize::{Zeroize, Zeroizing};
+
+use crate::cli::ErrorKind;
+use crate::git_credential::{self, Operation};
+use crate::loader::{self, Loaded};
+use crate::lockout::UnlockFailures;
+use crate::settings::Settings;
+
+/// The environment variable that overrides the location of the socket
+pub(crate) const SOCKET_ENV_VAR: &str = "RUST_KEYLOCK_AGENT_SOCK";
+const SOCKET_FILENAME: &str = "rust-keylock-agent.sock";
+/// The maximum size of a request, in bytes
+const MAX_REQUEST_SIZE: u64 = 64 * 1024;
+const IO_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Held while the agent reads or writes the passwords file, so that the unlocks are attempted one at a time
+static LOADING: Mutex<()> = Mutex::new(());
+
+#[derive(Debug, PartialEq, Eq, Clone, Serialize, Deserialize)]
+#[serde(tag = "command", rename_all = "lowercase")]
+pub(crate) enum Request {
+    Status,
+    /// The Entries whose name contains the filter, without their passwords
+    Ls { filter: String },
+    /// The Entry with the given name and, optionally, username
+    Get { name: String, user: Option<String> },
+    Unlock { password: String, number: usize },
+    Lock,
+    /// Loads the Entries again, after another mode changed the passwords file
+    Reload,
+    /// Stores or erases a git credential, given as the `key=value` lines of git
+    Credential { operation: String, credential: String },
+}
+
+impl Drop for Request {
+    fn drop(&mut self) {
+        match self {
+            Request::Unlock { password, .. } => password.zeroize(),
+            Request::Credential { credential, .. } => credential.zeroize(),
+            _ => {}
+        }
+    }
+}
+
+#[derive(Debug, Default, PartialEq, Eq, Clone, Serialize, Deserialize)]
+pub(crate) struct Response {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) error: Option<String>,
+    /// The kind of the error, so that the clients can tell the errors apart
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) code: Option<ErrorKind>,
+    #[serde(default)]
+    pub(crate) locked: bool,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub(crate) entries: Vec<AgentEntry>,
+}
+
+impl Response {
+    fn error(kind: ErrorKind, message: &str, locked: bool) -> Response {
+        Response { error: Some(message.to_string()), code: Some(kind), locked, ..Default::default() }
+    }
+}
+
+#[derive(Debug, Default, PartialEq, Eq, Clone, Serialize, Deserialize)]
+pub(crate) struct AgentEntry {
+    pub(crate) name: String,
+    pub(crate) url: String,
+    pub(crate) user: String,
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub(crate) pass: String,
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub(crate) desc: String,
+}
+
+impl AgentEntry {
+    fn new(entry: &Entry, with_secrets: bool) -> AgentEntry {
+        AgentEntry {
+            name: entry.name.clone(),
+            url: entry.url.clone(),
+            user: entry.user.clone(),
+            pass: if with_secrets { entry.pass.clone() } else { String::new() },
+            desc: if with_secrets { entry.desc.clone() } else { String::new() },
+        }
+    }
+}
+
+impl Drop for AgentEntry {
+    fn drop(&mut self) {
+        self.pass.zeroize();
+    }
+}
+
+/// How the agent slows down guessing the master password, as configured in the settings
+#[derive(Debug, Clone, Copy)]
+struct UnlockLimits {
+    backoff_seconds: u64,
+    max_attempts: u32,
+}
+
+/// What the agent keeps in memory
+#[derive(Default)]
+struct AgentState {
+    key: Option<(Zeroizing<String>, usize)>,
+    entries: Vec<Entry>,
+    // When the agent was unlocked or used for the last time, in seconds since the epoch
+    last_used_at: Option<i64>,
+    // The unlocks that the clients attempted and that failed, since the agent started
+    failed_unlocks: u32,
+}
+
+impl AgentState {
+    fn is_locked(&self) -> bool {
+        self.key.is_none()
+    }
+
+    fn unlock(&mut self, password: &str, number: usize, entries: Vec<Entry>, now: i64) {
+        self.key = Some((Zeroizing::new(password.to_string()), number));
+        self.entries = entries;
+        self.last_used_at = Some(now);
+    }
+
+    fn lock(&mut self) {
+        *self = AgentState { failed_unlocks: self.failed_unlocks, ..AgentState::default() };
+    }
+
+    fn is_expired(&self, now: i64, timeout_seconds: u64) -> bool {
+        match self.last_used_at {
+            Some(last_used_at) => now - last_used_at >= timeout_seconds as i64,
+            None => false,
+        }
+    }
+
+    /// Serves the requests that do not load the passwords file
+    fn handle(&mut self, request: &Request, now: i64) -> Response {
+        if !self.is_locked() && matches!(request, Request::Ls { .. } | Request::Get { .. }) {
+            // The timeout runs from the last request that uses the Entries
+            self.last_used_at = Some(now);
+        }
+        match request {
+            Request::Status => Response { locked: self.is_locked(), ..Default::default() },
+            Request::Lock => {
+                info!("Locking the agent, as a client asked for it");
+                self.lock();
+                Response { locked: true, ..Default::default() }
+            }
+            // They load the passwords file without holding the state
+            Request::Unlock { .. } | Request::Reload | Request::Credential { .. } => {
+                Response::error(ErrorKind::Other, "The request cannot be served here", self.is_locked())
+            }
+            _ if self.is_locked() => locked_response(),
+            Request::Ls { filter } => {
+                let filter = filter.to_lowercase();
+                let entries = self.entries.iter()
+                    .filter(|entry| entry.name.to_lowercase().contains(&filter))
+                    .map(|entry| AgentEntry::new(entry, false))
+                    .collect();
+                Response { entries, ..Default::default() }
+            }
+            Request::Get { name, user } => {
+                match loader::find_entry(&self.entries, name, user.as_deref()) {
+                    Some(entry) => Response { entries: vec![AgentEntry::new(entry, true)], ..Default::default() },
+                    None => Response::error(ErrorKind::NotFound, &format!("No Entry with name '{}' was found", name), false),
+                }
+            }
+        }
+    }
+
+    /// Refuses an unlock while the delay after the last failed one runs, and after too many failed ones
+    fn check_unlock(&self, failures: &UnlockFailures, limits: UnlockLimits, now: i64) -> Result<(), Response> {
+        if limits.max_attempts > 0 && self.failed_unlocks >= limits.max_attempts {
+            return Err(Response::error(ErrorKind::Unlock, "Too many unlocks failed. Please restart the agent.", self.is_locked()));
+        }
+        match failures.remaining_wait(limits.backoff_seconds, now) {
+            0 => Ok(()),
+            wait => Err(Response::error(ErrorKind::Unlock, &format!("Please wait {} seconds before unlocking again", wait), self.is_locked())),
+        }
+    }
+
+    /// Unlocks with the Entries that were loaded with the password and the number, and counts the failed unlocks
+    fn unlocked(&mut self, password: &str, number: usize, loaded: Loaded, failures: &mut UnlockFailures, now: i64) -> Response {
+        match loaded {
+            Ok(entries) => {
+                info!("The agent was unlocked with {} Entries", entries.len());
+                let failed = failures.take();
+                if failed > 0 {
+                    warn!("{} unlocks failed before this one", failed);
+                }
+                self.unlock(password, number, entries, now);
+                Response::default()
+            }
+            Err(error) => {
+                failures.record(now);
+                self.failed_unlocks = self.failed_unlocks.saturating_add(1);
+                Response::error(ErrorKind::Unlock, &error, self.is_locked())
+            }
+        }
+    }
+
+    /// Swaps in the Entries that were loaded again with the key, unless the agent was locked meanwhile
+    fn reloaded(&mut self, key: &(Zeroizing<String>, usize), loaded: Loaded, now: i64) -> Response {
+        if self.key.as_ref() != Some(key) {
+            return locked_response();
+        }
+        match loaded {
+            Ok(entries) => {
+                self.entries = entries;
+                self.last_used_at = Some(now);
+                Response::default()
+            }
+            Err(error) => {
+                warn!("Could not reload the Entries. Locking the agent. The error was: {}", error);
+                self.lock();
+                Response::error(ErrorKind::Unlock, &error, true)
+            }
+        }
+    }
+}
+
+fn locked_response() -> Response {
+    Response::error(ErrorKind::Locked, "The agent is locked. Please run 'rust-keylock unlock'.", true)
+}
+
+/// Returns the location of the socket of the agent
+pub(crate) fn socket_path() -> PathBuf {
+    match env::var_os(SOCKET_ENV_VAR) {
+        Some(path) if !path.is_empty() => PathBuf::from(path),
+        _ => {
+            let mut path = runtime_dir();
+            path.push(SOCKET_FILENAME);
+            path
+        }
+    }
+}
+
+/// The directory for the files that should not outlive the login of the User
+pub(crate) fn runtime_dir() -> PathBuf {
+    env::var_os("XDG_RUNTIME_DIR")
+        .map(PathBuf::from)
+        .filter(|dir| dir.is_dir())
+        .unwrap_or_else(rust_keylock::default_rustkeylock_location)
+}
+
+/// Returns the user ID of the process on the other side of the socket
+#[cfg(any(target_os = "linux", target_os = "android"))]
+pub(crate) fn peer_uid(stream: &UnixStream) -> io::Result<u32> {
+    let mut credentials = libc::ucred { pid: 0, uid: 0, gid: 0 };
+    let mut length = std::mem::size_of::<libc::ucred>() as libc::socklen_t;
+    let res = unsafe {
+        libc::getsockopt(stream.as_raw_fd(),
+                         libc::SOL_SOCKET,
+                         libc::SO_PEERCRED,
+                         &mut credentials as *mut libc::ucred as *mut libc::c_void,
+                         &mut length)
+    };
+    if res == 0 {
+        Ok(credentials.uid)
+    } else {
+        Err(io::Error::last_os_error())
+    }
+}
+
+/// Returns the user ID of the process on the other side of the socket
+#[cfg(not(any(target_os = "linux", target_os = "android")))]
+pub(crate) fn peer_uid(stream: &UnixStream) -> io::Result<u32> {
+    let mut uid: libc::uid_t = 0;
+    let mut gid: libc::gid_t = 0;
+    if unsafe { libc::getpeereid(stream.as_raw_fd(), &mut uid, &mut gid) } == 0 {
+        Ok(uid)
+    } else {
+        Err(io::Error::last_os_error())
+    }
+}
+
+/// Reads a request line from the stream
+pub(crate) fn read_message<T: for<'de> Deserialize<'de>>(stream: &UnixStream) -> Result<T, String> {
+    let mut line = Zeroizing::new(String::new());
+    BufReader::new(stream.take(MAX_REQUEST_SIZE)).read_line(&mut line).map_err(|error| error.to_string())?;
+    serde_json::from_str(&line).map_err(|error| format!("Invalid message: {}", error))
+}
+
+/// Writes a message as a single line to the stream
+pub(crate) fn write_message<T: Serialize>(mut stream: &UnixStream, message: &T) -> Result<(), String> {
+    let mut line = Zeroizing::new(serde_json::to_string(message).map_err(|error| error.to_string())?);
+    line.push('\n');
+    stream.write_all(line.as_bytes()).map_err(|error| error.to_string())
+}
+
+/// Starts the agent. It prompts for the password on the terminal and then serves the clients until it is killed.
+pub(crate) fn run(settings: &Settings) -> Result<(), String> {
+    if settings.session_timeout_seconds == 0 {
+        return Err("The agent is disabled, because session_timeout_seconds is zero".to_string());
+    }
+    let path = socket_path();
+    let listener = bind(&path)?;
+
+    let mut state = AgentState::default();
+    let unlocked = loader::ask_key().and_then(|(password, number)| {
+        let entries = loader::load_entries(&password, number)?;
+        state.unlock(&password, number, entries, chrono::Utc::now().timestamp());
+        Ok(())
+    });
+    if let Err(error) = unlocked {
+        let _ = fs::remove_file(&path);
+        return Err(error);
+    }
+
+    println!("{}={}; export {};", SOCKET_ENV_VAR, path.display(), SOCKET_ENV_VAR);
+    println!("echo Agent listening with {} Entries, locking after {} seconds;", state.entries.len(), settings.session_timeout_seconds);
+    let limits = UnlockLimits { backoff_seconds: settings.unlock_backoff_seconds, max_attempts: settings.max_unlock_attempts };
+    serve(listener, Arc::new(Mutex::new(state)), settings.session_timeout_seconds, limits)
+}
+
+/// Binds the socket, so that only the User can access it
+pub(crate) fn bind(path: &Path) -> Result<UnixListener, String> {
+    if path.exists() {
+        if UnixStream::connect(path).is_ok() {
+            return Err(format!("An agent is already listening on {}", path.display()));
+        }
+        // A socket left behind by an agent that was killed
+        fs::remove_file(path).map_err(|error| format!("Could not remove the stale socket {}: {}", path.display(), error))?;
+    }
+    let previous_umask = unsafe { libc::umask(0o177) };
+    let res = UnixListener::bind(path);
+    unsafe { libc::umask(previous_umask) };
+    res.map_err(|error| format!("Could not listen on {}: {}", path.display(), error))
+}
+
+fn serve(listener: UnixListener, state: Arc<Mutex<AgentState>>, timeout_seconds: u64, limits: UnlockLimits) -> Result<(), String> {
+    let hangup = Arc::new(AtomicBool::new(false));
+    let hangup_in_handler = hangup.clone();
+    unsafe { signal_hook_registry::register(libc::SIGHUP, move || hangup_in_handler.store(true, Ordering::SeqCst)) }
+        .map_err(|error| format!("Could not handle SIGHUP: {}", error))?;
+    spawn_locker(state.clone(), hangup, timeout_seconds);
+
+    let own_uid = unsafe { libc::getuid() };
+    for stream in listener.incoming() {
+        let stream = match stream {
+            Ok(stream) => stream,
+            Err(error) => {
+                error!("Could not accept a connection: {}", error);
+                continue;
+            }
+        };
+        match peer_uid(&stream) {
+            Ok(uid) if uid == own_uid => {
+                // A client that stalls must not block the others
+                let state = state.clone();
+                thread::spawn(move || handle_connection(&stream, &state, limits));
+            }
+            Ok(uid) => warn!("Refusing a connection from user ID {}", uid),
+            Err(error) => warn!("Refusing a connection, as its user ID is unknown: {}", error),
+        }
+    }
+    Ok(())
+}
+
+/// Locks the agent on timeout and on SIGHUP
+fn spawn_locker(state: Arc<Mutex<AgentState>>, hangup: Arc<AtomicBool>, timeout_seconds: u64) {
+    thread::spawn(move || loop {
+        thread::sleep(Duration::from_millis(500));
+        let hung_up = hangup.swap(false, Ordering::SeqCst);
+        if let Some(mut state) = lock_state(&state) {
+            if hung_up && !state.is_locked() {
+                info!("Locking the agent because of SIGHUP");
+                state.lock();
+            } else if state.is_expired(chrono::Utc::now().timestamp(), timeout_seconds) {
+                info!("Locking the agent because of timeout");
+                state.lock();
+            }
+        }
+    });
+}
+
+fn handle_connection(stream: &UnixStream, state: &Mutex<AgentState>, limits: UnlockLimits) {
+    let _ = stream.set_read_timeout(Some(IO_TIMEOUT));
+    let _ = stream.set_write_timeout(Some(IO_TIMEOUT));
+    let response = match read_message::<Request>(stream) {
+        Ok(request) => {
+            debug!("Agent request {:?}", std::mem::discriminant(&request));
+            respond(state, &request, &loader::load_entries, limits)
+        }
+        Err(error) => Response::error(ErrorKind::Usage, &error, false),
+    };
+    if let Err(error) = write_message(stream, &response) {
+        warn!("Could not respond to a client: {}", error);
+    }
+}
+
+/// Serves a request. Loading the passwords file takes a while, so the requests that do it do not hold the state
+/// meanwhile: the other clients are served, and the loaded Entries are swapped in afterwards.
+fn respond(state: &Mutex<AgentState>, request: &Request, load: &dyn Fn(&str, usize) -> Loaded, limits: UnlockLimits) -> Response {
+    match request {
+        Request::Unlock { password, number } => unlock(state, password, *number, load, limits),
+        Request::Reload => {
+            let key = match current_key(state) {
+                Ok(key) => key,
+                Err(response) => return response,
+            };
+            let loaded = {
+                let _loading = lock_state(&LOADING);
+                load(&key.0, key.1)
+            };
+            with_state(state, |state| state.reloaded(&key, loaded, chrono::Utc::now().timestamp()))
+        }
+        Request::Credential { operation, credential } => {
+            match current_key(state) {
+                Ok(key) => store_credential(state, key, operation, credential, load),
+                Err(response) => response,
+            }
+        }
+        _ => with_state(state, |state| state.handle(request, chrono::Utc::now().timestamp())),
+    }
+}
+
+/// Unlocks the agent, unless the unlocks that failed before make it wait
+fn unlock(state: &Mutex<AgentState>, password: &str, number: usize, load: &dyn Fn(&str, usize) -> Loaded, limits: UnlockLimits) -> Response {
+    let _loading = lock_state(&LOADING);
+    let mut failures = UnlockFailures::load();
+    let checked = match lock_state(state) {
+        Some(state) => state.check_unlock(&failures, limits, chrono::Utc::now().timestamp()),
+        None => Err(state_error()),
+    };
+    if let Err(response) = checked {
+        return response;
+    }
+    let before = failures.clone();
+    let loaded = load(password, number);
+    let response = with_state(state, |state| state.unlocked(password, number, loaded, &mut failures, chrono::Utc::now().timestamp()));
+    if failures != before {
+        failures.save();
+    }
+    response
+}
+
+/// Stores or erases a git credential with the key of the agent and loads the Entries again
+fn store_credential(state: &Mutex<AgentState>,
+                    key: (Zeroizing<String>, usize),
+                    operation: &str,
+                    credential: &str,
+                    load: &dyn Fn(&str, usize) -> Loaded) -> Response {
+    let operation = match Operation::parse(operation) {
+        Some(operation) if operation != Operation::Get => operation,
+        _ => return Response::error(ErrorKind::Usage, &format!("Cannot {} a credential", operation), false),
+    };
+    let loaded = {
+        let _loading = lock_state(&LOADING);
+        if let Err(error) = git_credential::apply(operation, credential, &key.0, key.1) {
+            return Response::error(error.kind, &error.error_message, false);
+        }
+        load(&key.0, key.1)
+    };
+    with_state(state, |state| state.reloaded(&key, loaded, chrono::Utc::now().timestamp()))
+}
+
+/// Returns the key of the agent, if it is unlocked
+fn current_key(state: &Mutex<AgentState>) -> Result<(Zeroizing<String>, usize), Response> {
+    match lock_state(state) {
+        Some(state) => state.key.clone().ok_or_else(locked_response),
+        None => Err(state_error()),
+    }
+}
+
+fn with_state<F>(state: &Mutex<AgentState>, f: F) -> Response where F: FnOnce(&mut AgentState) -> Response {
+    match lock_state(state) {
+        Some(mut state) => f(&mut state),
+        None => state_error(),
+    }
+}
+
+fn state_error() -> Response {
+    Response::error(ErrorKind::Other, "Could not access the state of the agent", true)
+}
+
+fn lock_state<T>(state: &Mutex<T>) -> Option<MutexGuard<'_, T>> {
+    match state.lock() {
+        Ok(guard) => Some(guard),
+        Err(error) => {
+            error!("Could not access the state of the agent: {:?}", error);
+            None
+        }
+    }
+}
+
+#[cfg(test)]
+mod test_agent {
+    use std::os::unix::net::UnixStream;
+
+    use rust_keylock::{Entry, EntryMeta};
+
+    use crate::cli::ErrorKind;
+
+    use std::sync::Mutex;
+
+    use crate::lockout::UnlockFailures;
+
+    use super::{AgentState, Request, Response, UnlockLimits};
+
+    fn entry(name: &str, user: &str, pass: &str) -> Entry {
+        Entry::new(name.to_string(), "https://example.com".to_string(), user.to_string(), pass.to_string(), "desc".to_string(), EntryMeta::default())
+    }
+
+    fn load(password: &str, _number: usize) -> crate::loader::Loaded {
+        if password == "password" {
+            Ok(vec![entry("GitHub", "aston", "pass1"), entry("github", "other", "pass2"), entry("mail", "aston", "pass3")])
+        } else {
+            Err("Wrong password or number".to_string())
+        }
+    }
+
+    fn limits() -> UnlockLimits {
+        UnlockLimits { backoff_seconds: 2, max_attempts: 3 }
+    }
+
+    #[test]
+    fn requests_and_locking() {
+        let mut state = AgentState::default();
+        let mut failures = UnlockFailures::default();
+        assert!(state.handle(&Request::Ls { filter: "".to_string() }, 0).locked);
+        let response = state.unlocked("wrong", 33, load("wrong", 33), &mut failures, 0);
+        assert!(response.code == Some(ErrorKind::Unlock) && response.locked);
+
+        assert!(state.unlocked("password", 33, load("password", 33), &mut failures, 100) == Response::default());
+        let response = state.handle(&Request::Ls { filter: "GIT".to_string() }, 110);
+        assert!(response.entries.len() == 2);
+        assert!(response.entries.iter().all(|entry| entry.pass.is_empty()));
+
+        let response = state.handle(&Request::Get { name: "github".to_string(), user: None }, 120);
+        assert!(response.entries[0].pass == "pass2");
+        let response = state.handle(&Request::Get { name: "GITHUB".to_string(), user: Some("aston".to_string()) }, 130);
+        assert!(response.entries[0].pass == "pass1");
+        assert!(state.handle(&Request::Get { name: "missing".to_string(), user: None }, 140).code == Some(ErrorKind::NotFound));
+        assert!(!serde_json::to_string(&state.handle(&Request::Status, 150)).unwrap().contains("password"));
+
+        // The timeout runs from the last request that used the Entries
+        assert!(!state.is_expired(199, 60));
+        assert!(state.is_expired(200, 60));
+        assert!(state.handle(&Request::Lock, 0).locked);
+        assert!(state.entries.is_empty());
+        assert!(!state.is_expired(1000, 60));
+    }
+
+    #[test]
+    fn failed_unlocks() {
+        let mut state = AgentState::default();
+        let mut failures = UnlockFailures::default();
+        assert!(state.check_unlock(&failures, limits(), 100).is_ok());
+        state.unlocked("wrong", 33, load("wrong", 33), &mut failures, 100);
+        state.unlocked("wrong", 33, load("wrong", 33), &mut failures, 100);
+        assert!(failures.count == 2);
+        // The delay doubles with every failure
+        assert!(state.check_unlock(&failures, limits(), 103).unwrap_err().code == Some(ErrorKind::Unlock));
+        assert!(state.check_unlock(&failures, limits(), 104).is_ok());
+
+        assert!(state.unlocked("password", 33, load("password", 33), &mut failures, 104) == Response::default());
+        assert!(failures == UnlockFailures::default());
+        state.lock();
+        state.unlocked("wrong", 33, load("wrong", 33), &mut failures, 200);
+        // Three unlocks failed since the agent started
+        assert!(state.check_unlock(&UnlockFailures::default(), limits(), 1000).is_err());
+        assert!(state.check_unlock(&UnlockFailures::default(), UnlockLimits { backoff_seconds: 0, max_attempts: 0 }, 1000).is_ok());
+    }
+
+    #[test]
+    fn loading_does_not_hold_the_state() {
+        let state = Mutex::new(AgentState::default());
+        state.lock().unwrap().unlock("password", 33, Vec::new(), 100);
+        let load_unheld = |password: &str, number: usize| {
+            // Another client is served meanwhile
+            assert!(state.try_lock().is_ok());
+            load(password, number)
+        };
+        assert!(super::respond(&state, &Request::Reload, &load_unheld, limits()) == Response::default());
+        assert!(state.lock().unwrap().entries.len() == 3);
+
+        // Locked while reloading
+        let load_and_lock = |password: &str, number: usize| {
+            state.lock().unwrap().lock();
+            load(password, number)
+        };
+        assert!(super::respond(&state, &Request::Reload, &load_and_lock, limits()).locked);
+        assert!(state.lock().unwrap().entries.is_empty());
+    }
+
+    #[test]
+    fn credentials_need_the_unlocked_agent() {
+        let state = Mutex::new(AgentState::default());
+        let request = Request::Credential { operation: "store".to_string(), credential: "protocol=https\nhost=example.com\n".to_string() };
+        assert!(super::respond(&state, &request, &load, limits()).code == Some(ErrorKind::Locked));
+        let key = (zeroize::Zeroizing::new("password".to_string()), 33);
+        assert!(super::store_credential(&state, key, "get", "protocol=https\nhost=example.com\n", &load).code == Some(ErrorKind::Usage));
+
+        // The Entries that were loaded with another key are not swapped in
+        let mut state = AgentState::default();
+        state.unlock("password", 33, load("password", 33).unwrap(), 100);
+        let other_key = (zeroize::Zeroizing::new("other".to_string()), 33);
+        assert!(state.reloaded(&other_key, Ok(Vec::new()), 110).locked);
+        assert!(state.entries.len() == 3);
+    }
+
+    #[test]
+    fn messages_over_socket() {
+        let (client, server) = UnixStream::pair().unwrap();
+        assert!(super::peer_uid(&server).unwrap() == unsafe { libc::getuid() });
+        let request = Request::Get { name: "mail".to_string(), user: None };
+        super::write_message(&client, &request).unwrap();
+        assert!(super::read_message::<Request>(&server).unwrap() == request);
+
+        let line = serde_json::to_string(&Request::Ls { filter: "a".to_string() }).unwrap();
+        assert!(line == r#"{"command":"ls","filter":"a"}"#);
+        assert!(serde_json::from_str::<Request>(r#"{"command":"unknown"}"#).is_err());
+        assert!(serde_json::from_str::<Request>(r#"{"command":"key"}"#).is_err());
+    }
+}
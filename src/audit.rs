@@ -0,0 +1,316 @@
+// Copyright 2017 astonbitecode
+// This file is part of rust-keylock password manager.
+//
+// rust-keylock is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// rust-keylock is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with rust-keylock.  If not, see <http://www.gnu.org/licenses/>.
+//! The audit log of the operations on the passwords file.
+//!
+//! Every record is a JSON line that holds the hash of the previous record, so changing or removing a record breaks the
+//! chain of the records after it. The records name the Entries, the files and the settings, but never hold their secrets.
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+#[cfg(unix)]
+use std::os::unix::fs::OpenOptionsExt;
+use std::path::{Path, PathBuf};
+
+use log::error;
+use serde::{Deserialize, Serialize};
+
+use crate::cli::{self, CliError, ErrorKind, Options};
+use crate::extras;
+use crate::i18n;
+use crate::sanitize;
+
+/// Where the audit log is kept
+const AUDIT_FILENAME: &str = ".shell_audit";
+/// The previous hash of the first record
+const GENESIS_HASH: &str = "0000000000000000000000000000000000000000000000000000000000000000";
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub(crate) enum AuditEvent {
+    Unlocked,
+    UnlockFailed,
+    MasterPasswordChanged,
+    EntryViewed,
+    EntryCopied,
+    EntryAdded,
+    EntryEdited,
+    EntryDeleted,
+    Exported,
+    Imported,
+    ConfigurationChanged,
+}
+
+impl AuditEvent {
+    pub(crate) fn text(&self) -> String {
+        let id = match self {
+            AuditEvent::Unlocked => "audit-event-unlocked",
+            AuditEvent::UnlockFailed => "audit-event-unlock-failed",
+            AuditEvent::MasterPasswordChanged => "audit-event-master-password-changed",
+            AuditEvent::EntryViewed => "audit-event-entry-viewed",
+            AuditEvent::EntryCopied => "audit-event-entry-copied",
+            AuditEvent::EntryAdded => "audit-event-entry-added",
+            AuditEvent::EntryEdited => "audit-event-entry-edited",
+            AuditEvent::EntryDeleted => "audit-event-entry-deleted",
+            AuditEvent::Exported => "audit-event-exported",
+            AuditEvent::Imported => "audit-event-imported",
+            AuditEvent::ConfigurationChanged => "audit-event-configuration-changed",
+        };
+        i18n::tr(id)
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Serialize, Deserialize)]
+pub(crate) struct AuditRecord {
+    pub(crate) seq: u64,
+    pub(crate) at: i64,
+    pub(crate) event: AuditEvent,
+    /// What the event concerns, like the name of an Entry
+    pub(crate) detail: String,
+    /// The hash of the previous record
+    pub(crate) prev: String,
+    pub(crate) hash: String,
+}
+
+impl AuditRecord {
+    fn new(seq: u64, at: i64, event: AuditEvent, detail: &str, prev: &str) -> AuditRecord {
+        let mut record = AuditRecord { seq, at, event, detail: detail.to_string(), prev: prev.to_string(), hash: String::new() };
+        record.hash = record.expected_hash();
+        record
+    }
+
+    /// The record as a line of text, with the control characters of the detail escaped
+    pub(crate) fn text(&self) -> String {
+        let mut text = format!("{} #{} {}", extras::format_timestamp(self.at), self.seq, self.event.text());
+        if !self.detail.is_empty() {
+            text.push_str(&format!(": {}", sanitize::line(&self.detail)));
+        }
+        text
+    }
+
+    fn expected_hash(&self) -> String {
+        let contents = serde_json::to_string(&(self.seq, self.at, self.event, &self.detail, &self.prev)).unwrap_or_default();
+        hash(contents.as_bytes())
+    }
+}
+
+fn hash(contents: &[u8]) -> String {
+    openssl::sha::sha256(contents).iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+/// The audit log, in a file that is only appended to
+pub(crate) struct AuditLog {
+    path: PathBuf,
+}
+
+impl Default for AuditLog {
+    fn default() -> AuditLog {
+        AuditLog::new(rust_keylock::default_rustkeylock_location().join(AUDIT_FILENAME))
+    }
+}
+
+impl AuditLog {
+    pub(crate) fn new(path: PathBuf) -> AuditLog {
+        AuditLog { path }
+    }
+
+    pub(crate) fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Appends a record, chained to the last one
+    pub(crate) fn append(&self, event: AuditEvent, detail: &str, at: i64) -> Result<AuditRecord, String> {
+        let contents = match fs::read_to_string(&self.path) {
+            Ok(contents) => contents,
+            Err(error) if error.kind() == std::io::ErrorKind::NotFound => String::new(),
+            Err(error) => return Err(error.to_string()),
+        };
+        let record = match contents.lines().rev().find(|line| !line.trim().is_empty()) {
+            None => AuditRecord::new(1, at, event, detail, GENESIS_HASH),
+            Some(line) => match serde_json::from_str::<AuditRecord>(line) {
+                Ok(last) => AuditRecord::new(last.seq + 1, at, event, detail, &last.hash),
+                // The chain is broken already. Chain to the line as it is, so that the verification reports where.
+                Err(_) => AuditRecord::new(contents.lines().count() as u64 + 1, at, event, detail, &hash(line.as_bytes())),
+            },
+        };
+        let line = serde_json::to_string(&record).map_err(|error| error.to_string())?;
+        let mut options = OpenOptions::new();
+        options.append(true).create(true);
+        #[cfg(unix)]
+        options.mode(0o600);
+        let mut file = options.open(&self.path).map_err(|error| error.to_string())?;
+        writeln!(file, "{}", line).map_err(|error| error.to_string())?;
+        Ok(record)
+    }
+
+    /// The records that can be parsed, oldest first
+    pub(crate) fn records(&self) -> Vec<AuditRecord> {
+        fs::read_to_string(&self.path)
+            .map(|contents| contents.lines().filter_map(|line| serde_json::from_str(line).ok()).collect())
+            .unwrap_or_default()
+    }
+
+    /// Checks the chain of the records. Returns the number of records, or what is wrong with the first broken one.
+    pub(crate) fn verify(&self) -> Result<usize, String> {
+        let contents = match fs::read_to_string(&self.path) {
+            Ok(contents) => contents,
+            Err(error) if error.kind() == std::io::ErrorKind::NotFound => return Ok(0),
+            Err(error) => return Err(error.to_string()),
+        };
+        let mut prev = GENESIS_HASH.to_string();
+        let mut count = 0;
+        for (index, line) in contents.lines().enumerate().filter(|(_, line)| !line.trim().is_empty()) {
+            let line_number = (index + 1).to_string();
+            let record: AuditRecord = serde_json::from_str(line)
+                .map_err(|_| i18n::tr_with("audit-invalid-record", &[("line", &line_number)]))?;
+            if record.prev != prev {
+                return Err(i18n::tr_with("audit-broken-chain", &[("line", &line_number)]));
+            }
+            if record.hash != record.expected_hash() {
+                return Err(i18n::tr_with("audit-modified-record", &[("line", &line_number)]));
+            }
+            prev = record.hash;
+            count += 1;
+        }
+        Ok(count)
+    }
+}
+
+/// Records an event in the audit log. The operation goes on if it cannot be recorded.
+pub(crate) fn record(event: AuditEvent, detail: &str) {
+    if let Err(error) = AuditLog::default().append(event, detail, chrono::Utc::now().timestamp()) {
+        error!("Could not record the event {:?} in the audit log: {}", event, error);
+    }
+}
+
+/// Runs `rust-keylock audit verify`
+pub(crate) fn run(args: &[String], options: &Options) -> Result<(), CliError> {
+    let log = AuditLog::default();
+    match args.first().map(String::as_str) {
+        Some("verify") => println!("{}", verify_output(&log, options)?),
+        Some("show") => println!("{}", show_output(&log, options)),
+        _ => return Err(CliError::new(ErrorKind::Usage, "Usage: rust-keylock audit verify|show")),
+    }
+    Ok(())
+}
+
+#[derive(Serialize)]
+struct JsonVerified<'a> {
+    path: &'a Path,
+    records: usize,
+}
+
+#[derive(Serialize)]
+struct JsonRecords {
+    records: Vec<AuditRecord>,
+}
+
+/// Verifies the chain of the records. A broken chain is an error.
+fn verify_output(log: &AuditLog, options: &Options) -> Result<String, CliError> {
+    let count = log.verify().map_err(|error| CliError::new(ErrorKind::Other, &error))?;
+    Ok(if options.json {
+        cli::to_json(JsonVerified { path: log.path(), records: count })
+    } else {
+        i18n::tr_with("audit-verified", &[("count", &count.to_string()), ("path", &log.path().display().to_string())])
+    })
+}
+
+/// Presents the records, the oldest first
+fn show_output(log: &AuditLog, options: &Options) -> String {
+    let records = log.records();
+    if options.json {
+        cli::to_json(JsonRecords { records })
+    } else if records.is_empty() {
+        i18n::tr("audit-empty")
+    } else {
+        records.iter().map(AuditRecord::text).collect::<Vec<_>>().join("\n")
+    }
+}
+
+#[cfg(test)]
+mod test_audit {
+    use std::fs;
+
+    use crate::cli::Options;
+
+    use super::{AuditEvent, AuditLog, AuditRecord};
+
+    fn audit_log(name: &str) -> AuditLog {
+        let path = std::env::temp_dir().join(format!("rust-keylock-shell-test-audit-{}-{}", name, std::process::id()));
+        let _ = fs::remove_file(&path);
+        AuditLog::new(path)
+    }
+
+    #[test]
+    fn append_and_verify() {
+        let log = audit_log("append");
+        assert!(log.verify() == Ok(0));
+        let first = log.append(AuditEvent::Unlocked, "", 10).unwrap();
+        let second = log.append(AuditEvent::EntryViewed, "mail", 11).unwrap();
+        assert!(first.seq == 1 && second.seq == 2);
+        assert!(second.prev == first.hash);
+        assert!(log.records() == vec![first, second]);
+        assert!(log.verify() == Ok(2));
+        let _ = fs::remove_file(log.path());
+    }
+
+    #[test]
+    fn tampering_is_detected() {
+        let log = audit_log("tampering");
+        log.append(AuditEvent::EntryViewed, "mail", 10).unwrap();
+        log.append(AuditEvent::EntryDeleted, "bank", 11).unwrap();
+        log.append(AuditEvent::Exported, "/tmp/export", 12).unwrap();
+        let contents = fs::read_to_string(log.path()).unwrap();
+
+        // A changed detail
+        fs::write(log.path(), contents.replace("\"bank\"", "\"shop\"")).unwrap();
+        assert!(log.verify().unwrap_err().contains('2'));
+
+        // A removed record
+        let lines: Vec<&str> = contents.lines().collect();
+        fs::write(log.path(), format!("{}\n{}\n", lines[0], lines[2])).unwrap();
+        assert!(log.verify().unwrap_err().contains('2'));
+
+        // A record that is rewritten with a hash of its own
+        let mut forged: AuditRecord = serde_json::from_str(lines[1]).unwrap();
+        forged.detail = "shop".to_string();
+        forged.hash = forged.expected_hash();
+        fs::write(log.path(), format!("{}\n{}\n{}\n", lines[0], serde_json::to_string(&forged).unwrap(), lines[2])).unwrap();
+        assert!(log.verify().unwrap_err().contains('3'));
+
+        // Records appended after a broken one do not repair the chain
+        fs::write(log.path(), format!("{}\nnot a record\n", lines[0])).unwrap();
+        log.append(AuditEvent::Unlocked, "", 13).unwrap();
+        assert!(log.verify().is_err());
+        let _ = fs::remove_file(log.path());
+    }
+
+    #[test]
+    fn json_output() {
+        let log = audit_log("json");
+        let json = Options { json: true, show_secrets: false };
+        log.append(AuditEvent::EntryViewed, "mail", 10).unwrap();
+        log.append(AuditEvent::EntryDeleted, "bank", 11).unwrap();
+        let verified: serde_json::Value = serde_json::from_str(&super::verify_output(&log, &json).unwrap()).unwrap();
+        assert!(verified["version"] == 1 && verified["records"] == 2);
+        let shown: serde_json::Value = serde_json::from_str(&super::show_output(&log, &json)).unwrap();
+        assert!(shown["records"][1]["event"] == "entry_deleted" && shown["records"][1]["detail"] == "bank");
+        assert!(super::show_output(&log, &Options::default()).lines().count() == 2);
+
+        let contents = fs::read_to_string(log.path()).unwrap();
+        fs::write(log.path(), contents.replace("bank", "shop")).unwrap();
+        assert!(super::verify_output(&log, &json).is_err());
+        let _ = fs::remove_file(log.path());
+    }
+}
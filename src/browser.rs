@@ -0,0 +1,178 @@
+// Copyright 2017 astonbitecode
+// This file is part of rust-keylock password manager.
+//
+// rust-keylock is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// rust-keylock is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with rust-keylock.  If not, see <http://www.gnu.org/licenses/>.
+use std::sync::Mutex;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use log::error;
+use url::Url;
+use zeroize::Zeroizing;
+
+/// Parses the URL of an Entry and checks that its scheme is one of the `allowed_schemes`
+pub(crate) fn validate_url(url: &str, allowed_schemes: &[String]) -> Result<Url, String> {
+    let parsed = Url::parse(url.trim()).map_err(|error| format!("'{}' is not a valid URL ({})", url, error))?;
+    if allowed_schemes.iter().any(|scheme| scheme.eq_ignore_ascii_case(parsed.scheme())) {
+        Ok(parsed)
+    } else {
+        Err(format!("URLs with scheme '{}' are not allowed to be opened. Allowed schemes: {}", parsed.scheme(), allowed_schemes.join(", ")))
+    }
+}
+
+pub(crate) fn open(url: &Url) -> Result<(), String> {
+    webbrowser::open(url.as_str()).map_err(|error| format!("Could not open the browser: {}", error))
+}
+
+pub(crate) fn copy_to_clipboard(content: &str) -> Result<(), String> {
+    terminal_clipboard::set_string(content).map_err(|error| format!("Could not copy: {}", error))
+}
+
+/// How often a delayed copy checks whether it was cancelled
+const COPY_POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// The password that is copied to the clipboard in the background, if any
+static DELAYED_COPY: Mutex<DelayedCopy> = Mutex::new(DelayedCopy { id: 0, state: CopyState::Idle, content: None });
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+enum CopyState {
+    Idle,
+    Waiting,
+    Copied,
+}
+
+struct DelayedCopy {
+    // Identifies the last copy that was started, so that the threads of older copies stop
+    id: u64,
+    state: CopyState,
+    content: Option<Zeroizing<String>>,
+}
+
+impl DelayedCopy {
+    /// Clears the clipboard, if it still holds the copied content
+    fn clear(&mut self) {
+        if let Some(content) = self.content.take() {
+            if self.state == CopyState::Copied && terminal_clipboard::get_string().map(|current| Zeroizing::new(current) == content).unwrap_or(false) {
+                if let Err(error) = copy_to_clipboard("") {
+                    error!("{}", error);
+                }
+            }
+        }
+        self.state = CopyState::Idle;
+    }
+}
+
+/// Copies the content to the clipboard after `delay`, in the background, and clears the clipboard `clear_after` the copy.
+///
+/// A copy that is started replaces the previous one.
+pub(crate) fn copy_to_clipboard_later(content: Zeroizing<String>, delay: Duration, clear_after: Duration) {
+    let id = match DELAYED_COPY.lock() {
+        Ok(mut copy) => {
+            copy.clear();
+            copy.id += 1;
+            copy.state = CopyState::Waiting;
+            copy.content = Some(content);
+            copy.id
+        }
+        Err(_) => return,
+    };
+    thread::spawn(move || {
+        if !wait_while(id, CopyState::Waiting, delay) {
+            return;
+        }
+        match DELAYED_COPY.lock() {
+            Ok(mut copy) if copy.id == id && copy.state == CopyState::Waiting => {
+                match copy.content.as_ref().map(|content| copy_to_clipboard(content)) {
+                    Some(Ok(_)) => copy.state = CopyState::Copied,
+                    Some(Err(error)) => {
+                        error!("{}", error);
+                        copy.clear();
+                        return;
+                    }
+                    None => return,
+                }
+            }
+            _ => return,
+        }
+        if wait_while(id, CopyState::Copied, clear_after) {
+            if let Ok(mut copy) = DELAYED_COPY.lock() {
+                if copy.id == id {
+                    copy.clear();
+                }
+            }
+        }
+    });
+}
+
+/// Waits for `duration`. Returns false if the copy `id` leaves the `state` meanwhile.
+fn wait_while(id: u64, state: CopyState, duration: Duration) -> bool {
+    let deadline = Instant::now() + duration;
+    loop {
+        match DELAYED_COPY.lock() {
+            Ok(copy) if copy.id == id && copy.state == state => {}
+            _ => return false,
+        }
+        let now = Instant::now();
+        if now >= deadline {
+            return true;
+        }
+        thread::sleep(COPY_POLL_INTERVAL.min(deadline - now));
+    }
+}
+
+/// Cancels a delayed copy that did not happen yet. A copied password is still cleared when its time comes.
+pub(crate) fn cancel_delayed_copy() {
+    if let Ok(mut copy) = DELAYED_COPY.lock() {
+        if copy.state == CopyState::Waiting {
+            copy.clear();
+        }
+    }
+}
+
+/// Cancels a delayed copy and clears the clipboard, if it holds a password that was copied
+pub(crate) fn clear_delayed_copy() {
+    if let Ok(mut copy) = DELAYED_COPY.lock() {
+        copy.clear();
+    }
+}
+
+#[cfg(test)]
+mod test_browser {
+    use std::time::Duration;
+
+    use zeroize::Zeroizing;
+
+    use super::{CopyState, DELAYED_COPY};
+
+    #[test]
+    fn cancel_delayed_copy() {
+        super::copy_to_clipboard_later(Zeroizing::new("secret".to_string()), Duration::from_secs(60), Duration::from_secs(60));
+        assert!(DELAYED_COPY.lock().unwrap().state == CopyState::Waiting);
+        super::cancel_delayed_copy();
+        let copy = DELAYED_COPY.lock().unwrap();
+        assert!(copy.state == CopyState::Idle);
+        assert!(copy.content.is_none());
+    }
+
+    #[test]
+    fn validate_url() {
+        let schemes = vec!["http".to_string(), "https".to_string()];
+        assert!(super::validate_url("https://example.com/login", &schemes).is_ok());
+        assert!(super::validate_url(" HTTP://example.com ", &schemes).is_ok());
+        assert!(super::validate_url("file:///etc/passwd", &schemes).is_err());
+        assert!(super::validate_url("javascript:alert(1)", &schemes).is_err());
+        assert!(super::validate_url("example.com", &schemes).is_err());
+        assert!(super::validate_url("ftp://example.com", &["ftp".to_string()]).is_ok());
+    }
+}
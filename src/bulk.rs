@@ -0,0 +1,277 @@
+// Copyright 2017 astonbitecode
+// This file is part of rust-keylock password manager.
+//
+// rust-keylock is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// rust-keylock is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with rust-keylock.  If not, see <http://www.gnu.org/licenses/>.
+use std::collections::VecDeque;
+
+use log::warn;
+use rust_keylock::{Entry, Menu, UserSelection};
+
+use crate::extras;
+use crate::i18n;
+
+/// Identifies an `Entry` without looking at its password.
+///
+/// The Entries that are passed to `Editor::show_entries` carry encrypted passwords and their indexes change after every
+/// mutation (the list is filtered and sorted again), so the shell needs another way to find an Entry again.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub(crate) struct EntryKey {
+    name: String,
+    url: String,
+    user: String,
+    desc: String,
+}
+
+impl EntryKey {
+    pub(crate) fn new(entry: &Entry) -> EntryKey {
+        EntryKey {
+            name: entry.name.clone(),
+            url: entry.url.clone(),
+            user: entry.user.clone(),
+            desc: entry.desc.clone(),
+        }
+    }
+
+    pub(crate) fn matches(&self, entry: &Entry) -> bool {
+        self.name == entry.name && self.url == entry.url && self.user == entry.user && self.desc == entry.desc
+    }
+
+    /// Returns the index of the first Entry that matches the key
+    pub(crate) fn find_in(&self, entries: &[Entry]) -> Option<usize> {
+        entries.iter().position(|entry| self.matches(entry))
+    }
+
+    pub(crate) fn name(&self) -> &str {
+        &self.name
+    }
+}
+
+/// The actions that can be applied to many Entries at once.
+///
+/// There is no bulk export: the library exports only the whole passwords file, which the Main Menu does already.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub(crate) enum BulkAction {
+    Delete,
+    Tag(String),
+    RegeneratePassword,
+}
+
+impl BulkAction {
+    pub(crate) fn description(&self) -> String {
+        match self {
+            BulkAction::Delete => i18n::tr("bulk-action-delete"),
+            BulkAction::Tag(tag) => i18n::tr_with("bulk-action-tag", &[("tag", tag)]),
+            BulkAction::RegeneratePassword => i18n::tr("bulk-action-regenerate"),
+        }
+    }
+}
+
+/// A bulk action that is in progress.
+///
+/// The library handles one `UserSelection` at a time, so the shell applies a bulk action by answering every following
+/// `show_entries` and `show_entry` call with the next step, until all the targets are processed.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub(crate) struct BulkOperation {
+    action: BulkAction,
+    targets: VecDeque<EntryKey>,
+    awaiting_passphrase: bool,
+    processed: usize,
+    not_found: usize,
+}
+
+impl BulkOperation {
+    pub(crate) fn new(action: BulkAction, targets: Vec<EntryKey>) -> BulkOperation {
+        BulkOperation {
+            action,
+            targets: VecDeque::from(targets),
+            awaiting_passphrase: false,
+            processed: 0,
+            not_found: 0,
+        }
+    }
+
+    /// Returns the selection that applies the action on the next target that can be found in the presented `entries`.
+    ///
+    /// Returns None when there are no more targets.
+    pub(crate) fn next_selection(&mut self, entries: &[Entry]) -> Option<UserSelection> {
+        while let Some(key) = self.targets.pop_front() {
+            match key.find_in(entries) {
+                Some(index) => {
+                    self.processed += 1;
+                    return match self.action {
+                        BulkAction::Delete => Some(UserSelection::GoTo(Menu::DeleteEntry(index))),
+                        _ => Some(UserSelection::GoTo(Menu::EditEntry(index))),
+                    };
+                }
+                None => {
+                    warn!("Entry '{}' could not be found while applying a bulk action", key.name());
+                    self.not_found += 1;
+                }
+            }
+        }
+        None
+    }
+
+    /// Applies the action on an Entry that the library presents for deletion or editing.
+    pub(crate) fn entry_selection(&mut self, mut entry: Entry, index: usize) -> UserSelection {
+        match &self.action {
+            BulkAction::Delete => UserSelection::DeleteEntry(index),
+            BulkAction::Tag(tag) => {
+                entry.desc = add_tag(&entry.desc, tag);
+                extras::touch(&entry);
+                UserSelection::ReplaceEntry(index, entry)
+            }
+            BulkAction::RegeneratePassword if !self.awaiting_passphrase => {
+                self.awaiting_passphrase = true;
+                let mut entry_extras = extras::of(&entry);
+                entry_extras.retire_password(&entry.pass);
+                extras::set(&entry, entry_extras);
+                UserSelection::GeneratePassphrase(Some(index), entry)
+            }
+            _ => {
+                self.awaiting_passphrase = false;
+                extras::touch(&entry);
+                UserSelection::ReplaceEntry(index, entry)
+            }
+        }
+    }
+
+    pub(crate) fn summary(&self) -> String {
+        let args = [
+            ("action", self.action.description()),
+            ("processed", self.processed.to_string()),
+            ("not_found", self.not_found.to_string()),
+        ];
+        let args: Vec<(&str, &str)> = args.iter().map(|(name, value)| (*name, value.as_str())).collect();
+        if self.not_found > 0 {
+            i18n::tr_with("bulk-summary-not-found", &args)
+        } else {
+            i18n::tr_with("bulk-summary", &args)
+        }
+    }
+}
+
+/// Parses a selection like `1-5,8` to zero-based, sorted and deduplicated indexes.
+///
+/// `a` selects all the `len` Entries.
+pub(crate) fn parse_selection(input: &str, len: usize) -> Result<Vec<usize>, String> {
+    if input.trim() == "a" {
+        return Ok((0..len).collect());
+    }
+    let mut indexes = Vec::new();
+    for part in input.split(',').map(str::trim).filter(|part| !part.is_empty()) {
+        let (start, end) = match part.split_once('-') {
+            Some((start, end)) => (parse_number(start, len)?, parse_number(end, len)?),
+            None => {
+                let num = parse_number(part, len)?;
+                (num, num)
+            }
+        };
+        if start > end {
+            return Err(format!("Invalid range '{}'", part));
+        }
+        indexes.extend((start..=end).map(|num| num - 1));
+    }
+    if indexes.is_empty() {
+        return Err("Nothing was selected".to_string());
+    }
+    indexes.sort_unstable();
+    indexes.dedup();
+    Ok(indexes)
+}
+
+fn parse_number(s: &str, len: usize) -> Result<usize, String> {
+    match s.trim().parse::<usize>() {
+        Ok(num) if num >= 1 && num <= len => Ok(num),
+        Ok(num) => Err(format!("'{}' is out of range (1-{})", num, len)),
+        Err(_) => Err(format!("'{}' is not a number", s.trim())),
+    }
+}
+
+/// Adds a `#tag` at the end of a description, if it does not exist already
+pub(crate) fn add_tag(desc: &str, tag: &str) -> String {
+    let tag = format!("#{}", tag.trim_start_matches('#'));
+    if desc.split_whitespace().any(|word| word == tag) {
+        desc.to_string()
+    } else if desc.is_empty() {
+        tag
+    } else {
+        format!("{} {}", desc, tag)
+    }
+}
+
+#[cfg(test)]
+mod test_bulk {
+    use rust_keylock::{Entry, EntryMeta, Menu, UserSelection};
+
+    use crate::extras;
+
+    use super::{BulkAction, BulkOperation, EntryKey};
+
+    fn entry(name: &str) -> Entry {
+        Entry::new(name.to_string(), "url".to_string(), "user".to_string(), "pass".to_string(), "desc".to_string(), EntryMeta::default())
+    }
+
+    #[test]
+    fn parse_selection() {
+        assert!(super::parse_selection("1-3,5", 6) == Ok(vec![0, 1, 2, 4]));
+        assert!(super::parse_selection(" 2 , 1-2 ", 6) == Ok(vec![0, 1]));
+        assert!(super::parse_selection("a", 3) == Ok(vec![0, 1, 2]));
+        assert!(super::parse_selection("4", 3).is_err());
+        assert!(super::parse_selection("0", 3).is_err());
+        assert!(super::parse_selection("3-1", 3).is_err());
+        assert!(super::parse_selection("x", 3).is_err());
+        assert!(super::parse_selection("", 3).is_err());
+    }
+
+    #[test]
+    fn add_tag() {
+        assert!(super::add_tag("", "work") == "#work");
+        assert!(super::add_tag("desc", "#work") == "desc #work");
+        assert!(super::add_tag("desc #work", "work") == "desc #work");
+    }
+
+    #[test]
+    fn bulk_delete_finds_entries_after_reindexing() {
+        let entries = vec![entry("a"), entry("b"), entry("c")];
+        let targets = vec![EntryKey::new(&entries[0]), EntryKey::new(&entries[2])];
+        let mut op = BulkOperation::new(BulkAction::Delete, targets);
+
+        assert!(op.next_selection(&entries) == Some(UserSelection::GoTo(Menu::DeleteEntry(0))));
+        assert!(op.entry_selection(entry("a"), 0) == UserSelection::DeleteEntry(0));
+        let entries = vec![entry("b"), entry("c")];
+        assert!(op.next_selection(&entries) == Some(UserSelection::GoTo(Menu::DeleteEntry(1))));
+        assert!(op.next_selection(&entries[..1]).is_none());
+    }
+
+    #[test]
+    fn bulk_regenerate_password() {
+        crate::extras::test_extras::open_store();
+        let entries = vec![entry("regenerated")];
+        let mut op = BulkOperation::new(BulkAction::RegeneratePassword, vec![EntryKey::new(&entries[0])]);
+
+        assert!(op.next_selection(&entries) == Some(UserSelection::GoTo(Menu::EditEntry(0))));
+        match op.entry_selection(entry("regenerated"), 0) {
+            UserSelection::GeneratePassphrase(Some(0), generate_for) => {
+                assert!(extras::of(&generate_for).password_history[0].pass == "pass");
+            }
+            other => panic!("Unexpected selection {:?}", other),
+        }
+        match op.entry_selection(entry("regenerated"), 0) {
+            UserSelection::ReplaceEntry(0, replacement) => assert!(extras::of(&replacement).modified_at.is_some()),
+            other => panic!("Unexpected selection {:?}", other),
+        }
+        assert!(op.next_selection(&entries).is_none());
+    }
+}
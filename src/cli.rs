@@ -0,0 +1,216 @@
+// Copyright 2017 astonbitecode
+// This file is part of rust-keylock password manager.
+//
+// rust-keylock is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// rust-keylock is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with rust-keylock.  If not, see <http://www.gnu.org/licenses/>.
+//! Common handling of the non-interactive commands: the global options, the errors and their exit codes and the
+//! json output.
+//!
+//! The json output is versioned with `JSON_VERSION`. Fields may be added within a version, but they are never removed
+//! or changed.
+use std::error::Error;
+use std::fmt;
+use std::process;
+
+use serde::{Deserialize, Serialize};
+
+/// The version of the json output. It changes only if the output changes in an incompatible way.
+pub(crate) const JSON_VERSION: u32 = 1;
+
+/// The kinds of errors of the non-interactive commands. Each one has its own exit code.
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub(crate) enum ErrorKind {
+    /// Anything that is not covered by the other kinds
+    Other,
+    /// The arguments are not valid
+    Usage,
+    /// The agent does not run or cannot be reached
+    AgentUnavailable,
+    /// The agent is locked
+    Locked,
+    /// The Entry was not found
+    NotFound,
+    /// The passwords file could not be unlocked with the provided password and number
+    Unlock,
+}
+
+impl ErrorKind {
+    pub(crate) fn exit_code(&self) -> i32 {
+        match self {
+            ErrorKind::Other => 1,
+            ErrorKind::Usage => 2,
+            ErrorKind::AgentUnavailable => 3,
+            ErrorKind::Locked => 4,
+            ErrorKind::NotFound => 5,
+            ErrorKind::Unlock => 6,
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub(crate) struct CliError {
+    pub(crate) kind: ErrorKind,
+    pub(crate) error_message: String,
+}
+
+impl CliError {
+    pub(crate) fn new(kind: ErrorKind, error_message: &str) -> CliError {
+        CliError { kind, error_message: error_message.to_string() }
+    }
+}
+
+impl fmt::Display for CliError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.error_message)
+    }
+}
+
+impl Error for CliError {}
+
+impl From<String> for CliError {
+    fn from(error_message: String) -> CliError {
+        CliError { kind: ErrorKind::Other, error_message }
+    }
+}
+
+impl From<CliError> for String {
+    fn from(error: CliError) -> String {
+        error.error_message
+    }
+}
+
+/// The options that all the non-interactive commands accept
+#[derive(Debug, Default, PartialEq, Eq, Clone, Copy)]
+pub(crate) struct Options {
+    /// Print json instead of text
+    pub(crate) json: bool,
+    /// Include the passwords in the json output
+    pub(crate) show_secrets: bool,
+}
+
+impl Options {
+    /// Takes the options out of the arguments. Arguments after `--` belong to other commands and are kept as they are.
+    pub(crate) fn parse(args: &[String]) -> (Options, Vec<String>) {
+        let mut options = Options::default();
+        let mut remaining = Vec::new();
+        let mut iter = args.iter();
+        for arg in iter.by_ref() {
+            match arg.as_str() {
+                "--json" => options.json = true,
+                "--show-secrets" => options.show_secrets = true,
+                "--" => {
+                    remaining.push(arg.clone());
+                    break;
+                }
+                _ => remaining.push(arg.clone()),
+            }
+        }
+        remaining.extend(iter.cloned());
+        (options, remaining)
+    }
+}
+
+/// An Entry in the json output. The password is included only if the User asks for the secrets.
+#[derive(Debug, PartialEq, Eq, Clone, Serialize)]
+pub(crate) struct JsonEntry {
+    pub(crate) name: String,
+    pub(crate) url: String,
+    pub(crate) user: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) desc: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) pass: Option<String>,
+}
+
+#[derive(Serialize)]
+struct JsonOutput<T: Serialize> {
+    version: u32,
+    #[serde(flatten)]
+    data: T,
+}
+
+#[derive(Serialize)]
+struct JsonErrorOutput<'a> {
+    kind: ErrorKind,
+    message: &'a str,
+    exit_code: i32,
+}
+
+/// Formats the data as json, together with the version of the output
+pub(crate) fn to_json<T: Serialize>(data: T) -> String {
+    serde_json::to_string(&JsonOutput { version: JSON_VERSION, data })
+        .unwrap_or_else(|error| format!("{{\"version\":{},\"error\":{{\"kind\":\"other\",\"message\":\"{}\",\"exit_code\":1}}}}",
+                                        JSON_VERSION,
+                                        error))
+}
+
+/// Formats an error as json
+pub(crate) fn error_to_json(error: &CliError) -> String {
+    #[derive(Serialize)]
+    struct ErrorData<'a> {
+        error: JsonErrorOutput<'a>,
+    }
+    to_json(ErrorData { error: JsonErrorOutput { kind: error.kind, message: &error.error_message, exit_code: error.kind.exit_code() } })
+}
+
+/// Exits with the exit code of the error, after reporting it on stderr, or on stdout as json
+pub(crate) fn exit_on_error<E: Into<CliError>>(result: Result<(), E>, options: &Options) {
+    if let Err(error) = result {
+        let error = error.into();
+        if options.json {
+            println!("{}", error_to_json(&error));
+        } else {
+            eprintln!("rust-keylock: {}", error);
+        }
+        process::exit(error.kind.exit_code());
+    }
+}
+
+#[cfg(test)]
+mod test_cli {
+    use serde::Serialize;
+
+    use super::{CliError, ErrorKind, JsonEntry, Options};
+
+    fn args(args: &[&str]) -> Vec<String> {
+        args.iter().map(|arg| arg.to_string()).collect()
+    }
+
+    #[test]
+    fn parse_options() {
+        let (options, remaining) = Options::parse(&args(&["ls", "--json", "git", "--show-secrets"]));
+        assert!(options == Options { json: true, show_secrets: true });
+        assert!(remaining == args(&["ls", "git"]));
+
+        let (options, remaining) = Options::parse(&args(&["exec", "--env", "A=entry:a:pass", "--", "cmd", "--json"]));
+        assert!(options == Options::default());
+        assert!(remaining == args(&["exec", "--env", "A=entry:a:pass", "--", "cmd", "--json"]));
+    }
+
+    #[test]
+    fn json_output() {
+        #[derive(Serialize)]
+        struct Entries {
+            entries: Vec<JsonEntry>,
+        }
+        let entry = JsonEntry { name: "name".to_string(), url: "url".to_string(), user: "user".to_string(), desc: None, pass: None };
+        assert!(super::to_json(Entries { entries: vec![entry] })
+            == r#"{"version":1,"entries":[{"name":"name","url":"url","user":"user"}]}"#);
+
+        let error = CliError::new(ErrorKind::NotFound, "No Entry with name 'a' was found");
+        assert!(super::error_to_json(&error)
+            == r#"{"version":1,"error":{"kind":"not_found","message":"No Entry with name 'a' was found","exit_code":5}}"#);
+        assert!(CliError::from("failure".to_string()).kind.exit_code() == 1);
+    }
+}
@@ -0,0 +1,198 @@
+// Copyright 2017 astonbitecode
+// This file is part of rust-keylock password manager.
+//
+// rust-keylock is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// rust-keylock is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with rust-keylock.  If not, see <http://www.gnu.org/licenses/>.
+//! The client of the agent and the subcommands that use it: `status`, `ls`, `get`, `lock` and `unlock`.
+use std::borrow::Cow;
+use std::io::{self, IsTerminal};
+use std::os::unix::net::UnixStream;
+use std::time::Duration;
+
+use serde::Serialize;
+
+use crate::agent::{self, AgentEntry, Request, Response};
+use crate::cli::{self, CliError, ErrorKind, JsonEntry, Options};
+use crate::sanitize;
+
+const IO_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// Sends a request to the agent. Errors reported by the agent are returned as errors.
+pub(crate) fn request(request: &Request) -> Result<Response, CliError> {
+    let path = agent::socket_path();
+    let stream = UnixStream::connect(&path)
+        .map_err(|error| CliError::new(ErrorKind::AgentUnavailable,
+                                       &format!("Could not connect to the agent at {} ({}). Is 'rust-keylock agent' running?", path.display(), error)))?;
+    let _ = stream.set_read_timeout(Some(IO_TIMEOUT));
+    agent::write_message(&stream, request).map_err(|error| CliError::new(ErrorKind::AgentUnavailable, &error))?;
+    let response: Response = agent::read_message(&stream).map_err(|error| CliError::new(ErrorKind::AgentUnavailable, &error))?;
+    match &response.error {
+        Some(error) => Err(CliError::new(response.code.unwrap_or(ErrorKind::Other), error)),
+        None => Ok(response),
+    }
+}
+
+/// Returns true if the agent runs and is unlocked
+pub(crate) fn is_unlocked() -> bool {
+    request(&Request::Status).map(|response| !response.locked).unwrap_or(false)
+}
+
+/// Returns the Entries, without their passwords
+pub(crate) fn ls(filter: &str) -> Result<Vec<AgentEntry>, CliError> {
+    request(&Request::Ls { filter: filter.to_string() }).map(|mut response| std::mem::take(&mut response.entries))
+}
+
+pub(crate) fn get(name: &str, user: Option<&str>) -> Result<AgentEntry, CliError> {
+    let mut response = request(&Request::Get { name: name.to_string(), user: user.map(str::to_string) })?;
+    response.entries.pop().ok_or_else(|| CliError::new(ErrorKind::NotFound, &format!("No Entry with name '{}' was found", name)))
+}
+
+/// Makes the agent store or erase a git credential, given as the `key=value` lines of git
+pub(crate) fn credential(operation: &str, credential: &str) -> Result<(), CliError> {
+    request(&Request::Credential { operation: operation.to_string(), credential: credential.to_string() }).map(|_| ())
+}
+
+/// Makes the agent load the Entries again, if it runs
+pub(crate) fn reload() {
+    let _ = request(&Request::Reload);
+}
+
+fn to_json_entry(entry: &AgentEntry, options: &Options) -> JsonEntry {
+    JsonEntry {
+        name: entry.name.clone(),
+        url: entry.url.clone(),
+        user: entry.user.clone(),
+        desc: if entry.desc.is_empty() { None } else { Some(entry.desc.clone()) },
+        pass: if options.show_secrets { Some(entry.pass.clone()) } else { None },
+    }
+}
+
+#[derive(Serialize)]
+struct JsonEntries {
+    entries: Vec<JsonEntry>,
+}
+
+#[derive(Serialize)]
+struct JsonSingleEntry {
+    entry: JsonEntry,
+}
+
+#[derive(Serialize)]
+struct JsonStatus {
+    locked: bool,
+}
+
+#[derive(Serialize)]
+struct JsonEmpty {}
+
+/// Runs one of the subcommands that use the agent
+pub(crate) fn run(command: &str, args: &[String], options: &Options) -> Result<(), CliError> {
+    match command {
+        "ls" => {
+            let entries = ls(args.first().map(String::as_str).unwrap_or(""))?;
+            if options.json {
+                println!("{}", cli::to_json(JsonEntries { entries: entries.iter().map(|entry| to_json_entry(entry, options)).collect() }));
+            } else {
+                for entry in entries {
+                    // Tabs are escaped as well, so that the columns stay aligned
+                    println!("{}\t{}\t{}", sanitize::line(&entry.name), sanitize::line(&entry.user), sanitize::line(&entry.url));
+                }
+            }
+            Ok(())
+        }
+        "get" => {
+            let usage = || CliError::new(ErrorKind::Usage, "Usage: rust-keylock get <name> [name|url|user|pass|desc]");
+            let name = args.first().ok_or_else(usage)?;
+            let entry = get(name, None)?;
+            if options.json {
+                // The json output has all the fields. The password is included only if asked explicitly.
+                println!("{}", cli::to_json(JsonSingleEntry { entry: to_json_entry(&entry, options) }));
+                return Ok(());
+            }
+            // The password is printed only if asked for
+            let field = args.get(1).map(String::as_str).unwrap_or("user");
+            let value = match field {
+                "name" => &entry.name,
+                "url" => &entry.url,
+                "user" => &entry.user,
+                "pass" => &entry.pass,
+                "desc" => &entry.desc,
+                other => return Err(CliError::new(ErrorKind::Usage, &format!("Unknown field '{}'", other))),
+            };
+            println!("{}", printable(field, value, io::stdout().is_terminal()));
+            Ok(())
+        }
+        "status" => {
+            let response = request(&Request::Status)?;
+            if options.json {
+                println!("{}", cli::to_json(JsonStatus { locked: response.locked }));
+            } else {
+                println!("The agent is {}", if response.locked { "locked" } else { "unlocked" });
+            }
+            Ok(())
+        }
+        "lock" | "unlock" => {
+            if command == "lock" {
+                request(&Request::Lock)?;
+            } else {
+                let (password, number) = crate::loader::ask_key()?;
+                request(&Request::Unlock { password: password.to_string(), number })?;
+            }
+            if options.json {
+                println!("{}", cli::to_json(JsonEmpty {}));
+            }
+            Ok(())
+        }
+        other => Err(CliError::new(ErrorKind::Usage, &format!("Unknown command '{}'", other))),
+    }
+}
+
+/// Escapes the field if it is printed on a terminal. Piped to another program, it is passed on as it is.
+fn printable<'a>(field: &str, value: &'a str, is_terminal: bool) -> Cow<'a, str> {
+    match (is_terminal, field) {
+        (false, _) => Cow::Borrowed(value),
+        (true, "desc") => sanitize::lines(value),
+        (true, _) => sanitize::line(value),
+    }
+}
+
+#[cfg(test)]
+mod test_client {
+    use crate::agent::AgentEntry;
+    use crate::cli::{self, Options};
+
+    use super::JsonSingleEntry;
+
+    #[test]
+    fn secrets_only_when_asked() {
+        let entry = AgentEntry {
+            name: "name".to_string(),
+            url: "url".to_string(),
+            user: "user".to_string(),
+            pass: "secret".to_string(),
+            desc: "".to_string(),
+        };
+        let json = cli::to_json(JsonSingleEntry { entry: super::to_json_entry(&entry, &Options { json: true, show_secrets: false }) });
+        assert!(json == r#"{"version":1,"entry":{"name":"name","url":"url","user":"user"}}"#);
+        let json = cli::to_json(JsonSingleEntry { entry: super::to_json_entry(&entry, &Options { json: true, show_secrets: true }) });
+        assert!(json == r#"{"version":1,"entry":{"name":"name","url":"url","user":"user","pass":"secret"}}"#);
+    }
+
+    #[test]
+    fn escaped_only_on_a_terminal() {
+        assert!(super::printable("user", "a\x1b]52;c;\x07", true) == "a\\x1b]52;c;\\x07");
+        assert!(super::printable("user", "a\x1b]52;c;\x07", false) == "a\x1b]52;c;\x07");
+        assert!(super::printable("desc", "line 1\nline 2\x1b", true) == "line 1\nline 2\\x1b");
+        assert!(super::printable("name", "line 1\nline 2", true) == "line 1\\nline 2");
+    }
+}
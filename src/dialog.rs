@@ -0,0 +1,271 @@
+// Copyright 2017 astonbitecode
+// This file is part of rust-keylock password manager.
+//
+// rust-keylock is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// rust-keylock is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with rust-keylock.  If not, see <http://www.gnu.org/licenses/>.
+//! The dialog that presents the messages of the library and lets the User select one of their options.
+use std::collections::VecDeque;
+use std::io::{self, BufRead};
+use std::time::Duration;
+
+use log::debug;
+use rust_keylock::{MessageSeverity, UserOption};
+
+use crate::i18n;
+
+/// The short label of the option that is selected by pressing Enter
+const DEFAULT_SHORT_LABEL: &str = "o";
+/// How many messages the log keeps
+const MESSAGE_LOG_CAPACITY: usize = 100;
+
+/// An option of the dialog and the key that selects it
+#[derive(Debug, PartialEq, Clone)]
+struct Choice {
+    key: String,
+    option: UserOption,
+}
+
+/// A message with the options the User may select
+#[derive(Debug, PartialEq, Clone)]
+pub(crate) struct Dialog {
+    message: String,
+    severity: MessageSeverity,
+    choices: Vec<Choice>,
+    default: Option<usize>,
+}
+
+impl Dialog {
+    /// Creates a dialog. The keys of the options are their short labels, unless they are invalid or taken by another
+    /// option. Then a free character of the label, or else a number, is used. Without options, an Ok option is offered.
+    pub(crate) fn new(message: &str, mut options: Vec<UserOption>, severity: MessageSeverity) -> Dialog {
+        if options.is_empty() {
+            options.push(UserOption::ok());
+        }
+        let mut choices: Vec<Choice> = Vec::new();
+        let taken = |choices: &[Choice], key: &str| choices.iter().any(|choice| choice.key == key);
+        for option in options {
+            let short_label = option.short_label.trim().to_lowercase();
+            let key = if is_valid_key(&short_label) && !taken(&choices, &short_label) {
+                short_label
+            } else {
+                let from_label = option.label.to_lowercase().chars()
+                    .filter(|c| c.is_alphabetic())
+                    .map(String::from)
+                    .find(|key| !taken(&choices, key));
+                let key = from_label.unwrap_or_else(|| {
+                    (1..).map(|number: usize| number.to_string()).find(|key| !taken(&choices, key)).unwrap_or_default()
+                });
+                debug!("The option '{}' gets the key '{}' instead of '{}'", option.label, key, option.short_label);
+                key
+            };
+            choices.push(Choice { key, option });
+        }
+        let default = if choices.len() == 1 {
+            Some(0)
+        } else {
+            choices.iter().position(|choice| choice.option.short_label == DEFAULT_SHORT_LABEL)
+        };
+        Dialog { message: message.to_string(), severity, choices, default }
+    }
+
+    pub(crate) fn severity(&self) -> &MessageSeverity {
+        &self.severity
+    }
+
+    pub(crate) fn message(&self) -> &str {
+        &self.message
+    }
+
+    /// The text that presents the message and its options
+    pub(crate) fn text(&self) -> String {
+        let severity_label = severity_label(&self.severity);
+        let mut text = format!("[{}] {}\n\n\t{} ", severity_label, self.message, i18n::tr("message-press"));
+        for (index, choice) in self.choices.iter().enumerate() {
+            let args = [("enter", i18n::tr("message-enter")), ("key", choice.key.clone()), ("label", choice.option.label.clone())];
+            let args: Vec<(&str, &str)> = args.iter().map(|(name, value)| (*name, value.as_str())).collect();
+            let id = if self.default == Some(index) { "message-default-option" } else { "message-option" };
+            text.push_str(&i18n::tr_with(id, &args));
+            text.push(' ');
+        }
+        text
+    }
+
+    /// The option of the input. The keys are case insensitive and an empty input selects the default option.
+    pub(crate) fn select(&self, input: &str) -> Option<&UserOption> {
+        let input = input.trim().to_lowercase();
+        let index = if input.is_empty() {
+            self.default
+        } else {
+            self.choices.iter().position(|choice| choice.key == input)
+        };
+        index.map(|index| &self.choices[index].option)
+    }
+
+    /// Whether the dialog selects its default option by itself, after the configured seconds
+    pub(crate) fn dismisses_after(&self, timeout_seconds: u64) -> Option<Duration> {
+        match self.severity {
+            MessageSeverity::Info if timeout_seconds > 0 && self.default.is_some() => Some(Duration::from_secs(timeout_seconds)),
+            _ => None,
+        }
+    }
+}
+
+/// The keys are typed in the prompt, so they must be single words that are not numbers
+fn is_valid_key(key: &str) -> bool {
+    !key.is_empty() && !key.contains(char::is_whitespace) && key.parse::<usize>().is_err()
+}
+
+pub(crate) fn severity_label(severity: &MessageSeverity) -> String {
+    match severity {
+        MessageSeverity::Error => i18n::tr("severity-error"),
+        MessageSeverity::Warn => i18n::tr("severity-warn"),
+        MessageSeverity::Info => i18n::tr("severity-info"),
+    }
+}
+
+/// Reads a line from the standard input, waiting for up to `timeout`. Returns `None` if nothing was entered in time.
+#[cfg(unix)]
+pub(crate) fn read_line_with_timeout(timeout: Duration) -> Option<String> {
+    let mut poll_fd = libc::pollfd { fd: libc::STDIN_FILENO, events: libc::POLLIN, revents: 0 };
+    let millis = timeout.as_millis().min(libc::c_int::MAX as u128) as libc::c_int;
+    let ready = unsafe { libc::poll(&mut poll_fd, 1, millis) };
+    if ready > 0 {
+        let mut line = String::new();
+        io::stdin().lock().read_line(&mut line).ok()?;
+        Some(line)
+    } else {
+        None
+    }
+}
+
+/// Reads a line from the standard input. Waiting with a timeout is not supported on this platform.
+#[cfg(not(unix))]
+pub(crate) fn read_line_with_timeout(_timeout: Duration) -> Option<String> {
+    let mut line = String::new();
+    io::stdin().lock().read_line(&mut line).ok()?;
+    Some(line)
+}
+
+/// A message that was shown to the User
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub(crate) struct LoggedMessage {
+    /// Seconds since the epoch
+    pub(crate) shown_at: i64,
+    pub(crate) severity: String,
+    pub(crate) message: String,
+    /// The label of the option that was selected
+    pub(crate) selected: String,
+}
+
+/// The messages that were shown in the current session, the oldest first
+#[derive(Debug, Default)]
+pub(crate) struct MessageLog {
+    messages: VecDeque<LoggedMessage>,
+}
+
+impl MessageLog {
+    pub(crate) fn record(&mut self, message: LoggedMessage) {
+        if self.messages.len() == MESSAGE_LOG_CAPACITY {
+            self.messages.pop_front();
+        }
+        self.messages.push_back(message);
+    }
+
+    pub(crate) fn messages(&self) -> impl Iterator<Item = &LoggedMessage> {
+        self.messages.iter()
+    }
+
+    pub(crate) fn is_empty(&self) -> bool {
+        self.messages.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod test_dialog {
+    use std::time::Duration;
+
+    use rust_keylock::{MessageSeverity, UserOption};
+
+    use super::{Dialog, LoggedMessage, MessageLog};
+
+    fn option(label: &str, short_label: &str) -> UserOption {
+        UserOption { label: label.to_string(), short_label: short_label.to_string(), ..UserOption::empty() }
+    }
+
+    #[test]
+    fn default_option() {
+        let dialog = Dialog::new("Saved", vec![UserOption::ok(), UserOption::cancel()], MessageSeverity::Info);
+        assert!(dialog.select("") == Some(&UserOption::ok()));
+        assert!(dialog.select("\n") == Some(&UserOption::ok()));
+        assert!(dialog.select("o") == Some(&UserOption::ok()));
+        assert!(dialog.select("c") == Some(&UserOption::cancel()));
+
+        let dialog = Dialog::new("Delete?", vec![UserOption::yes(), UserOption::no()], MessageSeverity::Warn);
+        assert!(dialog.select("").is_none());
+
+        let dialog = Dialog::new("Failed", vec![UserOption::cancel()], MessageSeverity::Error);
+        assert!(dialog.select("") == Some(&UserOption::cancel()));
+        assert!(Dialog::new("Done", Vec::new(), MessageSeverity::Info).select("") == Some(&UserOption::ok()));
+    }
+
+    #[test]
+    fn case_insensitive_keys() {
+        let upper = option("Retry", "R");
+        let dialog = Dialog::new("Failed", vec![upper.clone(), UserOption::no()], MessageSeverity::Error);
+        assert!(dialog.select("r") == Some(&upper));
+        assert!(dialog.select(" R ") == Some(&upper));
+        assert!(dialog.select("N") == Some(&UserOption::no()));
+        assert!(dialog.select("x").is_none());
+    }
+
+    #[test]
+    fn conflicting_short_labels() {
+        let first = option("Keep", "k");
+        let second = option("Kill", "k");
+        let third = option("Ki", "K");
+        let numeric = option("List", "1");
+        let dialog = Dialog::new("Conflict", vec![first.clone(), second.clone(), third.clone(), numeric.clone()], MessageSeverity::Warn);
+        assert!(dialog.select("k") == Some(&first));
+        assert!(dialog.select("i") == Some(&second));
+        assert!(dialog.select("1") == Some(&third));
+        assert!(dialog.select("l") == Some(&numeric));
+        assert!(dialog.text().contains("'i' for Kill"));
+    }
+
+    #[test]
+    fn text() {
+        let dialog = Dialog::new("Saved", vec![UserOption::ok(), UserOption::cancel()], MessageSeverity::Info);
+        assert!(dialog.text() == "[Info] Saved\n\n\tPress 'Enter' (or 'o') for Ok 'c' for Cancel ");
+    }
+
+    #[test]
+    fn dismisses_only_info_messages_with_a_default() {
+        let info = Dialog::new("Saved", vec![UserOption::ok()], MessageSeverity::Info);
+        assert!(info.dismisses_after(3) == Some(Duration::from_secs(3)));
+        assert!(info.dismisses_after(0).is_none());
+        let warn = Dialog::new("Careful", vec![UserOption::ok()], MessageSeverity::Warn);
+        assert!(warn.dismisses_after(3).is_none());
+        let question = Dialog::new("Sure?", vec![UserOption::yes(), UserOption::no()], MessageSeverity::Info);
+        assert!(question.dismisses_after(3).is_none());
+    }
+
+    #[test]
+    fn message_log_is_bounded() {
+        let mut log = MessageLog::default();
+        for i in 0..super::MESSAGE_LOG_CAPACITY + 5 {
+            log.record(LoggedMessage { shown_at: i as i64, severity: "Info".to_string(), message: i.to_string(), selected: "Ok".to_string() });
+        }
+        assert!(log.messages().count() == super::MESSAGE_LOG_CAPACITY);
+        assert!(log.messages().next().map(|message| message.shown_at) == Some(5));
+    }
+}
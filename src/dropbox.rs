@@ -0,0 +1,194 @@
+// Copyright 2017 astonbitecode
+// This file is part of rust-keylock password manager.
+//
+// rust-keylock is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// rust-keylock is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with rust-keylock.  If not, see <http://www.gnu.org/licenses/>.
+//! Acquires a Dropbox token without a browser on this machine.
+//!
+//! The User opens the authorization URL on any device. Dropbox then redirects to `localhost`, which fails on that
+//! device, but the address bar holds the code. The User pastes the code, or the whole URL, and the shell exchanges it
+//! for a token, the way the library does when it receives the redirect itself.
+use std::env;
+
+use serde::Deserialize;
+use url::Url;
+use zeroize::Zeroizing;
+
+use crate::http;
+use crate::i18n;
+
+pub(crate) const TOKEN_URL: &str = "https://api.dropbox.com/oauth2/token";
+
+/// What the token request needs to repeat from the authorization URL
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub(crate) struct Authorization {
+    client_id: String,
+    /// The authorization uses the `plain` PKCE method, so the verifier is the challenge
+    code_verifier: Zeroizing<String>,
+    redirect_uri: String,
+}
+
+impl Authorization {
+    /// Reads the authorization from the URL that the library creates
+    pub(crate) fn from_url(url: &str) -> Result<Authorization, String> {
+        let url = Url::parse(url).map_err(|error| error.to_string())?;
+        let param = |name: &str| url.query_pairs().find(|(key, _)| key == name).map(|(_, value)| value.to_string());
+        match (param("client_id"), param("code_challenge"), param("redirect_uri")) {
+            (Some(client_id), Some(code_challenge), Some(redirect_uri)) => Ok(Authorization {
+                client_id,
+                // Like the library does
+                code_verifier: Zeroizing::new(code_challenge.replace(' ', "+")),
+                redirect_uri,
+            }),
+            _ => Err(i18n::tr("dropbox-url-invalid")),
+        }
+    }
+}
+
+/// Takes the code out of the input, which is either the code or the URL Dropbox redirected to
+pub(crate) fn parse_code(input: &str) -> Result<Zeroizing<String>, String> {
+    let input = input.trim();
+    if input.starts_with("http://") || input.starts_with("https://") {
+        let url = Url::parse(input).map_err(|error| error.to_string())?;
+        let param = |name: &str| url.query_pairs().find(|(key, _)| key == name).map(|(_, value)| Zeroizing::new(value.to_string()));
+        if let Some(error) = param("error") {
+            let description = param("error_description").map(|description| description.to_string()).unwrap_or_else(|| error.to_string());
+            Err(i18n::tr_with("dropbox-authorization-denied", &[("error", &description)]))
+        } else {
+            param("code").filter(|code| !code.is_empty()).ok_or_else(|| i18n::tr("dropbox-code-missing"))
+        }
+    } else if input.is_empty() || input.contains(char::is_whitespace) {
+        Err(i18n::tr("dropbox-code-missing"))
+    } else {
+        Ok(Zeroizing::new(input.to_string()))
+    }
+}
+
+#[derive(Deserialize)]
+struct TokenResponse {
+    #[serde(default)]
+    refresh_token: Option<String>,
+    #[serde(default)]
+    error: Option<String>,
+    #[serde(default)]
+    error_description: Option<String>,
+}
+
+/// Exchanges the code for the long lived token that the library stores
+pub(crate) fn exchange_code(token_url: &str, authorization: &Authorization, code: &str) -> Result<Zeroizing<String>, String> {
+    let body = Zeroizing::new(url::form_urlencoded::Serializer::new(String::new())
+        .append_pair("code", code)
+        .append_pair("grant_type", "authorization_code")
+        .append_pair("redirect_uri", &authorization.redirect_uri)
+        .append_pair("code_verifier", &authorization.code_verifier)
+        .append_pair("client_id", &authorization.client_id)
+        .finish());
+    let response = http::request("POST", token_url, &[("Content-Type", "application/x-www-form-urlencoded")], body.as_bytes(), false)
+        .map_err(|error| i18n::tr_with("dropbox-request-failed", &[("error", &format!("{:?}", error))]))?;
+    let parsed: TokenResponse = serde_json::from_slice(&response.body)
+        .map_err(|_| i18n::tr_with("dropbox-request-failed", &[("error", &format!("HTTP {}", response.status))]))?;
+    match parsed {
+        TokenResponse { refresh_token: Some(token), .. } if response.status == 200 && !token.is_empty() => Ok(Zeroizing::new(token)),
+        TokenResponse { error_description: Some(error), .. } | TokenResponse { error: Some(error), .. } => {
+            Err(i18n::tr_with("dropbox-code-rejected", &[("error", &error)]))
+        }
+        _ => Err(i18n::tr_with("dropbox-request-failed", &[("error", &format!("HTTP {}", response.status))])),
+    }
+}
+
+/// Whether a browser cannot be opened here, like in SSH sessions or without a graphical session
+pub(crate) fn is_headless() -> bool {
+    let set = |name: &str| env::var(name).map(|value| !value.is_empty()).unwrap_or(false);
+    if set("SSH_CONNECTION") || set("SSH_TTY") {
+        return true;
+    }
+    cfg!(all(unix, not(target_os = "macos"))) && !set("DISPLAY") && !set("WAYLAND_DISPLAY")
+}
+
+#[cfg(test)]
+pub(crate) mod test_dropbox {
+    use std::io::{BufRead, BufReader, Read, Write};
+    use std::net::TcpListener;
+    use std::thread::{self, JoinHandle};
+
+    use super::Authorization;
+
+    pub(crate) const AUTHORIZATION_URL: &str = "https://www.dropbox.com/oauth2/authorize?client_id=app&response_type=code&code_challenge=abc&code_challenge_method=plain&redirect_uri=http://localhost:8899&token_access_type=offline";
+
+    /// Answers one token request with the response and returns the body of the request
+    pub(crate) fn serve_once(status: &'static str, response: &'static str) -> (String, JoinHandle<String>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://127.0.0.1:{}/oauth2/token", listener.local_addr().unwrap().port());
+        let handle = thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            let mut reader = BufReader::new(stream.try_clone().unwrap());
+            let mut content_length = 0;
+            loop {
+                let mut header = String::new();
+                reader.read_line(&mut header).unwrap();
+                if header.trim().is_empty() {
+                    break;
+                }
+                // Header names are case insensitive
+                if let Some((name, length)) = header.split_once(':') {
+                    if name.eq_ignore_ascii_case("content-length") {
+                        content_length = length.trim().parse().unwrap();
+                    }
+                }
+            }
+            let mut body = vec![0; content_length];
+            reader.read_exact(&mut body).unwrap();
+            let _ = write!(stream, "HTTP/1.1 {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\n\r\n{}", status, response.len(), response);
+            String::from_utf8(body).unwrap()
+        });
+        (url, handle)
+    }
+
+    #[test]
+    fn authorization_from_url() {
+        let authorization = Authorization::from_url(AUTHORIZATION_URL).unwrap();
+        assert!(authorization.client_id == "app");
+        assert!(authorization.code_verifier.as_str() == "abc");
+        assert!(authorization.redirect_uri == "http://localhost:8899");
+        assert!(Authorization::from_url("https://www.dropbox.com/oauth2/authorize").is_err());
+    }
+
+    #[test]
+    fn parse_code() {
+        assert!(super::parse_code(" thecode ").unwrap().as_str() == "thecode");
+        assert!(super::parse_code("http://localhost:8899/?code=the%2Fcode&state=x").unwrap().as_str() == "the/code");
+        assert!(super::parse_code("http://localhost:8899/?error=access_denied&error_description=The+user+denied").unwrap_err().contains("The user denied"));
+        assert!(super::parse_code("http://localhost:8899/").is_err());
+        assert!(super::parse_code("").is_err());
+        assert!(super::parse_code("two words").is_err());
+    }
+
+    #[test]
+    fn exchange_code() {
+        let (url, server) = serve_once("200 OK", r#"{"access_token":"short","token_type":"bearer","expires_in":14400,"refresh_token":"long"}"#);
+        let authorization = Authorization::from_url(AUTHORIZATION_URL).unwrap();
+        let token = super::exchange_code(&url, &authorization, "the/code").unwrap();
+        assert!(token.as_str() == "long");
+        let body = server.join().unwrap();
+        assert!(body == "code=the%2Fcode&grant_type=authorization_code&redirect_uri=http%3A%2F%2Flocalhost%3A8899&code_verifier=abc&client_id=app");
+    }
+
+    #[test]
+    fn exchange_code_rejected() {
+        let (url, server) = serve_once("400 Bad Request", r#"{"error":"invalid_grant","error_description":"code doesn't exist or has expired"}"#);
+        let authorization = Authorization::from_url(AUTHORIZATION_URL).unwrap();
+        let error = super::exchange_code(&url, &authorization, "old").unwrap_err();
+        assert!(error.contains("code doesn't exist or has expired"));
+        server.join().unwrap();
+    }
+}
@@ -0,0 +1,383 @@
+// Copyright 2017 astonbitecode
+// This file is part of rust-keylock password manager.
+//
+// rust-keylock is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// rust-keylock is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with rust-keylock.  If not, see <http://www.gnu.org/licenses/>.
+//! Passing fields of Entries to other programs, without writing them to disk or to the command line.
+//!
+//! * `rust-keylock exec --env DB_PASS=entry:prod-db:pass -- ./deploy.sh` runs a command with environment variables
+//!   that are set from Entries.
+//! * `rust-keylock render config.tmpl` prints a template to stdout, replacing `{{ rkl "prod-db" "pass" }}` with the
+//!   field of the Entry.
+//!
+//! The Entries are taken from the agent, if it runs and is unlocked. Otherwise the User is prompted for the password.
+use std::fs;
+use std::io::{self, Read, Write};
+use std::process::Command;
+
+use rust_keylock::{Entry, EntryMeta};
+use zeroize::Zeroizing;
+
+use crate::cli::{CliError, ErrorKind};
+use crate::loader::{self, Loaded};
+
+const REFERENCE_PREFIX: &str = "entry:";
+const TEMPLATE_START: &str = "{{";
+const TEMPLATE_END: &str = "}}";
+const TEMPLATE_FUNCTION: &str = "rkl";
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub(crate) enum Field {
+    Name,
+    Url,
+    User,
+    Pass,
+    Desc,
+}
+
+impl Field {
+    pub(crate) fn parse(field: &str) -> Result<Field, String> {
+        match field {
+            "name" => Ok(Field::Name),
+            "url" => Ok(Field::Url),
+            "user" => Ok(Field::User),
+            "pass" => Ok(Field::Pass),
+            "desc" => Ok(Field::Desc),
+            other => Err(format!("Unknown field '{}'. The fields are name, url, user, pass and desc.", other)),
+        }
+    }
+
+    pub(crate) fn value<'a>(&self, entry: &'a Entry) -> &'a str {
+        match self {
+            Field::Name => &entry.name,
+            Field::Url => &entry.url,
+            Field::User => &entry.user,
+            Field::Pass => &entry.pass,
+            Field::Desc => &entry.desc,
+        }
+    }
+}
+
+/// A field of an Entry, referenced by the name of the Entry
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub(crate) struct Reference {
+    pub(crate) name: String,
+    pub(crate) field: Field,
+}
+
+impl Reference {
+    /// Parses `entry:<name>:<field>`. The name may contain colons.
+    pub(crate) fn parse(reference: &str) -> Result<Reference, String> {
+        let (name, field) = reference.strip_prefix(REFERENCE_PREFIX)
+            .and_then(|rest| rest.rsplit_once(':'))
+            .ok_or_else(|| format!("Invalid reference '{}'. The expected format is entry:<name>:<field>", reference))?;
+        if name.is_empty() {
+            return Err(format!("The reference '{}' does not have an Entry name", reference));
+        }
+        Ok(Reference { name: name.to_string(), field: Field::parse(field)? })
+    }
+}
+
+/// The arguments of `exec`
+#[derive(Debug, PartialEq, Eq)]
+pub(crate) struct ExecArgs {
+    /// The environment variables and the fields that they are set to
+    pub(crate) variables: Vec<(String, Reference)>,
+    pub(crate) command: Vec<String>,
+}
+
+/// Parses the arguments of `exec`: `--env VAR=entry:<name>:<field>`, repeated, then `--` and the command
+pub(crate) fn parse_exec_args(args: &[String]) -> Result<ExecArgs, String> {
+    let usage = "Usage: rust-keylock exec --env VAR=entry:<name>:<field> [--env ...] -- <command> [args...]";
+    let mut variables = Vec::new();
+    let mut iter = args.iter();
+    loop {
+        match iter.next().map(String::as_str) {
+            Some("--env") | Some("-e") => {
+                let definition = iter.next().ok_or_else(|| usage.to_string())?;
+                let (variable, reference) = definition.split_once('=')
+                    .ok_or_else(|| format!("Invalid variable definition '{}'. {}", definition, usage))?;
+                if variable.is_empty() || variable.contains('\0') {
+                    return Err(format!("Invalid variable name '{}'", variable));
+                }
+                variables.push((variable.to_string(), Reference::parse(reference)?));
+            }
+            Some("--") => break,
+            _ => return Err(usage.to_string()),
+        }
+    }
+    let command: Vec<String> = iter.cloned().collect();
+    if command.is_empty() {
+        return Err(usage.to_string());
+    }
+    Ok(ExecArgs { variables, command })
+}
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub(crate) enum Segment {
+    Text(String),
+    Reference(Reference),
+}
+
+/// Parses a template. Only `{{ rkl "<name>" "<field>" }}` is replaced; other `{{ ... }}` blocks are kept as they are.
+pub(crate) fn parse_template(template: &str) -> Result<Vec<Segment>, String> {
+    let mut segments = Vec::new();
+    let mut text = String::new();
+    let mut rest = template;
+    while let Some(start) = rest.find(TEMPLATE_START) {
+        text.push_str(&rest[..start]);
+        let after_start = &rest[start + TEMPLATE_START.len()..];
+        let end = match after_start.find(TEMPLATE_END) {
+            Some(end) => end,
+            None => {
+                rest = &rest[start..];
+                break;
+            }
+        };
+        let inner = after_start[..end].trim();
+        match inner.strip_prefix(TEMPLATE_FUNCTION).filter(|args| args.starts_with(char::is_whitespace)) {
+            Some(args) => {
+                let arguments = parse_quoted(args)
+                    .map_err(|error| format!("Invalid template expression '{{{{{}}}}}': {}", &after_start[..end], error))?;
+                match arguments.as_slice() {
+                    [name, field] => {
+                        if !text.is_empty() {
+                            segments.push(Segment::Text(std::mem::take(&mut text)));
+                        }
+                        segments.push(Segment::Reference(Reference { name: name.clone(), field: Field::parse(field)? }));
+                    }
+                    _ => return Err(format!("The template expression '{{{{{}}}}}' needs a name and a field", &after_start[..end])),
+                }
+            }
+            None => {
+                text.push_str(TEMPLATE_START);
+                text.push_str(&after_start[..end + TEMPLATE_END.len()]);
+            }
+        }
+        rest = &after_start[end + TEMPLATE_END.len()..];
+    }
+    text.push_str(rest);
+    if !text.is_empty() {
+        segments.push(Segment::Text(text));
+    }
+    Ok(segments)
+}
+
+/// Parses whitespace-separated strings in double quotes, where `\"` and `\\` are escapes
+fn parse_quoted(input: &str) -> Result<Vec<String>, String> {
+    let mut arguments = Vec::new();
+    let mut chars = input.trim().chars();
+    while let Some(c) = chars.next() {
+        if c.is_whitespace() {
+            continue;
+        }
+        if c != '"' {
+            return Err("the arguments must be in double quotes".to_string());
+        }
+        let mut argument = String::new();
+        loop {
+            match chars.next() {
+                Some('"') => break,
+                Some('\\') => argument.push(chars.next().ok_or_else(|| "unterminated string".to_string())?),
+                Some(c) => argument.push(c),
+                None => return Err("unterminated string".to_string()),
+            }
+        }
+        arguments.push(argument);
+    }
+    Ok(arguments)
+}
+
+/// Returns the values of the references, in the same order
+fn resolve<T>(references: &[&Reference], lookup: T) -> Result<Vec<Zeroizing<String>>, CliError>
+    where T: Fn(&str) -> Result<Entry, CliError>
+{
+    references.iter()
+        .map(|reference| lookup(&reference.name).map(|entry| Zeroizing::new(reference.field.value(&entry).to_string())))
+        .collect()
+}
+
+/// Resolves the references with the Entries of the agent or, if it is not available, by unlocking the passwords file
+fn resolve_entries(references: &[&Reference]) -> Result<Vec<Zeroizing<String>>, CliError> {
+    #[cfg(unix)]
+    {
+        if crate::client::is_unlocked() {
+            return resolve(references, |name| {
+                crate::client::get(name, None)
+                    .map(|found| {
+                        Entry::new(found.name.clone(), found.url.clone(), found.user.clone(), found.pass.clone(), found.desc.clone(), EntryMeta::default())
+                    })
+            });
+        }
+    }
+    let (password, number) = loader::ask_key()?;
+    resolve_loaded(references, loader::load_entries(&password, number))
+}
+
+/// Resolves the references with the Entries of the unlocked passwords file
+fn resolve_loaded(references: &[&Reference], loaded: Loaded) -> Result<Vec<Zeroizing<String>>, CliError> {
+    let entries = loaded.map_err(|error| CliError::new(ErrorKind::Unlock, &error))?;
+    resolve(references, |name| {
+        loader::find_entry(&entries, name, None)
+            .cloned()
+            .ok_or_else(|| CliError::new(ErrorKind::NotFound, &format!("No Entry with name '{}' was found", name)))
+    })
+}
+
+/// Runs `exec`. On success it does not return on Unix, as the command replaces the current process.
+pub(crate) fn run_exec(args: &[String]) -> Result<(), CliError> {
+    let ExecArgs { variables, command: command_line } = parse_exec_args(args).map_err(|error| CliError::new(ErrorKind::Usage, &error))?;
+    let references: Vec<&Reference> = variables.iter().map(|(_, reference)| reference).collect();
+    let values = resolve_entries(&references)?;
+
+    let mut command = Command::new(&command_line[0]);
+    command.args(&command_line[1..]);
+    for ((variable, _), value) in variables.iter().zip(values.iter()) {
+        command.env(variable, value.as_str());
+    }
+    spawn(command, &command_line[0]).map_err(CliError::from)
+}
+
+#[cfg(unix)]
+fn spawn(mut command: Command, program: &str) -> Result<(), String> {
+    use std::os::unix::process::CommandExt;
+    let error = command.exec();
+    Err(format!("Could not execute '{}': {}", program, error))
+}
+
+#[cfg(not(unix))]
+fn spawn(mut command: Command, program: &str) -> Result<(), String> {
+    let status = command.status().map_err(|error| format!("Could not execute '{}': {}", program, error))?;
+    std::process::exit(status.code().unwrap_or(1));
+}
+
+/// Runs `render`, printing the rendered template to stdout. The template is read from stdin if the path is `-`.
+pub(crate) fn run_render(args: &[String]) -> Result<(), CliError> {
+    let path = args.first().ok_or_else(|| CliError::new(ErrorKind::Usage, "Usage: rust-keylock render <template file | ->"))?;
+    let template = if path == "-" {
+        let mut template = String::new();
+        io::stdin().read_to_string(&mut template).map_err(|error| error.to_string())?;
+        template
+    } else {
+        fs::read_to_string(path).map_err(|error| format!("Could not read {}: {}", path, error))?
+    };
+    let segments = parse_template(&template)?;
+    let references: Vec<&Reference> = segments.iter()
+        .filter_map(|segment| match segment {
+            Segment::Reference(reference) => Some(reference),
+            Segment::Text(_) => None,
+        })
+        .collect();
+    let values = resolve_entries(&references)?;
+    let rendered = Zeroizing::new(render(&segments, &values));
+    io::stdout().write_all(rendered.as_bytes()).map_err(|error| CliError::from(error.to_string()))
+}
+
+/// Joins the segments, replacing the references with the `values`, in order
+fn render(segments: &[Segment], values: &[Zeroizing<String>]) -> String {
+    let mut values = values.iter();
+    segments.iter()
+        .map(|segment| match segment {
+            Segment::Text(text) => text.as_str(),
+            Segment::Reference(_) => values.next().map(|value| value.as_str()).unwrap_or(""),
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod test_exec {
+    use rust_keylock::{Entry, EntryMeta};
+
+    use crate::cli::{CliError, ErrorKind};
+
+    use super::{Field, Reference, Segment};
+
+    fn args(args: &[&str]) -> Vec<String> {
+        args.iter().map(|arg| arg.to_string()).collect()
+    }
+
+    fn reference(name: &str, field: Field) -> Reference {
+        Reference { name: name.to_string(), field }
+    }
+
+    #[test]
+    fn parse_reference() {
+        assert!(Reference::parse("entry:prod-db:pass") == Ok(reference("prod-db", Field::Pass)));
+        assert!(Reference::parse("entry:host:5432:user") == Ok(reference("host:5432", Field::User)));
+        assert!(Reference::parse("entry::pass").is_err());
+        assert!(Reference::parse("entry:prod-db:password").is_err());
+        assert!(Reference::parse("prod-db:pass").is_err());
+    }
+
+    #[test]
+    fn parse_exec_args() {
+        let super::ExecArgs { variables, command } = super::parse_exec_args(&args(&["--env", "DB_PASS=entry:prod-db:pass", "-e", "DB_USER=entry:prod-db:user",
+            "--", "./deploy.sh", "--env", "prod"])).unwrap();
+        assert!(variables == vec![("DB_PASS".to_string(), reference("prod-db", Field::Pass)),
+                                  ("DB_USER".to_string(), reference("prod-db", Field::User))]);
+        assert!(command == args(&["./deploy.sh", "--env", "prod"]));
+
+        assert!(super::parse_exec_args(&args(&["--env", "DB_PASS=entry:prod-db:pass", "--"])).is_err());
+        assert!(super::parse_exec_args(&args(&["--env", "DB_PASS", "--", "cmd"])).is_err());
+        assert!(super::parse_exec_args(&args(&["--env", "=entry:a:pass", "--", "cmd"])).is_err());
+        assert!(super::parse_exec_args(&args(&["cmd"])).is_err());
+    }
+
+    #[test]
+    fn parse_and_render_template() {
+        let template = "user: {{ rkl \"prod-db\" \"user\" }}\npass: {{rkl \"prod \\\"db\\\"\"   \"pass\"}}\nkept: {{ other }} {{";
+        let segments = super::parse_template(template).unwrap();
+        assert!(segments == vec![
+            Segment::Text("user: ".to_string()),
+            Segment::Reference(reference("prod-db", Field::User)),
+            Segment::Text("\npass: ".to_string()),
+            Segment::Reference(reference("prod \"db\"", Field::Pass)),
+            Segment::Text("\nkept: {{ other }} {{".to_string()),
+        ]);
+
+        let entries = [
+            Entry::new("prod-db".to_string(), "".to_string(), "admin".to_string(), "pass1".to_string(), "".to_string(), EntryMeta::default()),
+            Entry::new("prod \"db\"".to_string(), "".to_string(), "".to_string(), "pass2".to_string(), "".to_string(), EntryMeta::default()),
+        ];
+        let references: Vec<&Reference> = segments.iter()
+            .filter_map(|segment| if let Segment::Reference(reference) = segment { Some(reference) } else { None })
+            .collect();
+        let values = super::resolve(&references, |name| {
+            entries.iter().find(|entry| entry.name == name).cloned().ok_or_else(|| CliError::from("missing".to_string()))
+        }).unwrap();
+        assert!(super::render(&segments, &values) == "user: admin\npass: pass2\nkept: {{ other }} {{");
+
+        assert!(super::parse_template("{{ rkl \"a\" }}").is_err());
+        assert!(super::parse_template("{{ rkl \"a\" \"pass }}").is_err());
+        assert!(super::parse_template("{{ rklx \"a\" \"pass\" }}").unwrap() == vec![Segment::Text("{{ rklx \"a\" \"pass\" }}".to_string())]);
+    }
+
+    #[test]
+    fn errors_keep_their_kind() {
+        let entries = vec![Entry::new("prod-db".to_string(), "".to_string(), "admin".to_string(), "pass".to_string(), "".to_string(), EntryMeta::default())];
+        let found = reference("prod-db", Field::Pass);
+        let missing = reference("missing", Field::Pass);
+        assert!(super::resolve_loaded(&[&found], Ok(entries.clone())).unwrap()[0].as_str() == "pass");
+
+        let error = super::resolve_loaded(&[&found, &missing], Ok(entries)).unwrap_err();
+        assert!(error.kind.exit_code() == 5);
+        let error = super::resolve_loaded(&[&found], Err("Wrong password or number".to_string())).unwrap_err();
+        assert!(error.kind.exit_code() == 6);
+        // The errors of the agent are passed on as they are
+        let error = super::resolve(&[&found], |_| Err(CliError::new(ErrorKind::Locked, "The agent is locked"))).unwrap_err();
+        assert!(error.kind == ErrorKind::Locked);
+
+        assert!(super::run_exec(&args(&["--env", "A=entry:a:pass"])).unwrap_err().kind.exit_code() == 2);
+        assert!(super::run_render(&[]).unwrap_err().kind.exit_code() == 2);
+    }
+}
@@ -0,0 +1,557 @@
+// Copyright 2017 astonbitecode
+// This file is part of rust-keylock password manager.
+//
+// rust-keylock is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// rust-keylock is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with rust-keylock.  If not, see <http://www.gnu.org/licenses/>.
+//! Data that the shell keeps for each `Entry`, on top of what the library supports: when it was created and modified,
+//! when its password should be rotated and its previous passwords.
+//!
+//! The library has no room for this data in an Entry, and the description belongs to the User: other clients present it
+//! as it is and the filter of the library matches it. So the shell keeps the data in a file of its own, next to the
+//! passwords file, by the name and the username of the Entries. The file is sealed with AES-256-GCM, under a key that is
+//! derived from the master password and the number with a random salt that is stored in the file. It is written after
+//! the library saves the passwords file. It is not exported or synchronised together with it.
+use std::collections::BTreeMap;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, MutexGuard};
+
+use chrono::TimeZone;
+use log::{error, warn};
+use openssl::base64;
+use openssl::hash::MessageDigest;
+use openssl::symm::{self, Cipher};
+use rust_keylock::Entry;
+use serde::{Deserialize, Serialize};
+use zeroize::Zeroizing;
+
+use crate::sync;
+
+/// Where the data of the Entries is kept
+const EXTRAS_FILENAME: &str = ".shell_extras";
+/// How many previous passwords are kept for each Entry
+pub(crate) const MAX_PASSWORD_HISTORY: usize = 10;
+const SECONDS_IN_A_DAY: i64 = 24 * 60 * 60;
+/// The iterations of PBKDF2 that derive the key of the data
+const KEY_ITERATIONS: usize = 100_000;
+const SALT_LENGTH: usize = 16;
+const NONCE_LENGTH: usize = 12;
+const TAG_LENGTH: usize = 16;
+
+/// The name and the username of an Entry
+type EntryId = (String, String);
+
+/// The timestamps are in seconds since the epoch
+#[derive(Debug, Default, PartialEq, Eq, Clone, Serialize, Deserialize)]
+pub(crate) struct EntryExtras {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) created_at: Option<i64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) modified_at: Option<i64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) password_changed_at: Option<i64>,
+    /// After how many days the password should be rotated
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) rotation_days: Option<u32>,
+    /// The previous passwords of the Entry, the most recent first
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub(crate) password_history: Vec<PreviousPassword>,
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Serialize, Deserialize)]
+pub(crate) struct PreviousPassword {
+    pub(crate) pass: String,
+    /// When the password was replaced, in seconds since the epoch
+    pub(crate) replaced_at: i64,
+}
+
+impl EntryExtras {
+    /// Keeps a password that is being replaced
+    pub(crate) fn push_password(&mut self, pass: &str, replaced_at: i64) {
+        if pass.is_empty() {
+            return;
+        }
+        self.password_history.insert(0, PreviousPassword { pass: pass.to_string(), replaced_at });
+        self.password_history.truncate(MAX_PASSWORD_HISTORY);
+    }
+
+    /// Returns true if the password should have been rotated at the given time
+    pub(crate) fn is_due_for_rotation(&self, now: i64) -> bool {
+        match (self.rotation_days, self.password_changed_at.or(self.created_at)) {
+            (Some(days), Some(changed_at)) => now - changed_at >= days as i64 * SECONDS_IN_A_DAY,
+            _ => false,
+        }
+    }
+
+    /// Marks the Entry as modified now. It is marked as created as well, if this is not known already.
+    pub(crate) fn touch(&mut self) {
+        let now = chrono::Utc::now().timestamp();
+        self.created_at = self.created_at.or(Some(now));
+        self.modified_at = Some(now);
+    }
+
+    /// Keeps the current password of the Entry in the history, before it gets replaced
+    pub(crate) fn retire_password(&mut self, pass: &str) {
+        let now = chrono::Utc::now().timestamp();
+        self.push_password(pass, now);
+        self.password_changed_at = Some(now);
+    }
+
+    /// Sets after how many days the password should be rotated. Zero disables the rotation.
+    pub(crate) fn set_rotation_days(&mut self, days: u32) {
+        if days == 0 {
+            self.rotation_days = None;
+        } else {
+            self.rotation_days = Some(days);
+            // Start counting from now, if it is not known when the password was set
+            self.password_changed_at = self.password_changed_at.or(self.created_at).or(Some(chrono::Utc::now().timestamp()));
+        }
+    }
+}
+
+/// The key of the data and the salt it was derived with
+struct StoreKey {
+    key: Zeroizing<[u8; 32]>,
+    salt: Vec<u8>,
+}
+
+/// Verifies the password and the number of the unlocked passwords file, without keeping them
+pub(crate) struct KeyCheck(StoreKey);
+
+impl KeyCheck {
+    pub(crate) fn new(password: &str, number: usize) -> Result<KeyCheck, String> {
+        new_key(password, number).map(KeyCheck)
+    }
+
+    pub(crate) fn matches(&self, password: &str, number: usize) -> bool {
+        derive_key(password, number, &self.0.salt)
+            .map(|key| openssl::memcmp::eq(&key[..], &self.0.key[..]))
+            .unwrap_or(false)
+    }
+}
+
+/// The data of all the Entries
+struct Store {
+    /// None while the file is not opened. The data is not known then: it does not change and the file is not written.
+    key: Option<StoreKey>,
+    extras: BTreeMap<EntryId, EntryExtras>,
+    /// The data as it was before the changes that the library did not apply yet, the oldest first
+    pending: Vec<(EntryId, Option<EntryExtras>)>,
+}
+
+static STORE: Mutex<Store> = Mutex::new(Store::closed());
+
+/// The file of the data
+#[derive(Serialize, Deserialize)]
+struct SealedFile {
+    /// base64 of the salt of the key
+    salt: String,
+    /// base64 of the nonce, the ciphertext and the tag of the data
+    sealed: String,
+}
+
+impl Store {
+    const fn closed() -> Store {
+        Store { key: None, extras: BTreeMap::new(), pending: Vec::new() }
+    }
+
+    /// Opens the file with the master password and the number that unlock the passwords file. Without a file, the data
+    /// starts empty.
+    fn open(path: &Path, password: &str, number: usize) -> Result<Store, String> {
+        let contents = match fs::read_to_string(path) {
+            Ok(contents) => contents,
+            Err(error) if error.kind() == io::ErrorKind::NotFound => {
+                return Ok(Store { key: Some(new_key(password, number)?), ..Store::closed() });
+            }
+            Err(error) => return Err(format!("{}: {}", path.display(), error)),
+        };
+        let file: SealedFile = toml::from_str(&contents).map_err(|error| format!("{}: {}", path.display(), error))?;
+        let salt = base64::decode_block(&file.salt).map_err(|_| format!("{}: the salt is not valid base64", path.display()))?;
+        let key = derive_key(password, number, &salt)?;
+        let plain = open_sealed(&key, &file.sealed).ok_or_else(|| format!("{} cannot be opened with this password and number", path.display()))?;
+        let extras: Vec<(EntryId, EntryExtras)> = serde_json::from_slice(&plain).map_err(|error| format!("{}: {}", path.display(), error))?;
+        Ok(Store { key: Some(StoreKey { key, salt }), extras: extras.into_iter().collect(), pending: Vec::new() })
+    }
+
+    /// Writes the file, if it is opened
+    fn save(&self, path: &Path) -> Result<(), String> {
+        let key = match &self.key {
+            Some(key) => key,
+            None => return Ok(()),
+        };
+        let extras: Vec<(&EntryId, &EntryExtras)> = self.extras.iter().collect();
+        let plain = Zeroizing::new(serde_json::to_vec(&extras).map_err(|error| error.to_string())?);
+        let sealed = seal(&key.key, &plain).ok_or_else(|| "Could not seal the data of the Entries".to_string())?;
+        let contents = toml::to_string(&SealedFile { salt: base64::encode_block(&key.salt), sealed }).map_err(|error| error.to_string())?;
+        sync::write_atomically(path, contents.as_bytes())
+    }
+
+    fn get(&self, entry: &Entry) -> EntryExtras {
+        self.extras.get(&id(entry)).cloned().unwrap_or_default()
+    }
+
+    fn set(&mut self, entry: &Entry, extras: EntryExtras) {
+        if self.key.is_none() {
+            warn!("The data of Entry '{}' does not change, as the data of the Entries could not be opened", entry.name);
+            return;
+        }
+        let id = id(entry);
+        let previous = if extras == EntryExtras::default() {
+            self.extras.remove(&id)
+        } else {
+            self.extras.insert(id.clone(), extras)
+        };
+        self.pending.push((id, previous));
+    }
+
+    /// Undoes the pending changes
+    fn discard(&mut self) {
+        while let Some((id, previous)) = self.pending.pop() {
+            match previous {
+                Some(extras) => self.extras.insert(id, extras),
+                None => self.extras.remove(&id),
+            };
+        }
+    }
+
+    fn retain(&mut self, entries: &[Entry]) {
+        self.extras.retain(|id, _| entries.iter().any(|entry| entry.name == id.0 && entry.user == id.1));
+    }
+}
+
+fn id(entry: &Entry) -> EntryId {
+    (entry.name.clone(), entry.user.clone())
+}
+
+fn extras_path() -> PathBuf {
+    rust_keylock::default_rustkeylock_location().join(EXTRAS_FILENAME)
+}
+
+fn lock_store() -> Option<MutexGuard<'static, Store>> {
+    STORE.lock().map_err(|error| error!("Could not access the data of the Entries: {:?}", error)).ok()
+}
+
+/// Opens the data of the Entries with the master password and the number that unlock the passwords file
+pub(crate) fn open(password: &str, number: usize) {
+    let store = Store::open(&extras_path(), password, number).unwrap_or_else(|error| {
+        warn!("Could not open the data of the Entries. It is not presented and does not change. The error was: {}", error);
+        Store::closed()
+    });
+    if let Some(mut current) = lock_store() {
+        *current = store;
+    }
+}
+
+/// Uses a key of a new master password for the data. The file is written with it the next time the passwords file is
+/// saved.
+pub(crate) fn change_key(password: &str, number: usize) {
+    match new_key(password, number) {
+        Ok(key) => {
+            if let Some(mut store) = lock_store() {
+                store.key = Some(key);
+            }
+        }
+        Err(error) => error!("Could not change the key of the data of the Entries: {}", error),
+    }
+}
+
+/// Writes the data of the Entries, after the library saved the passwords file
+pub(crate) fn save() {
+    if let Some(store) = lock_store() {
+        if let Err(error) = store.save(&extras_path()) {
+            error!("Could not save the data of the Entries: {}", error);
+        }
+    }
+}
+
+/// Forgets the data of the Entries and its key
+pub(crate) fn close() {
+    if let Some(mut store) = lock_store() {
+        *store = Store::closed();
+    }
+}
+
+/// Returns the data of the Entry
+pub(crate) fn of(entry: &Entry) -> EntryExtras {
+    lock_store().map(|store| store.get(entry)).unwrap_or_default()
+}
+
+/// Keeps the data of the Entry. The change is pending until it is confirmed, as the library may still reject the Entry.
+pub(crate) fn set(entry: &Entry, extras: EntryExtras) {
+    if let Some(mut store) = lock_store() {
+        store.set(entry, extras);
+    }
+}
+
+/// The library applied the pending changes
+pub(crate) fn confirm() {
+    if let Some(mut store) = lock_store() {
+        store.pending.clear();
+    }
+}
+
+/// The library rejected the pending changes
+pub(crate) fn discard() {
+    if let Some(mut store) = lock_store() {
+        store.discard();
+    }
+}
+
+/// Forgets the data of the Entries that do not exist anymore. The `entries` must be all the Entries.
+pub(crate) fn retain(entries: &[Entry]) {
+    if let Some(mut store) = lock_store() {
+        store.retain(entries);
+    }
+}
+
+/// Marks the Entry as modified now
+pub(crate) fn touch(entry: &Entry) {
+    let mut extras = of(entry);
+    extras.touch();
+    set(entry, extras);
+}
+
+/// Returns true if the password of the Entry should have been rotated by now
+pub(crate) fn is_due_for_rotation(entry: &Entry) -> bool {
+    of(entry).is_due_for_rotation(chrono::Utc::now().timestamp())
+}
+
+/// Changes the password of the Entry, keeping the previous one in the history
+pub(crate) fn change_password(entry: &mut Entry, extras: &mut EntryExtras, pass: String) {
+    if entry.pass != pass {
+        extras.retire_password(&entry.pass);
+        entry.pass = pass;
+    }
+}
+
+/// Makes the password with the given index in the history the current password of the Entry
+pub(crate) fn restore_password(entry: &mut Entry, extras: &mut EntryExtras, index: usize) {
+    if index < extras.password_history.len() {
+        let previous = extras.password_history.remove(index);
+        extras.retire_password(&entry.pass);
+        entry.pass = previous.pass;
+    }
+}
+
+/// Derives the key of the data. The number is part of the salt, as it is part of what unlocks the passwords file.
+fn derive_key(password: &str, number: usize, salt: &[u8]) -> Result<Zeroizing<[u8; 32]>, String> {
+    let mut key = Zeroizing::new([0u8; 32]);
+    let salt = [salt, number.to_string().as_bytes()].concat();
+    openssl::pkcs5::pbkdf2_hmac(password.as_bytes(), &salt, KEY_ITERATIONS, MessageDigest::sha256(), &mut key[..])
+        .map_err(|error| format!("Could not derive the key of the data of the Entries: {}", error))?;
+    Ok(key)
+}
+
+/// Derives a key with a new random salt
+fn new_key(password: &str, number: usize) -> Result<StoreKey, String> {
+    let mut salt = vec![0u8; SALT_LENGTH];
+    openssl::rand::rand_bytes(&mut salt).map_err(|error| format!("Could not create a salt: {}", error))?;
+    Ok(StoreKey { key: derive_key(password, number, &salt)?, salt })
+}
+
+/// Seals as base64 of the nonce, the ciphertext and the tag
+fn seal(key: &[u8; 32], plain: &[u8]) -> Option<String> {
+    let mut nonce = [0u8; NONCE_LENGTH];
+    openssl::rand::rand_bytes(&mut nonce).ok()?;
+    let mut tag = [0u8; TAG_LENGTH];
+    let ciphertext = symm::encrypt_aead(Cipher::aes_256_gcm(), key, Some(&nonce), &[], plain, &mut tag).ok()?;
+    Some(base64::encode_block(&[&nonce[..], &ciphertext, &tag].concat()))
+}
+
+fn open_sealed(key: &[u8; 32], sealed: &str) -> Option<Zeroizing<Vec<u8>>> {
+    let bytes = base64::decode_block(sealed).ok()?;
+    if bytes.len() < NONCE_LENGTH + TAG_LENGTH {
+        return None;
+    }
+    let (nonce, rest) = bytes.split_at(NONCE_LENGTH);
+    let (ciphertext, tag) = rest.split_at(rest.len() - TAG_LENGTH);
+    symm::decrypt_aead(Cipher::aes_256_gcm(), key, Some(nonce), &[], ciphertext, tag).ok().map(Zeroizing::new)
+}
+
+/// Formats seconds since the epoch as a local date and time
+pub(crate) fn format_timestamp_opt(timestamp: Option<i64>) -> String {
+    timestamp.map(format_timestamp).unwrap_or_else(|| "-".to_string())
+}
+
+/// Formats seconds since the epoch as a local date and time
+pub(crate) fn format_timestamp(timestamp: i64) -> String {
+    match chrono::Local.timestamp_opt(timestamp, 0).single() {
+        Some(date_time) => date_time.format("%Y-%m-%d %H:%M").to_string(),
+        None => "-".to_string(),
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod test_extras {
+    use std::fs;
+    use std::path::PathBuf;
+
+    use rust_keylock::{Entry, EntryMeta};
+    use zeroize::Zeroizing;
+
+    use super::{EntryExtras, PreviousPassword, Store, StoreKey, STORE};
+
+    fn entry(name: &str) -> Entry {
+        Entry::new(name.to_string(), "url".to_string(), "user".to_string(), "pass".to_string(), "desc".to_string(), EntryMeta::default())
+    }
+
+    fn temp_path(name: &str) -> PathBuf {
+        let path = std::env::temp_dir().join(format!("rust-keylock-shell-test-extras-{}-{}", name, std::process::id()));
+        let _ = fs::remove_file(&path);
+        path
+    }
+
+    fn with_history(pass: &str) -> EntryExtras {
+        EntryExtras { password_history: vec![PreviousPassword { pass: pass.to_string(), replaced_at: 10 }], ..Default::default() }
+    }
+
+    /// Opens the data of the Entries that the tests use, with a key that is not derived
+    pub(crate) fn open_store() {
+        let mut store = STORE.lock().unwrap();
+        if store.key.is_none() {
+            store.key = Some(StoreKey { key: Zeroizing::new([7u8; 32]), salt: Vec::new() });
+        }
+    }
+
+    #[test]
+    fn change_and_restore_password() {
+        let mut entry = entry("name");
+        let mut extras = EntryExtras::default();
+        super::change_password(&mut entry, &mut extras, "pass".to_string());
+        assert!(extras == EntryExtras::default());
+
+        super::change_password(&mut entry, &mut extras, "newpass".to_string());
+        assert!(entry.pass == "newpass");
+        assert!(extras.password_history.len() == 1);
+        assert!(extras.password_history[0].pass == "pass");
+        assert!(extras.password_changed_at.is_some());
+
+        super::restore_password(&mut entry, &mut extras, 0);
+        assert!(entry.pass == "pass");
+        assert!(extras.password_history.len() == 1);
+        assert!(extras.password_history[0].pass == "newpass");
+    }
+
+    #[test]
+    fn rotation() {
+        let mut extras = EntryExtras { password_changed_at: Some(1000), ..Default::default() };
+        assert!(!extras.is_due_for_rotation(1000 + 100 * super::SECONDS_IN_A_DAY));
+        extras.rotation_days = Some(90);
+        assert!(!extras.is_due_for_rotation(1000 + 89 * super::SECONDS_IN_A_DAY));
+        assert!(extras.is_due_for_rotation(1000 + 90 * super::SECONDS_IN_A_DAY));
+
+        let mut extras = EntryExtras::default();
+        extras.set_rotation_days(90);
+        assert!(extras.rotation_days == Some(90));
+        assert!(extras.password_changed_at.is_some());
+        assert!(!extras.is_due_for_rotation(chrono::Utc::now().timestamp()));
+        extras.set_rotation_days(0);
+        assert!(extras.rotation_days.is_none());
+    }
+
+    #[test]
+    fn password_history_is_bounded() {
+        let mut entry = entry("name");
+        let mut extras = EntryExtras::default();
+        for i in 0..super::MAX_PASSWORD_HISTORY + 5 {
+            super::change_password(&mut entry, &mut extras, format!("pass{}", i));
+        }
+        assert!(extras.password_history.len() == super::MAX_PASSWORD_HISTORY);
+        assert!(extras.password_history[0].pass == format!("pass{}", super::MAX_PASSWORD_HISTORY + 3));
+    }
+
+    #[test]
+    fn data_is_sealed_with_a_salt_of_its_own() {
+        let (path, other_path) = (temp_path("sealed"), temp_path("sealed-other"));
+        let mut store = Store::open(&path, "password", 33).unwrap();
+        store.set(&entry("mail"), with_history("oldpass"));
+        store.save(&path).unwrap();
+        let mut other = Store::open(&other_path, "password", 33).unwrap();
+        other.set(&entry("mail"), with_history("oldpass"));
+        other.save(&other_path).unwrap();
+
+        let (contents, other_contents) = (fs::read_to_string(&path).unwrap(), fs::read_to_string(&other_path).unwrap());
+        assert!(!contents.contains("oldpass") && !contents.contains("mail"));
+        assert!(contents.lines().next() != other_contents.lines().next());
+
+        assert!(Store::open(&path, "password", 33).unwrap().get(&entry("mail")) == with_history("oldpass"));
+        assert!(Store::open(&path, "password", 34).is_err());
+        assert!(Store::open(&path, "other", 33).is_err());
+        let _ = fs::remove_file(&path);
+        let _ = fs::remove_file(&other_path);
+    }
+
+    #[test]
+    fn changes_without_the_key_keep_the_history() {
+        let path = temp_path("without-key");
+        let mut store = Store::open(&path, "password", 33).unwrap();
+        store.set(&entry("mail"), with_history("oldpass"));
+        store.save(&path).unwrap();
+        let saved = fs::read(&path).unwrap();
+
+        // The file was not opened, so nothing changes
+        let mut store = Store::open(&path, "wrong", 33).unwrap_or_else(|_| Store::closed());
+        let mut extras = store.get(&entry("mail"));
+        extras.touch();
+        store.set(&entry("mail"), extras);
+        store.save(&path).unwrap();
+        assert!(fs::read(&path).unwrap() == saved);
+        assert!(Store::open(&path, "password", 33).unwrap().get(&entry("mail")) == with_history("oldpass"));
+        let _ = fs::remove_file(&path);
+    }
+
+    #[test]
+    fn key_of_a_new_master_password() {
+        let path = temp_path("change-key");
+        let mut store = Store::open(&path, "password", 33).unwrap();
+        store.set(&entry("mail"), with_history("oldpass"));
+        store.key = Some(super::new_key("new password", 34).unwrap());
+        store.save(&path).unwrap();
+        assert!(Store::open(&path, "password", 33).is_err());
+        assert!(Store::open(&path, "new password", 34).unwrap().get(&entry("mail")) == with_history("oldpass"));
+        let _ = fs::remove_file(&path);
+    }
+
+    #[test]
+    fn key_check() {
+        let check = super::KeyCheck::new("password", 33).unwrap();
+        assert!(check.matches("password", 33));
+        assert!(!check.matches("password", 34));
+        assert!(!check.matches("Password", 33));
+    }
+
+    #[test]
+    fn pending_changes() {
+        let mut store = Store { key: Some(StoreKey { key: Zeroizing::new([1u8; 32]), salt: Vec::new() }), ..Store::closed() };
+        store.set(&entry("mail"), with_history("first"));
+        store.pending.clear();
+        store.set(&entry("mail"), with_history("second"));
+        store.set(&entry("bank"), with_history("third"));
+        store.discard();
+        assert!(store.get(&entry("mail")) == with_history("first"));
+        assert!(store.get(&entry("bank")) == EntryExtras::default());
+
+        store.retain(&[entry("bank")]);
+        assert!(store.get(&entry("mail")) == EntryExtras::default());
+    }
+
+    #[test]
+    fn sealed_data_needs_the_key() {
+        let sealed = super::seal(&[1u8; 32], b"old").unwrap();
+        assert!(!sealed.contains("old"));
+        assert!(super::open_sealed(&[1u8; 32], &sealed).map(|plain| plain.to_vec()) == Some(b"old".to_vec()));
+        assert!(super::open_sealed(&[2u8; 32], &sealed).is_none());
+        assert!(super::open_sealed(&[1u8; 32], "dG9vIHNob3J0").is_none());
+    }
+}
@@ -0,0 +1,756 @@
+// Copyright 2017 astonbitecode
+// This file is part of rust-keylock password manager.
+//
+// rust-keylock is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// rust-keylock is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with rust-keylock.  If not, see <http://www.gnu.org/licenses/>.
+//! The git credential helper mode: `rust-keylock git-credential get|store|erase`.
+//!
+//! Git describes the credential on stdin, as `key=value` lines. The helper unlocks the passwords file, finds the Entry
+//! whose URL and username match and answers on stdout. The prompts for the password and the number go to the terminal,
+//! because stdin and stdout are used by the protocol.
+//!
+//! The library is driven by an `Editor` that answers every step without any User interaction.
+use std::io::BufRead;
+use std::sync::{Arc, Mutex};
+
+use log::{debug, error, info};
+use rust_keylock::dropbox::DropboxConfiguration;
+use rust_keylock::nextcloud::NextcloudConfiguration;
+use rust_keylock::{Editor, Entry, EntryMeta, EntryPresentationType, Menu, MessageSeverity, UserOption, UserSelection};
+use url::Url;
+use zeroize::Zeroizing;
+
+use crate::cli::{CliError, ErrorKind};
+use crate::extras;
+use crate::key_source;
+
+/// How many times the User may provide the password before the helper gives up
+const MAX_UNLOCK_ATTEMPTS: usize = 3;
+/// How the library tells that the passwords file was saved
+const SAVED_MESSAGE: &str = "Encrypted and saved successfully!";
+/// How the library asks whether a leaked password should be kept
+const LEAKED_PASSWORD_MESSAGE: &str = "The password you provided has been leaked and is not safe. Are you sure you want to use it?";
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub(crate) enum Operation {
+    Get,
+    Store,
+    Erase,
+}
+
+impl Operation {
+    /// Returns None for operations that are not supported. Git expects the helpers to ignore them.
+    pub(crate) fn parse(operation: &str) -> Option<Operation> {
+        match operation {
+            "get" => Some(Operation::Get),
+            "store" => Some(Operation::Store),
+            "erase" => Some(Operation::Erase),
+            _ => None,
+        }
+    }
+
+    pub(crate) fn name(&self) -> &'static str {
+        match self {
+            Operation::Get => "get",
+            Operation::Store => "store",
+            Operation::Erase => "erase",
+        }
+    }
+}
+
+/// A credential, as described by git
+#[derive(Debug, Default, PartialEq, Eq, Clone)]
+pub(crate) struct CredentialRequest {
+    pub(crate) protocol: Option<String>,
+    pub(crate) host: Option<String>,
+    pub(crate) path: Option<String>,
+    pub(crate) username: Option<String>,
+    pub(crate) password: Option<Zeroizing<String>>,
+}
+
+impl CredentialRequest {
+    /// Parses the `key=value` lines that git sends, until an empty line or the end of the input
+    pub(crate) fn parse<R: BufRead>(input: R) -> Result<CredentialRequest, String> {
+        let mut request = CredentialRequest::default();
+        for line in input.lines() {
+            let line = Zeroizing::new(line.map_err(|error| format!("Could not read the credential: {}", error))?);
+            let line = line.trim_end_matches('\r');
+            if line.is_empty() {
+                break;
+            }
+            let (key, value) = line.split_once('=')
+                .ok_or_else(|| format!("Invalid line in the credential: '{}'", line.split('=').next().unwrap_or("")))?;
+            match key {
+                "protocol" => request.protocol = Some(value.to_string()),
+                "host" => request.host = Some(value.to_string()),
+                "path" => request.path = Some(value.to_string()),
+                "username" => request.username = Some(value.to_string()),
+                "password" => request.password = Some(Zeroizing::new(value.to_string())),
+                "url" => request.set_url(value)?,
+                // Git may send more attributes in the future. The helpers should ignore what they do not know.
+                other => debug!("Ignoring the credential attribute '{}'", other),
+            }
+        }
+        if request.host.is_none() {
+            return Err("The credential does not have a host".to_string());
+        }
+        Ok(request)
+    }
+
+    /// Formats the credential as the `key=value` lines that git sends
+    pub(crate) fn to_git(&self) -> Zeroizing<String> {
+        let mut lines = Zeroizing::new(String::new());
+        let attributes = [("protocol", self.protocol.as_deref()),
+            ("host", self.host.as_deref()),
+            ("path", self.path.as_deref()),
+            ("username", self.username.as_deref()),
+            ("password", self.password.as_ref().map(|password| password.as_str()))];
+        for (key, value) in attributes {
+            if let Some(value) = value {
+                lines.push_str(&format!("{}={}\n", key, value));
+            }
+        }
+        lines
+    }
+
+    fn set_url(&mut self, url: &str) -> Result<(), String> {
+        let parsed = Url::parse(url).map_err(|error| format!("Invalid url in the credential: {}", error))?;
+        self.protocol = Some(parsed.scheme().to_string());
+        self.host = host_with_port(&parsed);
+        let path = parsed.path().trim_start_matches('/');
+        if !path.is_empty() {
+            self.path = Some(path.to_string());
+        }
+        if !parsed.username().is_empty() {
+            self.username = Some(parsed.username().to_string());
+        }
+        if let Some(password) = parsed.password() {
+            self.password = Some(Zeroizing::new(password.to_string()));
+        }
+        Ok(())
+    }
+
+    /// Returns the index of the Entry that matches the credential best.
+    ///
+    /// The host and the protocol must match, as well as the username, if git knows it.
+    /// If git sends a path, an Entry with the same path is preferred over one that has no path.
+    pub(crate) fn find_match(&self, entries: &[Entry]) -> Option<usize> {
+        let mut best: Option<(usize, usize)> = None;
+        for (index, entry) in entries.iter().enumerate() {
+            if let Some(score) = self.match_score(entry) {
+                if best.map(|(_, best_score)| score > best_score).unwrap_or(true) {
+                    best = Some((index, score));
+                }
+            }
+        }
+        best.map(|(index, _)| index)
+    }
+
+    fn match_score(&self, entry: &Entry) -> Option<usize> {
+        let protocol = self.protocol.as_deref().unwrap_or("https");
+        let url = Url::parse(entry.url.trim()).ok()
+            .filter(|url| url.host_str().is_some())
+            .or_else(|| Url::parse(&format!("{}://{}", protocol, entry.url.trim())).ok())?;
+        if !url.scheme().eq_ignore_ascii_case(protocol) || !host_with_port(&url)?.eq_ignore_ascii_case(self.host.as_ref()?) {
+            return None;
+        }
+        if let Some(username) = &self.username {
+            if &entry.user != username {
+                return None;
+            }
+        }
+        let entry_path = normalize_path(url.path());
+        match self.path.as_deref().map(normalize_path) {
+            Some(path) if path == entry_path => Some(2),
+            Some(_) if !entry_path.is_empty() => None,
+            _ => Some(1),
+        }
+    }
+
+    /// Creates an Entry for the credential
+    pub(crate) fn new_entry(&self) -> Entry {
+        let host = self.host.clone().unwrap_or_default();
+        let location = match self.path.as_deref().map(normalize_path) {
+            Some(path) if !path.is_empty() => format!("{}/{}", host, path),
+            _ => host,
+        };
+        let url = format!("{}://{}", self.protocol.as_deref().unwrap_or("https"), location);
+        let password = self.password.as_ref().map(|password| password.to_string()).unwrap_or_default();
+        let entry = Entry::new(location,
+                                   url,
+                                   self.username.clone().unwrap_or_default(),
+                                   password,
+                                   "Stored by git".to_string(),
+                                   EntryMeta::default());
+        extras::touch(&entry);
+        entry
+    }
+}
+
+/// Formats the username and the password of the Entry as an answer to git
+pub(crate) fn format_response(entry: &Entry) -> Result<Zeroizing<String>, String> {
+    if entry.user.contains('\n') || entry.pass.contains('\n') {
+        return Err(format!("The Entry '{}' cannot be passed to git, because it contains new lines", entry.name));
+    }
+    Ok(Zeroizing::new(format!("username={}\npassword={}\n", entry.user, entry.pass)))
+}
+
+fn host_with_port(url: &Url) -> Option<String> {
+    let host = url.host_str()?;
+    Some(match url.port() {
+        Some(port) => format!("{}:{}", host, port),
+        None => host.to_string(),
+    })
+}
+
+fn normalize_path(path: &str) -> String {
+    let path = path.trim_matches('/');
+    path.strip_suffix(".git").unwrap_or(path).to_string()
+}
+
+/// Runs a git credential operation. Operations that are not supported are ignored.
+pub(crate) fn run<R: BufRead>(operation: &str, input: R) -> Result<(), CliError> {
+    let operation = match Operation::parse(operation) {
+        Some(operation) => operation,
+        None => {
+            info!("Ignoring the unsupported git credential operation '{}'", operation);
+            return Ok(());
+        }
+    };
+    let request = CredentialRequest::parse(input)?;
+    if operation == Operation::Erase && request.password.is_none() {
+        // Only the credential that git rejected is erased, and git tells its password
+        info!("Not erasing a credential without a password");
+        return Ok(());
+    }
+    #[cfg(unix)]
+    {
+        // The agent answers and changes the passwords file without unlocking it again
+        if let Ok(listed) = crate::client::ls("") {
+            return run_with_agent(operation, &request, &listed);
+        }
+    }
+    let response = execute(operation, request, None)?;
+    #[cfg(unix)]
+    {
+        if operation != Operation::Get {
+            crate::client::reload();
+        }
+    }
+    if let Some(response) = &response {
+        print!("{}", response.as_str());
+    }
+    Ok(())
+}
+
+/// Stores or erases the credential, given as the `key=value` lines of git, with the password and the number of the
+/// passwords file. The agent uses it for its clients.
+pub(crate) fn apply(operation: Operation, credential: &str, password: &str, number: usize) -> Result<(), CliError> {
+    let request = CredentialRequest::parse(credential.as_bytes())?;
+    execute(operation, request, Some((Zeroizing::new(password.to_string()), number))).map(|_| ())
+}
+
+/// Runs the library for the operation. Returns the answer to git, if any.
+fn execute(operation: Operation, request: CredentialRequest, key: Option<(Zeroizing<String>, usize)>) -> Result<Option<Zeroizing<String>>, CliError> {
+    let outcome = Arc::new(Mutex::new(Outcome::default()));
+    let mut editor = CredentialEditor::new(operation, request, outcome.clone(), ask_tty);
+    editor.key = key;
+    rust_keylock::execute(Box::new(editor));
+
+    let mut outcome = outcome.lock().map_err(|error| format!("Could not access the outcome: {:?}", error))?;
+    match &outcome.error {
+        Some(error) => Err(error.clone()),
+        None => Ok(outcome.response.take()),
+    }
+}
+
+#[cfg(unix)]
+fn run_with_agent(operation: Operation, request: &CredentialRequest, listed: &[crate::agent::AgentEntry]) -> Result<(), CliError> {
+    let found = find_in_agent(request, listed)?;
+    match operation {
+        Operation::Get => {
+            // An erased credential has no password
+            if let Some(found) = found.filter(|found| !found.pass.is_empty()) {
+                let entry = Entry::new(found.name.clone(), found.url.clone(), found.user.clone(), found.pass.clone(), String::new(), EntryMeta::default());
+                print!("{}", format_response(&entry)?.as_str());
+            }
+            Ok(())
+        }
+        Operation::Store if found.map(|found| Some(found.pass.as_str()) == request.password.as_deref().map(String::as_str)).unwrap_or(false) => {
+            debug!("The credential is stored already");
+            Ok(())
+        }
+        _ => crate::client::credential(operation.name(), &request.to_git()),
+    }
+}
+
+/// Finds the credential in the Entries that the agent listed and gets it, with its password, from the agent
+#[cfg(unix)]
+fn find_in_agent(request: &CredentialRequest, listed: &[crate::agent::AgentEntry]) -> Result<Option<crate::agent::AgentEntry>, CliError> {
+    let entries: Vec<Entry> = listed.iter()
+        .map(|listed| Entry::new(listed.name.clone(), listed.url.clone(), listed.user.clone(), String::new(), String::new(), EntryMeta::default()))
+        .collect();
+    match request.find_match(&entries) {
+        Some(index) => crate::client::get(&listed[index].name, Some(&listed[index].user)).map(Some),
+        None => Ok(None),
+    }
+}
+
+fn ask_tty(prompt: &str) -> String {
+    rpassword::prompt_password(prompt).unwrap_or_else(|error| {
+        error!("Could not read from the terminal: {}", error);
+        String::new()
+    })
+}
+
+/// What the helper did, for the caller of the library
+#[derive(Default)]
+struct Outcome {
+    response: Option<Zeroizing<String>>,
+    error: Option<CliError>,
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+enum Stage {
+    Unlocking,
+    Lookup,
+    Saving,
+    Done,
+}
+
+struct State {
+    stage: Stage,
+    attempts: usize,
+}
+
+struct CredentialEditor {
+    operation: Operation,
+    request: CredentialRequest,
+    outcome: Arc<Mutex<Outcome>>,
+    state: Mutex<State>,
+    ask: fn(&str) -> String,
+    /// Opens the data that the shell keeps for the Entries, with the credentials that unlock the passwords file
+    open_extras: fn(&str, usize),
+    /// The password and the number of the passwords file, if they are known already
+    key: Option<(Zeroizing<String>, usize)>,
+}
+
+impl CredentialEditor {
+    fn new(operation: Operation,
+           request: CredentialRequest,
+           outcome: Arc<Mutex<Outcome>>,
+           ask: fn(&str) -> String) -> CredentialEditor {
+        CredentialEditor {
+            operation,
+            request,
+            outcome,
+            state: Mutex::new(State { stage: Stage::Unlocking, attempts: 0 }),
+            ask,
+            open_extras: extras::open,
+            key: None,
+        }
+    }
+
+    fn update_outcome<F>(&self, f: F) where F: FnOnce(&mut Outcome) {
+        match self.outcome.lock() {
+            Ok(mut outcome) => f(&mut outcome),
+            Err(error) => error!("Could not access the outcome: {:?}", error),
+        }
+    }
+
+    fn fail(&self, error: CliError) -> UserSelection {
+        self.update_outcome(|outcome| outcome.error = Some(error));
+        UserSelection::GoTo(Menu::Exit)
+    }
+
+    fn set_stage(&self, stage: Stage) {
+        if let Ok(mut state) = self.state.lock() {
+            state.stage = stage;
+        }
+    }
+
+    fn stage(&self) -> Stage {
+        self.state.lock().map(|state| state.stage).unwrap_or(Stage::Done)
+    }
+
+    fn ask_password(&self) -> Result<(Zeroizing<String>, usize), String> {
+        if let Some((password, number)) = &self.key {
+            return Ok((password.clone(), *number));
+        }
+        let ask_number = || {
+            let number = Zeroizing::new((self.ask)("rust-keylock favorite number: "));
+            number.trim().parse::<usize>().map_err(|_| "A password and a numeric favorite number are needed".to_string())
+        };
+        if let Some(provided) = key_source::provided_key(&ask_number) {
+            return provided;
+        }
+        let password = Zeroizing::new((self.ask)("rust-keylock password: "));
+        let number = ask_number()?;
+        if password.is_empty() {
+            Err("A password and a numeric favorite number are needed".to_string())
+        } else {
+            Ok((password, number))
+        }
+    }
+
+    fn lookup(&self, entries: &[Entry]) -> UserSelection {
+        let found = self.request.find_match(entries);
+        debug!("git credential {:?}: matching Entry index {:?}", self.operation, found);
+        match (self.operation, found) {
+            (Operation::Get, Some(index)) => UserSelection::GoTo(Menu::ShowEntry(index)),
+            (Operation::Store, Some(index)) => UserSelection::GoTo(Menu::EditEntry(index)),
+            (Operation::Store, None) => {
+                if self.request.username.is_none() || self.request.password.is_none() {
+                    return self.fail(CliError::new(ErrorKind::Other, "git did not provide a username and a password to store"));
+                }
+                self.set_stage(Stage::Saving);
+                UserSelection::NewEntry(self.request.new_entry())
+            }
+            // Only the password is erased, and only if it is the one that git rejected
+            (Operation::Erase, Some(index)) if self.request.password.is_some() => UserSelection::GoTo(Menu::EditEntry(index)),
+            _ => UserSelection::GoTo(Menu::Exit),
+        }
+    }
+}
+
+impl Editor for CredentialEditor {
+    fn show_password_enter(&self) -> UserSelection {
+        let mut state = match self.state.lock() {
+            Ok(state) => state,
+            Err(_) => return UserSelection::GoTo(Menu::Exit),
+        };
+        // Called again only if the previous attempt failed
+        if state.attempts > 0 {
+            eprintln!("Wrong password or number");
+        }
+        // A known key is not tried again
+        if state.attempts >= MAX_UNLOCK_ATTEMPTS || (state.attempts > 0 && self.key.is_some()) {
+            drop(state);
+            return self.fail(CliError::new(ErrorKind::Unlock, "Could not unlock the passwords file"));
+        }
+        state.attempts += 1;
+
+        drop(state);
+        match self.ask_password() {
+            Ok((password, number)) => {
+                (self.open_extras)(&password, number);
+                UserSelection::new_provided_password(password.to_string(), number)
+            }
+            Err(error) => self.fail(CliError::new(ErrorKind::Unlock, &error)),
+        }
+    }
+
+    fn show_change_password(&self) -> UserSelection {
+        self.fail(CliError::new(ErrorKind::Unlock, "No passwords file was found. Please run rust-keylock interactively first, in order to create it."))
+    }
+
+    fn show_menu(&self, _menu: &Menu) -> UserSelection {
+        UserSelection::GoTo(Menu::Exit)
+    }
+
+    fn show_entries(&self, entries: Vec<Entry>, _filter: String) -> UserSelection {
+        match self.stage() {
+            Stage::Unlocking | Stage::Lookup => {
+                self.set_stage(Stage::Lookup);
+                self.lookup(&entries)
+            }
+            Stage::Saving => {
+                self.set_stage(Stage::Done);
+                UserSelection::GoTo(Menu::Save(false))
+            }
+            Stage::Done => UserSelection::GoTo(Menu::Exit),
+        }
+    }
+
+    fn show_entry(&self, entry: Entry, index: usize, presentation_type: EntryPresentationType) -> UserSelection {
+        let same_password = self.request.password.as_ref().map(|password| password.as_str() == entry.pass);
+        match presentation_type {
+            // An erased credential has no password. Git asks for one then.
+            EntryPresentationType::View if entry.pass.is_empty() => {
+                self.set_stage(Stage::Done);
+                UserSelection::GoTo(Menu::Exit)
+            }
+            EntryPresentationType::View => {
+                match format_response(&entry) {
+                    Ok(response) => {
+                        self.update_outcome(|outcome| outcome.response = Some(response));
+                        self.set_stage(Stage::Done);
+                        UserSelection::GoTo(Menu::Exit)
+                    }
+                    Err(message) => self.fail(CliError::from(message)),
+                }
+            }
+            EntryPresentationType::Edit => {
+                match (self.operation, same_password, &self.request.password) {
+                    (Operation::Store, Some(false), Some(password)) => {
+                        let mut entry = entry;
+                        let mut entry_extras = extras::of(&entry);
+                        extras::change_password(&mut entry, &mut entry_extras, password.to_string());
+                        entry_extras.touch();
+                        extras::set(&entry, entry_extras);
+                        self.set_stage(Stage::Saving);
+                        UserSelection::ReplaceEntry(index, entry)
+                    }
+                    // The rest of the Entry stays, and the erased password goes to its history
+                    (Operation::Erase, Some(true), _) => {
+                        let mut entry = entry;
+                        let mut entry_extras = extras::of(&entry);
+                        extras::change_password(&mut entry, &mut entry_extras, String::new());
+                        entry_extras.touch();
+                        extras::set(&entry, entry_extras);
+                        self.set_stage(Stage::Saving);
+                        UserSelection::ReplaceEntry(index, entry)
+                    }
+                    _ => UserSelection::GoTo(Menu::Exit),
+                }
+            }
+            // Entries are never deleted, only their password is erased
+            EntryPresentationType::Delete => UserSelection::GoTo(Menu::Exit),
+        }
+    }
+
+    fn exit(&self, _contents_changed: bool) -> UserSelection {
+        UserSelection::GoTo(Menu::ForceExit)
+    }
+
+    fn show_configuration(&self, _nextcloud: NextcloudConfiguration, _dropbox: DropboxConfiguration) -> UserSelection {
+        UserSelection::GoTo(Menu::Exit)
+    }
+
+    fn show_message(&self, message: &str, options: Vec<UserOption>, severity: MessageSeverity) -> UserSelection {
+        if message == SAVED_MESSAGE {
+            extras::save();
+        }
+        if message == LEAKED_PASSWORD_MESSAGE && self.operation == Operation::Erase {
+            // The empty password that replaces the erased one is not used for anything
+            return UserSelection::UserOption(UserOption::yes());
+        }
+        if severity == MessageSeverity::Error || severity == MessageSeverity::Warn {
+            eprintln!("[{:?}] {}", severity, message);
+        }
+        // Only informational messages are acknowledged. Nobody can answer the questions, so they are declined.
+        let option = if options.is_empty() || options.contains(&UserOption::ok()) {
+            UserOption::ok()
+        } else {
+            eprintln!("Declined, as it cannot be confirmed here: {}", message);
+            options.iter()
+                .find(|option| **option == UserOption::no())
+                .or_else(|| options.iter().find(|option| **option == UserOption::cancel()))
+                .cloned()
+                .unwrap_or_else(UserOption::cancel)
+        };
+        UserSelection::UserOption(option)
+    }
+}
+
+#[cfg(test)]
+mod test_git_credential {
+    use std::sync::{Arc, Mutex};
+
+    use rust_keylock::{Editor, Entry, EntryMeta, EntryPresentationType, Menu, MessageSeverity, UserOption, UserSelection};
+
+    use super::{CredentialEditor, CredentialRequest, Operation, Outcome};
+    use crate::extras;
+
+    const GET_FIXTURE: &str = "protocol=https\nhost=github.com\npath=rust-keylock/rust-keylock-shell.git\nusername=aston\n\n";
+    const STORE_FIXTURE: &str = "protocol=https\r\nhost=example.com:8443\r\nusername=bob\r\npassword=s3cr3t\r\nwwwauth[]=Basic\r\n";
+    const URL_FIXTURE: &str = "url=https://alice@git.example.com/team/repo.git\n";
+
+    fn entry(name: &str, url: &str, user: &str, pass: &str) -> Entry {
+        Entry::new(name.to_string(), url.to_string(), user.to_string(), pass.to_string(), "".to_string(), EntryMeta::default())
+    }
+
+    fn entries() -> Vec<Entry> {
+        vec![
+            entry("github", "https://github.com", "aston", "pass1"),
+            entry("github other user", "https://github.com", "other", "pass2"),
+            entry("github repo", "https://github.com/rust-keylock/rust-keylock-shell", "aston", "pass3"),
+            entry("example", "example.com:8443", "bob", "pass4"),
+            entry("git example", "https://git.example.com/team/repo", "alice", "pass5"),
+        ]
+    }
+
+    fn new_editor(operation: Operation, fixture: &str) -> (CredentialEditor, Arc<Mutex<Outcome>>) {
+        let request = CredentialRequest::parse(fixture.as_bytes()).unwrap();
+        let outcome = Arc::new(Mutex::new(Outcome::default()));
+        let mut editor = CredentialEditor::new(operation, request, outcome.clone(), |prompt| {
+            if prompt.contains("number") { "33".to_string() } else { "password".to_string() }
+        });
+        // The tests share the data of the Entries
+        editor.open_extras = |_, _| crate::extras::test_extras::open_store();
+        (editor, outcome)
+    }
+
+    #[test]
+    fn parse_fixtures() {
+        let request = CredentialRequest::parse(GET_FIXTURE.as_bytes()).unwrap();
+        assert!(request.protocol.as_deref() == Some("https"));
+        assert!(request.host.as_deref() == Some("github.com"));
+        assert!(request.path.as_deref() == Some("rust-keylock/rust-keylock-shell.git"));
+        assert!(request.username.as_deref() == Some("aston"));
+        assert!(request.password.is_none());
+
+        let request = CredentialRequest::parse(STORE_FIXTURE.as_bytes()).unwrap();
+        assert!(request.host.as_deref() == Some("example.com:8443"));
+        assert!(request.password.as_ref().map(|p| p.as_str()) == Some("s3cr3t"));
+
+        let request = CredentialRequest::parse(URL_FIXTURE.as_bytes()).unwrap();
+        assert!(request.host.as_deref() == Some("git.example.com"));
+        assert!(request.path.as_deref() == Some("team/repo.git"));
+        assert!(request.username.as_deref() == Some("alice"));
+
+        assert!(CredentialRequest::parse("protocol=https\nhost\n".as_bytes()).is_err());
+        assert!(CredentialRequest::parse("protocol=https\n\nhost=github.com\n".as_bytes()).is_err());
+        assert!(Operation::parse("capability").is_none());
+    }
+
+    #[test]
+    fn find_match() {
+        let entries = entries();
+        let request = CredentialRequest::parse(GET_FIXTURE.as_bytes()).unwrap();
+        assert!(request.find_match(&entries) == Some(2));
+        let request = CredentialRequest::parse("protocol=https\nhost=github.com\nusername=other\n".as_bytes()).unwrap();
+        assert!(request.find_match(&entries) == Some(1));
+        let request = CredentialRequest::parse("protocol=https\nhost=GitHub.com\npath=another/repo\n".as_bytes()).unwrap();
+        assert!(request.find_match(&entries) == Some(0));
+        let request = CredentialRequest::parse("protocol=http\nhost=github.com\n".as_bytes()).unwrap();
+        assert!(request.find_match(&entries).is_none());
+        let request = CredentialRequest::parse(STORE_FIXTURE.as_bytes()).unwrap();
+        assert!(request.find_match(&entries) == Some(3));
+        let request = CredentialRequest::parse(URL_FIXTURE.as_bytes()).unwrap();
+        assert!(request.find_match(&entries) == Some(4));
+        let request = CredentialRequest::parse("protocol=https\nhost=example.com\n".as_bytes()).unwrap();
+        assert!(request.find_match(&entries).is_none());
+    }
+
+    #[test]
+    fn get() {
+        let (editor, outcome) = new_editor(Operation::Get, GET_FIXTURE);
+        assert!(editor.show_password_enter() == UserSelection::new_provided_password("password".to_string(), 33));
+        assert!(editor.show_entries(entries(), "".to_string()) == UserSelection::GoTo(Menu::ShowEntry(2)));
+        assert!(editor.show_entry(entries()[2].clone(), 2, EntryPresentationType::View) == UserSelection::GoTo(Menu::Exit));
+        assert!(editor.exit(false) == UserSelection::GoTo(Menu::ForceExit));
+
+        let outcome = outcome.lock().unwrap();
+        assert!(outcome.response.as_ref().map(|r| r.as_str()) == Some("username=aston\npassword=pass3\n"));
+        assert!(outcome.error.is_none());
+    }
+
+    #[test]
+    fn wrong_password() {
+        let (editor, outcome) = new_editor(Operation::Get, GET_FIXTURE);
+        for _ in 0..super::MAX_UNLOCK_ATTEMPTS {
+            let _ = editor.show_password_enter();
+            let _ = editor.show_message("Wrong password or number!", vec![UserOption::ok()], MessageSeverity::Error);
+        }
+        assert!(editor.show_password_enter() == UserSelection::GoTo(Menu::Exit));
+        let outcome = outcome.lock().unwrap();
+        assert!(outcome.error.as_ref().map(|error| error.kind.exit_code()) == Some(6));
+        assert!(outcome.response.is_none());
+    }
+
+    #[test]
+    fn store_new_and_update() {
+        crate::extras::test_extras::open_store();
+        let (editor, _) = new_editor(Operation::Store, STORE_FIXTURE);
+        let _ = editor.show_password_enter();
+        let selection = editor.show_entries(vec![entries()[0].clone()], "".to_string());
+        match selection {
+            UserSelection::NewEntry(entry) => {
+                assert!(entry.name == "example.com:8443");
+                assert!(entry.url == "https://example.com:8443");
+                assert!(entry.user == "bob");
+                assert!(entry.pass == "s3cr3t");
+                assert!(extras::of(&entry).created_at.is_some());
+            }
+            other => panic!("Unexpected selection {:?}", other),
+        }
+        // The leaked password check of the library asks a question, which nobody can answer
+        let answer = editor.show_message("leaked", vec![UserOption::yes(), UserOption::no()], MessageSeverity::Warn);
+        assert!(answer == UserSelection::UserOption(UserOption::no()));
+        let answer = editor.show_message("saved", vec![UserOption::ok()], MessageSeverity::Info);
+        assert!(answer == UserSelection::UserOption(UserOption::ok()));
+        // Otherwise the Entry is saved
+        assert!(editor.show_entries(entries(), "".to_string()) == UserSelection::GoTo(Menu::Save(false)));
+        assert!(editor.show_menu(&Menu::Main) == UserSelection::GoTo(Menu::Exit));
+
+        let (editor, _) = new_editor(Operation::Store, STORE_FIXTURE);
+        let _ = editor.show_password_enter();
+        assert!(editor.show_entries(entries(), "".to_string()) == UserSelection::GoTo(Menu::EditEntry(3)));
+        match editor.show_entry(entries()[3].clone(), 3, EntryPresentationType::Edit) {
+            UserSelection::ReplaceEntry(3, entry) => {
+                assert!(entry.pass == "s3cr3t");
+                assert!(extras::of(&entry).password_history[0].pass == "pass4");
+            }
+            other => panic!("Unexpected selection {:?}", other),
+        }
+        assert!(editor.show_entries(entries(), "".to_string()) == UserSelection::GoTo(Menu::Save(false)));
+    }
+
+    #[test]
+    fn erase() {
+        crate::extras::test_extras::open_store();
+        let fixture = "protocol=https\nhost=example.com:8443\nusername=bob\npassword=pass4\n";
+        let erased = entry("erased example", "example.com:8443", "bob", "pass4");
+        let (editor, _) = new_editor(Operation::Erase, fixture);
+        let _ = editor.show_password_enter();
+        assert!(editor.show_entries(vec![erased.clone()], "".to_string()) == UserSelection::GoTo(Menu::EditEntry(0)));
+        match editor.show_entry(erased.clone(), 0, EntryPresentationType::Edit) {
+            UserSelection::ReplaceEntry(0, entry) => {
+                assert!(entry.name == "erased example");
+                assert!(entry.pass.is_empty());
+                assert!(extras::of(&entry).password_history[0].pass == "pass4");
+            }
+            other => panic!("Unexpected selection {:?}", other),
+        }
+        // The empty password is kept, even if the library tells that it leaked
+        let answer = editor.show_message(super::LEAKED_PASSWORD_MESSAGE, vec![UserOption::yes(), UserOption::no()], MessageSeverity::Warn);
+        assert!(answer == UserSelection::UserOption(UserOption::yes()));
+        assert!(editor.show_entries(vec![erased.clone()], "".to_string()) == UserSelection::GoTo(Menu::Save(false)));
+
+        // A different password is not erased
+        let (editor, _) = new_editor(Operation::Erase, STORE_FIXTURE);
+        let _ = editor.show_password_enter();
+        let _ = editor.show_entries(vec![erased.clone()], "".to_string());
+        assert!(editor.show_entry(erased.clone(), 0, EntryPresentationType::Edit) == UserSelection::GoTo(Menu::Exit));
+
+        // An erased credential is not answered
+        let mut cleared = erased;
+        cleared.pass = String::new();
+        let (editor, outcome) = new_editor(Operation::Get, fixture);
+        let _ = editor.show_password_enter();
+        assert!(editor.show_entries(vec![cleared.clone()], "".to_string()) == UserSelection::GoTo(Menu::ShowEntry(0)));
+        assert!(editor.show_entry(cleared, 0, EntryPresentationType::View) == UserSelection::GoTo(Menu::Exit));
+        assert!(outcome.lock().unwrap().response.is_none());
+    }
+
+    #[test]
+    fn erase_without_password() {
+        let fixture = "protocol=https\nhost=example.com:8443\nusername=bob\n";
+        // Nothing is unlocked
+        assert!(super::run("erase", fixture.as_bytes()) == Ok(()));
+
+        let (editor, outcome) = new_editor(Operation::Erase, fixture);
+        let _ = editor.show_password_enter();
+        assert!(editor.show_entries(entries(), "".to_string()) == UserSelection::GoTo(Menu::Exit));
+        assert!(editor.show_entry(entries()[3].clone(), 3, EntryPresentationType::Delete) == UserSelection::GoTo(Menu::Exit));
+        assert!(outcome.lock().unwrap().error.is_none());
+    }
+
+    #[test]
+    fn credential_to_git() {
+        let request = CredentialRequest::parse(STORE_FIXTURE.as_bytes()).unwrap();
+        assert!(request.to_git().as_str() == "protocol=https\nhost=example.com:8443\nusername=bob\npassword=s3cr3t\n");
+        assert!(CredentialRequest::parse(request.to_git().as_bytes()).unwrap() == request);
+    }
+}
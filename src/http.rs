@@ -0,0 +1,191 @@
+// Copyright 2017 astonbitecode
+// This file is part of rust-keylock password manager.
+//
+// rust-keylock is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// rust-keylock is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with rust-keylock.  If not, see <http://www.gnu.org/licenses/>.
+//! The blocking HTTP client for the few requests the shell sends itself, on top of reqwest.
+//!
+//! A response counts only if its body was received completely: a broken connection or a body whose length differs from
+//! the `Content-Length` fails the request.
+use std::error::Error;
+use std::net::ToSocketAddrs;
+use std::time::Duration;
+
+use log::debug;
+use reqwest::blocking::Client;
+use reqwest::Method;
+use url::Url;
+
+/// How long a request waits for the server
+const TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Why a request got no response
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub(crate) enum HttpError {
+    /// The URL is not valid
+    Url(String),
+    /// The name of the server could not be resolved
+    Dns(String),
+    /// The server could not be reached, or the connection broke
+    Connection(String),
+    /// The TLS handshake failed
+    Tls(String),
+    /// The response is not HTTP, or it is incomplete
+    InvalidResponse(String),
+}
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub(crate) struct Response {
+    pub(crate) status: u16,
+    pub(crate) body: Vec<u8>,
+}
+
+/// Sends a request and returns the response. `accept_invalid_certificates` allows self-signed certificates.
+pub(crate) fn request(method: &str, url: &str, headers: &[(&str, &str)], body: &[u8], accept_invalid_certificates: bool) -> Result<Response, HttpError> {
+    let url = Url::parse(url).map_err(|error| HttpError::Url(error.to_string()))?;
+    let method = Method::from_bytes(method.as_bytes()).map_err(|error| HttpError::Url(error.to_string()))?;
+    // Resolved here, so that an unknown server can be told apart from one that cannot be reached
+    let host = url.host_str().unwrap_or_default().to_string();
+    let port = url.port_or_known_default().unwrap_or(443);
+    if (host.as_str(), port).to_socket_addrs().map(|mut addresses| addresses.next().is_none()).unwrap_or(true) {
+        return Err(HttpError::Dns(host));
+    }
+
+    let client = Client::builder()
+        .user_agent("rust-keylock")
+        .timeout(TIMEOUT)
+        .danger_accept_invalid_certs(accept_invalid_certificates)
+        .build()
+        .map_err(|error| HttpError::Tls(error.to_string()))?;
+    let mut request = client.request(method.clone(), url.clone()).body(body.to_vec());
+    for (name, value) in headers {
+        request = request.header(*name, *value);
+    }
+
+    debug!("Sending {} {}", method, url);
+    let response = request.send().map_err(|error| send_error(&error))?;
+    let status = response.status().as_u16();
+    let expected_length = response.content_length();
+    let body = response.bytes().map_err(|error| HttpError::Connection(describe(&error)))?.to_vec();
+    // The Content-Length of a HEAD response is the length of the body a GET would return
+    if method != Method::HEAD {
+        check_length(body.len(), expected_length)?;
+    }
+    Ok(Response { status, body })
+}
+
+/// Rejects a body that is shorter or longer than the `Content-Length` of the response
+fn check_length(received: usize, expected: Option<u64>) -> Result<(), HttpError> {
+    match expected {
+        Some(expected) if expected != received as u64 => {
+            Err(HttpError::InvalidResponse(format!("Received {} bytes instead of {}", received, expected)))
+        }
+        _ => Ok(()),
+    }
+}
+
+fn send_error(error: &reqwest::Error) -> HttpError {
+    if error.is_builder() {
+        HttpError::Url(describe(error))
+    } else if is_tls_error(error) {
+        HttpError::Tls(describe(error))
+    } else if error.is_connect() || error.is_timeout() || error.is_request() {
+        HttpError::Connection(describe(error))
+    } else {
+        HttpError::InvalidResponse(describe(error))
+    }
+}
+
+/// Whether OpenSSL, which does the TLS handshakes, caused the error
+fn is_tls_error(error: &reqwest::Error) -> bool {
+    let mut source = error.source();
+    while let Some(cause) = source {
+        if cause.is::<openssl::ssl::Error>() || cause.is::<openssl::error::ErrorStack>() {
+            return true;
+        }
+        source = cause.source();
+    }
+    false
+}
+
+/// The message of the error and of its causes, which tell what actually went wrong
+fn describe(error: &reqwest::Error) -> String {
+    let mut message = error.to_string();
+    let mut source = error.source();
+    while let Some(cause) = source {
+        message.push_str(&format!(": {}", cause));
+        source = cause.source();
+    }
+    message
+}
+
+#[cfg(test)]
+mod test_http {
+    use std::io::{BufRead, BufReader, Write};
+    use std::net::TcpListener;
+    use std::thread::{self, JoinHandle};
+
+    use super::{HttpError, Response};
+
+    /// Answers one request with the raw response
+    fn serve_once(response: &'static [u8]) -> (String, JoinHandle<()>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://127.0.0.1:{}/file", listener.local_addr().unwrap().port());
+        let handle = thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            let mut reader = BufReader::new(stream.try_clone().unwrap());
+            loop {
+                let mut header = String::new();
+                reader.read_line(&mut header).unwrap();
+                if header.trim().is_empty() {
+                    break;
+                }
+            }
+            let _ = stream.write_all(response);
+        });
+        (url, handle)
+    }
+
+    #[test]
+    fn complete_responses() {
+        let (url, server) = serve_once(b"HTTP/1.1 200 OK\r\nContent-Length: 5\r\nConnection: close\r\n\r\nhello");
+        assert!(super::request("GET", &url, &[], &[], false) == Ok(Response { status: 200, body: b"hello".to_vec() }));
+        server.join().unwrap();
+
+        let (url, server) = serve_once(b"HTTP/1.1 207 Multi-Status\r\nTransfer-Encoding: chunked\r\nConnection: close\r\n\r\n5\r\nhello\r\n6\r\n world\r\n0\r\n\r\n");
+        let response = super::request("PROPFIND", &url, &[("Depth", "0")], &[], false).unwrap();
+        assert!(response.status == 207 && response.body == b"hello world");
+        server.join().unwrap();
+    }
+
+    #[test]
+    fn truncated_responses() {
+        let (url, server) = serve_once(b"HTTP/1.1 200 OK\r\nContent-Length: 10\r\nConnection: close\r\n\r\nhello");
+        assert!(super::request("GET", &url, &[], &[], false).is_err());
+        server.join().unwrap();
+
+        let (url, server) = serve_once(b"HTTP/1.1 200 OK\r\nTransfer-Encoding: chunked\r\nConnection: close\r\n\r\n5\r\nhel");
+        assert!(super::request("GET", &url, &[], &[], false).is_err());
+        server.join().unwrap();
+
+        assert!(super::check_length(5, Some(5)).is_ok());
+        assert!(super::check_length(5, None).is_ok());
+        assert!(matches!(super::check_length(4, Some(5)), Err(HttpError::InvalidResponse(_))));
+    }
+
+    #[test]
+    fn invalid_requests() {
+        assert!(matches!(super::request("GET", "not a url", &[], &[], false), Err(HttpError::Url(_))));
+        assert!(matches!(super::request("GET", "http://unknown.invalid/", &[], &[], false), Err(HttpError::Dns(_))));
+    }
+}
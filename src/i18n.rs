@@ -0,0 +1,297 @@
+// Copyright 2017 astonbitecode
+// This file is part of rust-keylock password manager.
+//
+// rust-keylock is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// rust-keylock is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with rust-keylock.  If not, see <http://www.gnu.org/licenses/>.
+//! The message catalogues of the shell.
+//!
+//! The catalogues use a subset of the Fluent syntax: `id = value` lines, values that continue in the following indented
+//! lines, `# comments` and `{ $variable }` placeables. The catalogues of the supported locales are built in and can be
+//! extended or overridden with `<locale>.ftl` files in `~/.rust-keylock/locales`.
+//!
+//! The messages whose id starts with `key-` are the shortcut keys of the menus. They are checked for collisions and if
+//! the keys of a locale collide, the English keys are used instead.
+use std::collections::HashMap;
+use std::fs;
+use std::sync::OnceLock;
+
+use log::{debug, warn};
+
+const FALLBACK_LOCALE: &str = "en";
+const LOCALES_DIRECTORY: &str = "locales";
+const KEY_PREFIX: &str = "key-";
+
+/// The built in catalogues
+const BUILT_IN: &[(&str, &str)] = &[
+    ("en", include_str!("../locales/en.ftl")),
+    ("de", include_str!("../locales/de.ftl")),
+];
+
+/// The shortcut keys that are offered together in the same prompt and must be different
+const SHORTCUT_GROUPS: &[&[&str]] = &[
+    &["key-new", "key-bulk", "key-return", "key-filter", "key-clear-filter", "key-rotation", "key-undo", "key-redo",
+        "key-next-page", "key-previous-page", "key-goto-page"],
+    &["key-accept", "key-cancel", "key-test"],
+    &["key-keep-local", "key-take-remote", "key-merge", "key-cancel"],
+    &["key-add-folder", "key-add-webdav", "key-return"],
+    &["key-sync-now", "key-sync-log", "key-return"],
+    &["key-reveal", "key-accept", "key-cancel"],
+    &["key-keep-mine", "key-take-theirs", "key-keep-both"],
+    &["key-yes", "key-no"],
+    &["key-quit"],
+    &["key-all"],
+];
+
+static CATALOGUE: OnceLock<Catalogue> = OnceLock::new();
+
+#[derive(Debug, Default, PartialEq, Eq, Clone)]
+pub(crate) struct Catalogue {
+    messages: HashMap<String, String>,
+}
+
+impl Catalogue {
+    /// Parses a catalogue. Lines that cannot be parsed are logged and skipped.
+    pub(crate) fn parse(text: &str) -> Catalogue {
+        let mut messages = HashMap::new();
+        let mut current: Option<(String, String)> = None;
+        for line in text.lines() {
+            let trimmed = line.trim();
+            if line.starts_with(char::is_whitespace) && !trimmed.is_empty() {
+                match current.as_mut() {
+                    Some((_, value)) => {
+                        if !value.is_empty() {
+                            value.push('\n');
+                        }
+                        value.push_str(trimmed);
+                    }
+                    None => debug!("Ignoring a continuation line without a message: {}", trimmed),
+                }
+                continue;
+            }
+            if let Some((id, value)) = current.take() {
+                messages.insert(id, value);
+            }
+            if trimmed.is_empty() || trimmed.starts_with('#') {
+                continue;
+            }
+            match trimmed.split_once('=') {
+                Some((id, value)) if is_valid_id(id.trim()) => current = Some((id.trim().to_string(), value.trim().to_string())),
+                _ => debug!("Ignoring an invalid catalogue line: {}", trimmed),
+            }
+        }
+        if let Some((id, value)) = current.take() {
+            messages.insert(id, value);
+        }
+        Catalogue { messages }
+    }
+
+    fn get(&self, id: &str) -> Option<&str> {
+        self.messages.get(id).map(String::as_str)
+    }
+
+    /// Adds the messages of the other catalogue, replacing the existing ones
+    fn extend(&mut self, other: Catalogue) {
+        self.messages.extend(other.messages);
+    }
+
+    /// Returns the problems of the shortcut keys: invalid keys and keys that collide in the same prompt
+    pub(crate) fn shortcut_problems(&self) -> Vec<String> {
+        let mut problems = Vec::new();
+        let mut invalid: Vec<&str> = Vec::new();
+        for group in SHORTCUT_GROUPS {
+            let mut seen: HashMap<&str, &str> = HashMap::new();
+            for id in group.iter() {
+                let key = match self.get(id) {
+                    Some(key) => key,
+                    None => continue,
+                };
+                if key.is_empty() || key.contains(char::is_whitespace) || key.parse::<usize>().is_ok() {
+                    // A key may be offered in several prompts
+                    if !invalid.contains(id) {
+                        invalid.push(id);
+                        problems.push(format!("'{}' is not a valid shortcut for {}", key, id));
+                    }
+                } else if let Some(other) = seen.insert(key, id) {
+                    problems.push(format!("'{}' is the shortcut of both {} and {}", key, other, id));
+                }
+            }
+        }
+        problems
+    }
+
+    fn without_shortcuts(mut self) -> Catalogue {
+        self.messages.retain(|id, _| !id.starts_with(KEY_PREFIX));
+        self
+    }
+}
+
+fn is_valid_id(id: &str) -> bool {
+    !id.is_empty() && id.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+}
+
+/// Returns the locale to use: the one of the settings, or the one of the environment
+pub(crate) fn select_locale(setting: &str, environment: &[Option<String>]) -> String {
+    let chosen = if !setting.trim().is_empty() {
+        setting.trim().to_string()
+    } else {
+        environment.iter().flatten().find(|value| !value.trim().is_empty()).cloned().unwrap_or_default()
+    };
+    // Like de_DE.UTF-8@euro
+    let locale = chosen.split(['.', '@']).next().unwrap_or("").replace('-', "_");
+    match locale.as_str() {
+        "" | "C" | "POSIX" => FALLBACK_LOCALE.to_string(),
+        _ => locale,
+    }
+}
+
+/// The catalogue of the locale, including the messages of the locale without the territory, like `de` for `de_AT`
+fn catalogue_of(locale: &str) -> Option<Catalogue> {
+    let language = locale.split('_').next().unwrap_or(locale);
+    let mut candidates = vec![language];
+    if language != locale {
+        candidates.push(locale);
+    }
+    let mut catalogue: Option<Catalogue> = None;
+    for candidate in candidates {
+        let mut found = BUILT_IN.iter().find(|(name, _)| *name == candidate).map(|(_, text)| Catalogue::parse(text));
+        let mut path = rust_keylock::default_rustkeylock_location();
+        path.push(LOCALES_DIRECTORY);
+        path.push(format!("{}.ftl", candidate));
+        if let Ok(text) = fs::read_to_string(&path) {
+            debug!("Loading the catalogue {:?}", path);
+            found.get_or_insert_with(Catalogue::default).extend(Catalogue::parse(&text));
+        }
+        if let Some(found) = found {
+            catalogue.get_or_insert_with(Catalogue::default).extend(found);
+        }
+    }
+    catalogue
+}
+
+/// Builds the catalogue to use for a locale: English, overridden by the messages of the locale
+fn build(locale: &str) -> Catalogue {
+    let mut catalogue = catalogue_of(FALLBACK_LOCALE).unwrap_or_default();
+    if locale != FALLBACK_LOCALE {
+        match catalogue_of(locale) {
+            Some(localized) => {
+                // The locale may define only some of the keys. They must not collide with the English ones either.
+                let mut merged = catalogue.clone();
+                merged.extend(localized.clone());
+                let problems = merged.shortcut_problems();
+                if problems.is_empty() {
+                    catalogue = merged;
+                } else {
+                    warn!("Using the English shortcuts, as the shortcuts of locale '{}' collide: {}", locale, problems.join("; "));
+                    catalogue.extend(localized.without_shortcuts());
+                }
+            }
+            None => debug!("No catalogue for locale '{}'. Using English.", locale),
+        }
+    }
+    catalogue
+}
+
+/// Selects the catalogue to use. Only the first call has an effect.
+pub(crate) fn init(locale_setting: &str) {
+    let environment: Vec<Option<String>> = ["LC_ALL", "LC_MESSAGES", "LANG"].iter().map(|name| std::env::var(name).ok()).collect();
+    let locale = select_locale(locale_setting, &environment);
+    debug!("Using locale '{}'", locale);
+    let _ = CATALOGUE.set(build(&locale));
+}
+
+fn catalogue() -> &'static Catalogue {
+    CATALOGUE.get_or_init(|| build(FALLBACK_LOCALE))
+}
+
+/// Returns the message with the id
+pub(crate) fn tr(id: &str) -> String {
+    tr_with(id, &[])
+}
+
+/// Returns the message with the id, with its `{ $variable }` placeables replaced by the values of the arguments
+pub(crate) fn tr_with(id: &str, args: &[(&str, &str)]) -> String {
+    let mut message = catalogue().get(id).unwrap_or(id).to_string();
+    for (name, value) in args {
+        message = message.replace(&format!("{{ ${} }}", name), value);
+    }
+    message
+}
+
+/// Returns the shortcut key with the id, like `key-yes`
+pub(crate) fn key(id: &str) -> String {
+    tr(id)
+}
+
+#[cfg(test)]
+mod test_i18n {
+    use super::Catalogue;
+
+    #[test]
+    fn parse() {
+        let catalogue = Catalogue::parse(r#"
+# A comment
+simple = A message
+with-args = Press '{ $key }' to return
+multiline =
+    First line
+    Second line
+invalid line
+key-yes = y
+"#);
+        assert!(catalogue.get("simple") == Some("A message"));
+        assert!(catalogue.get("multiline") == Some("First line\nSecond line"));
+        assert!(catalogue.get("key-yes") == Some("y"));
+        assert!(catalogue.messages.len() == 4);
+    }
+
+    #[test]
+    fn select_locale() {
+        assert!(super::select_locale("", &[None, None, Some("de_DE.UTF-8".to_string())]) == "de_DE");
+        assert!(super::select_locale("", &[Some("".to_string()), Some("fr_FR@euro".to_string()), None]) == "fr_FR");
+        assert!(super::select_locale("el", &[Some("de_DE.UTF-8".to_string())]) == "el");
+        assert!(super::select_locale("", &[Some("C".to_string())]) == "en");
+        assert!(super::select_locale("", &[]) == "en");
+    }
+
+    #[test]
+    fn built_in_catalogues_are_complete_and_without_collisions() {
+        let english = Catalogue::parse(super::BUILT_IN[0].1);
+        for (locale, text) in super::BUILT_IN {
+            let catalogue = Catalogue::parse(text);
+            assert!(catalogue.shortcut_problems().is_empty(), "Shortcut collisions in '{}'", locale);
+            for id in english.messages.keys() {
+                assert!(catalogue.get(id).is_some(), "'{}' is missing from '{}'", id, locale);
+            }
+            for id in catalogue.messages.keys() {
+                assert!(english.get(id).is_some(), "'{}' of '{}' is not in the English catalogue", id, locale);
+            }
+        }
+    }
+
+    #[test]
+    fn shortcut_collisions() {
+        let catalogue = Catalogue::parse("key-yes = j\nkey-no = j\nkey-accept = 1\nkey-quit = q");
+        let problems = catalogue.shortcut_problems();
+        assert!(problems.len() == 2);
+        assert!(catalogue.without_shortcuts().messages.is_empty());
+    }
+
+    #[test]
+    fn fallback_and_placeables() {
+        let english = super::build("en");
+        let german = super::build("de_AT");
+        assert!(german.get("main-quit") != english.get("main-quit"));
+        assert!(super::tr_with("entries-filtered", &[("filter", "mail")]) == "Entries filtered by 'mail'");
+        assert!(super::tr("no-such-message") == "no-such-message");
+    }
+}
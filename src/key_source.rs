@@ -0,0 +1,343 @@
+// Copyright 2017 astonbitecode
+// This file is part of rust-keylock password manager.
+//
+// rust-keylock is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// rust-keylock is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with rust-keylock.  If not, see <http://www.gnu.org/licenses/>.
+//! Non-interactive sources of the master password and the favorite number.
+//!
+//! A source provides the password in its first line and, optionally, the favorite number in its second line. If the
+//! number is missing, it is prompted for.
+//!
+//! The precedence is `--password-fd`, then `--password-command`, then `--keyring`. When none is given, or the keyring
+//! does not hold the key, the User is prompted.
+//!
+//! A source is used only for the first unlock attempt. If it does not unlock the passwords file, the sources given
+//! explicitly fail, while the keyring falls back to prompting.
+use std::io::Read;
+use std::process::{Command, Stdio};
+use std::sync::Mutex;
+
+use log::warn;
+use zeroize::Zeroizing;
+
+/// The name of the key in the kernel keyring and the attribute value in the Secret Service
+const KEYRING_KEY_NAME: &str = "rust-keylock";
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub(crate) enum KeySource {
+    Prompt,
+    /// A file descriptor, inherited by the process, to read from
+    Fd(i32),
+    /// A shell command that prints the key
+    Command(String),
+    /// The Linux kernel keyring, or the Secret Service
+    Keyring,
+}
+
+impl KeySource {
+    fn describe(&self) -> String {
+        match self {
+            KeySource::Prompt => "the terminal".to_string(),
+            KeySource::Fd(fd) => format!("--password-fd {}", fd),
+            KeySource::Command(_) => "--password-command".to_string(),
+            KeySource::Keyring => "the keyring".to_string(),
+        }
+    }
+
+    /// Reads the key from the source. `Ok(None)` means that the source does not hold a key.
+    fn read(&self) -> Result<Option<Zeroizing<String>>, String> {
+        match self {
+            KeySource::Prompt => Ok(None),
+            KeySource::Fd(fd) => read_fd(*fd).map(Some),
+            KeySource::Command(command) => read_command(command).map(Some),
+            KeySource::Keyring => Ok(read_kernel_keyring().or_else(read_secret_service)),
+        }
+    }
+}
+
+/// The password and the number, parsed from the text of a source. The number is missing if the text has one line.
+fn parse_key_text(text: &str) -> Result<(Zeroizing<String>, Option<usize>), String> {
+    let mut lines = text.lines();
+    let password = Zeroizing::new(lines.next().unwrap_or("").to_string());
+    if password.is_empty() {
+        return Err("The password is empty".to_string());
+    }
+    let number = match lines.next().map(str::trim) {
+        None | Some("") => None,
+        Some(number) => Some(number.parse::<usize>().map_err(|_| "The favorite number must be a number".to_string())?),
+    };
+    Ok((password, number))
+}
+
+/// Takes the arguments that select the source out of the arguments.
+///
+/// Returns the source with the highest precedence, the names of the sources that were ignored because of it, and the
+/// remaining arguments.
+pub(crate) fn parse_args(args: &[String]) -> Result<(KeySource, Vec<String>, Vec<String>), String> {
+    let mut fd = None;
+    let mut command = None;
+    let mut keyring = false;
+    let mut remaining = Vec::new();
+    let mut iter = args.iter();
+    while let Some(arg) = iter.next() {
+        match arg.as_str() {
+            "--password-fd" => {
+                let value = iter.next().ok_or_else(|| "--password-fd needs a file descriptor".to_string())?;
+                fd = Some(value.parse::<i32>().ok().filter(|fd| *fd >= 0)
+                    .ok_or_else(|| format!("Invalid file descriptor '{}'", value))?);
+            }
+            "--password-command" => {
+                command = Some(iter.next().ok_or_else(|| "--password-command needs a command".to_string())?.clone());
+            }
+            "--keyring" => keyring = true,
+            "--" => {
+                remaining.push(arg.clone());
+                remaining.extend(iter.by_ref().cloned());
+            }
+            _ => remaining.push(arg.clone()),
+        }
+    }
+    let mut given = Vec::new();
+    if let Some(fd) = fd {
+        given.push(KeySource::Fd(fd));
+    }
+    if let Some(command) = command {
+        given.push(KeySource::Command(command));
+    }
+    if keyring {
+        given.push(KeySource::Keyring);
+    }
+    let ignored = given.iter().skip(1).map(KeySource::describe).collect();
+    Ok((given.into_iter().next().unwrap_or(KeySource::Prompt), ignored, remaining))
+}
+
+struct SourceState {
+    source: KeySource,
+    ignored: Vec<String>,
+    used: bool,
+}
+
+impl SourceState {
+    fn provided_key(&mut self, ask_number: &dyn Fn() -> Result<usize, String>) -> Option<Result<(Zeroizing<String>, usize), String>> {
+        if self.source == KeySource::Prompt {
+            return None;
+        }
+        let description = self.source.describe();
+        if self.used {
+            if self.source == KeySource::Keyring {
+                self.source = KeySource::Prompt;
+                warning("The key from the keyring did not unlock the passwords file. Please enter it manually.");
+                return None;
+            }
+            return Some(Err(format!("The password and number from {} did not unlock the passwords file", description)));
+        }
+        self.used = true;
+        for ignored in self.ignored.drain(..) {
+            warning(&format!("Ignoring {}, as {} takes precedence", ignored, description));
+        }
+        let text = match self.source.read() {
+            Ok(Some(text)) => text,
+            Ok(None) => {
+                warning(&format!("No key named '{}' was found in the keyring. Please enter it manually.", KEYRING_KEY_NAME));
+                return None;
+            }
+            Err(error) => return Some(Err(format!("Could not read the key from {}: {}", description, error))),
+        };
+        let provided = parse_key_text(&text).map_err(|error| format!("Invalid key from {}: {}", description, error));
+        Some(provided.and_then(|(password, number)| match number {
+            Some(number) => Ok((password, number)),
+            None => ask_number().map(|number| (password, number)),
+        }))
+    }
+}
+
+static SOURCE: Mutex<SourceState> = Mutex::new(SourceState { source: KeySource::Prompt, ignored: Vec::new(), used: false });
+
+/// Sets the source to use for unlocking
+pub(crate) fn init(source: KeySource, ignored: Vec<String>) {
+    if let Ok(mut state) = SOURCE.lock() {
+        *state = SourceState { source, ignored, used: false };
+    }
+}
+
+/// Returns the password and the number from the source that was set with `init`, or `None` if the User should be
+/// prompted for them. `ask_number` is called if the source provides only the password.
+pub(crate) fn provided_key(ask_number: &dyn Fn() -> Result<usize, String>) -> Option<Result<(Zeroizing<String>, usize), String>> {
+    SOURCE.lock().ok().and_then(|mut state| state.provided_key(ask_number))
+}
+
+fn warning(message: &str) {
+    warn!("{}", message);
+    eprintln!("Warning: {}", message);
+}
+
+#[cfg(unix)]
+fn read_fd(fd: i32) -> Result<Zeroizing<String>, String> {
+    use std::fs::File;
+    use std::os::unix::io::FromRawFd;
+    // Duplicate the descriptor, so that the one that was passed is not closed when the file is dropped
+    let duplicate = unsafe { libc::dup(fd) };
+    if duplicate < 0 {
+        return Err(std::io::Error::last_os_error().to_string());
+    }
+    let mut file = unsafe { File::from_raw_fd(duplicate) };
+    let mut text = Zeroizing::new(String::new());
+    file.read_to_string(&mut text).map_err(|error| error.to_string())?;
+    Ok(text)
+}
+
+#[cfg(not(unix))]
+fn read_fd(_fd: i32) -> Result<Zeroizing<String>, String> {
+    Err("Reading from file descriptors is supported only on Unix".to_string())
+}
+
+fn read_command(command: &str) -> Result<Zeroizing<String>, String> {
+    let mut child = shell_command(command)
+        .stdin(Stdio::inherit())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::inherit())
+        .spawn()
+        .map_err(|error| format!("Could not run '{}': {}", command, error))?;
+    let mut text = Zeroizing::new(String::new());
+    if let Some(mut stdout) = child.stdout.take() {
+        stdout.read_to_string(&mut text).map_err(|error| error.to_string())?;
+    }
+    let status = child.wait().map_err(|error| error.to_string())?;
+    if status.success() {
+        Ok(text)
+    } else {
+        Err(format!("'{}' failed with {}", command, status))
+    }
+}
+
+#[cfg(unix)]
+fn shell_command(command: &str) -> Command {
+    let mut shell = Command::new("sh");
+    shell.arg("-c").arg(command);
+    shell
+}
+
+#[cfg(not(unix))]
+fn shell_command(command: &str) -> Command {
+    let mut shell = Command::new("cmd");
+    shell.arg("/C").arg(command);
+    shell
+}
+
+/// Reads the key `rust-keylock` of type `user` from the keyrings of the process. It can be added with
+/// `keyctl padd user rust-keylock @u`.
+#[cfg(target_os = "linux")]
+fn read_kernel_keyring() -> Option<Zeroizing<String>> {
+    use std::ffi::CString;
+
+    const KEY_SPEC_USER_KEYRING: libc::c_long = -4;
+    const KEYCTL_SEARCH: libc::c_long = 10;
+    const KEYCTL_READ: libc::c_long = 11;
+
+    let key_type = CString::new("user").ok()?;
+    let description = CString::new(KEYRING_KEY_NAME).ok()?;
+    let mut serial = unsafe {
+        libc::syscall(libc::SYS_request_key, key_type.as_ptr(), description.as_ptr(), std::ptr::null::<libc::c_char>(), 0 as libc::c_long)
+    };
+    if serial < 0 {
+        // The user keyring is not always linked to the session keyring
+        serial = unsafe {
+            libc::syscall(libc::SYS_keyctl, KEYCTL_SEARCH, KEY_SPEC_USER_KEYRING, key_type.as_ptr(), description.as_ptr(), 0 as libc::c_long)
+        };
+    }
+    if serial < 0 {
+        return None;
+    }
+    let size = unsafe { libc::syscall(libc::SYS_keyctl, KEYCTL_READ, serial, std::ptr::null_mut::<u8>(), 0 as libc::size_t) };
+    if size <= 0 {
+        return None;
+    }
+    let mut buffer = Zeroizing::new(vec![0u8; size as usize]);
+    let read = unsafe { libc::syscall(libc::SYS_keyctl, KEYCTL_READ, serial, buffer.as_mut_ptr(), buffer.len()) };
+    if read < 0 || read as usize > buffer.len() {
+        return None;
+    }
+    std::str::from_utf8(&buffer[..read as usize]).ok().map(|text| Zeroizing::new(text.to_string()))
+}
+
+#[cfg(not(target_os = "linux"))]
+fn read_kernel_keyring() -> Option<Zeroizing<String>> {
+    None
+}
+
+/// Looks the key up in the Secret Service, with `secret-tool`. It can be stored with
+/// `secret-tool store --label=rust-keylock application rust-keylock`.
+fn read_secret_service() -> Option<Zeroizing<String>> {
+    let output = Command::new("secret-tool")
+        .args(["lookup", "application", KEYRING_KEY_NAME])
+        .stdin(Stdio::null())
+        .stderr(Stdio::null())
+        .output()
+        .ok()?;
+    let text = Zeroizing::new(String::from_utf8(output.stdout).ok()?);
+    if output.status.success() && !text.is_empty() {
+        Some(text)
+    } else {
+        None
+    }
+}
+
+#[cfg(test)]
+mod test_key_source {
+    use super::{KeySource, SourceState};
+
+    fn args(args: &[&str]) -> Vec<String> {
+        args.iter().map(|arg| arg.to_string()).collect()
+    }
+
+    #[test]
+    fn parse_args() {
+        let (source, ignored, remaining) = super::parse_args(&args(&["ls", "--keyring", "--password-command", "pass show rkl", "--password-fd", "3"])).unwrap();
+        assert!(source == KeySource::Fd(3));
+        assert!(ignored == args(&["--password-command", "the keyring"]));
+        assert!(remaining == args(&["ls"]));
+
+        let (source, ignored, remaining) = super::parse_args(&args(&["exec", "--", "cmd", "--keyring"])).unwrap();
+        assert!(source == KeySource::Prompt);
+        assert!(ignored.is_empty());
+        assert!(remaining == args(&["exec", "--", "cmd", "--keyring"]));
+
+        assert!(super::parse_args(&args(&["--password-fd", "-1"])).is_err());
+        assert!(super::parse_args(&args(&["--password-command"])).is_err());
+    }
+
+    #[test]
+    fn parse_key_text() {
+        let (password, number) = super::parse_key_text("secret\n33\n").unwrap();
+        assert!(password.as_str() == "secret" && number == Some(33));
+        let (password, number) = super::parse_key_text("secret\r\n").unwrap();
+        assert!(password.as_str() == "secret" && number.is_none());
+        assert!(super::parse_key_text("").is_err());
+        assert!(super::parse_key_text("secret\nthirty").is_err());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn command_source_is_used_once() {
+        let mut state = SourceState { source: KeySource::Command("printf 'secret\\n'".to_string()), ignored: Vec::new(), used: false };
+        let (password, number) = state.provided_key(&|| Ok(33)).unwrap().unwrap();
+        assert!(password.as_str() == "secret" && number == 33);
+        assert!(state.provided_key(&|| Ok(33)).unwrap().is_err());
+
+        let mut state = SourceState { source: KeySource::Command("exit 1".to_string()), ignored: Vec::new(), used: false };
+        assert!(state.provided_key(&|| Ok(33)).unwrap().is_err());
+
+        let mut state = SourceState { source: KeySource::Prompt, ignored: Vec::new(), used: false };
+        assert!(state.provided_key(&|| Ok(33)).is_none());
+    }
+}
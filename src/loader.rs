@@ -0,0 +1,220 @@
+// Copyright 2017 astonbitecode
+// This file is part of rust-keylock password manager.
+//
+// rust-keylock is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// rust-keylock is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with rust-keylock.  If not, see <http://www.gnu.org/licenses/>.
+//! Unlocks the passwords file without User interaction and gets all the Entries, decrypted.
+//!
+//! The library is driven by an `Editor` that provides the password and then visits every Entry.
+use std::sync::{Arc, Mutex};
+
+use rust_keylock::dropbox::DropboxConfiguration;
+use rust_keylock::nextcloud::NextcloudConfiguration;
+use rust_keylock::{Editor, Entry, EntryPresentationType, Menu, MessageSeverity, UserOption, UserSelection};
+use zeroize::Zeroizing;
+
+use crate::key_source;
+
+/// The decrypted Entries, or an error message for the User
+pub(crate) type Loaded = Result<Vec<Entry>, String>;
+
+/// Gets the password and the number from the source that the User selected, or prompts on the terminal for them
+pub(crate) fn ask_key() -> Result<(Zeroizing<String>, usize), String> {
+    if let Some(provided) = key_source::provided_key(&ask_number) {
+        return provided;
+    }
+    let password = Zeroizing::new(rpassword::prompt_password("Please provide your password: ").map_err(|error| error.to_string())?);
+    let number = ask_number()?;
+    Ok((password, number))
+}
+
+fn ask_number() -> Result<usize, String> {
+    let number = Zeroizing::new(rpassword::prompt_password("What is your favorite number?: ").map_err(|error| error.to_string())?);
+    number.trim().parse::<usize>().map_err(|_| "The favorite number must be a number".to_string())
+}
+
+/// Unlocks the passwords file with the library and returns all the Entries, decrypted
+pub(crate) fn load_entries(password: &str, number: usize) -> Loaded {
+    let result = Arc::new(Mutex::new(None));
+    let loader = Loader {
+        password: Zeroizing::new(password.to_string()),
+        number,
+        state: Mutex::new(LoaderState::default()),
+        result: result.clone(),
+    };
+    rust_keylock::execute(Box::new(loader));
+    let mut result = result.lock().map_err(|error| format!("Could not load the Entries: {:?}", error))?;
+    result.take().unwrap_or_else(|| Err("Could not load the Entries".to_string()))
+}
+
+#[derive(Default)]
+struct LoaderState {
+    attempted: bool,
+    count: Option<usize>,
+    entries: Vec<Entry>,
+}
+
+/// An `Editor` that unlocks and then visits all the Entries, in order to get them decrypted
+struct Loader {
+    password: Zeroizing<String>,
+    number: usize,
+    state: Mutex<LoaderState>,
+    result: Arc<Mutex<Option<Loaded>>>,
+}
+
+impl Loader {
+    fn finish(&self, result: Loaded) -> UserSelection {
+        if let Ok(mut stored) = self.result.lock() {
+            if stored.is_none() {
+                *stored = Some(result);
+            }
+        }
+        UserSelection::GoTo(Menu::Exit)
+    }
+
+    fn take_entries(&self) -> Vec<Entry> {
+        self.state.lock().map(|mut state| std::mem::take(&mut state.entries)).unwrap_or_default()
+    }
+}
+
+impl Editor for Loader {
+    fn show_password_enter(&self) -> UserSelection {
+        let attempted = self.state.lock().map(|mut state| std::mem::replace(&mut state.attempted, true)).unwrap_or(true);
+        if attempted {
+            self.finish(Err("Wrong password or number".to_string()))
+        } else {
+            UserSelection::new_provided_password(self.password.to_string(), self.number)
+        }
+    }
+
+    fn show_change_password(&self) -> UserSelection {
+        self.finish(Err("No passwords file was found. Please run rust-keylock interactively first, in order to create it.".to_string()))
+    }
+
+    fn show_menu(&self, _menu: &Menu) -> UserSelection {
+        UserSelection::GoTo(Menu::Exit)
+    }
+
+    fn show_entries(&self, entries: Vec<Entry>, _filter: String) -> UserSelection {
+        let first_time = self.state.lock()
+            .map(|mut state| state.count.replace(entries.len()).is_none())
+            .unwrap_or(false);
+        if first_time && !entries.is_empty() {
+            UserSelection::GoTo(Menu::ShowEntry(0))
+        } else {
+            self.finish(Ok(self.take_entries()))
+        }
+    }
+
+    fn show_entry(&self, entry: Entry, index: usize, _presentation_type: EntryPresentationType) -> UserSelection {
+        let count = match self.state.lock() {
+            Ok(mut state) => {
+                state.entries.push(entry);
+                state.count.unwrap_or(0)
+            }
+            Err(_) => 0,
+        };
+        if index + 1 < count {
+            UserSelection::GoTo(Menu::ShowEntry(index + 1))
+        } else {
+            self.finish(Ok(self.take_entries()))
+        }
+    }
+
+    fn exit(&self, _contents_changed: bool) -> UserSelection {
+        UserSelection::GoTo(Menu::ForceExit)
+    }
+
+    fn show_configuration(&self, _nextcloud: NextcloudConfiguration, _dropbox: DropboxConfiguration) -> UserSelection {
+        UserSelection::GoTo(Menu::Exit)
+    }
+
+    fn show_message(&self, _message: &str, options: Vec<UserOption>, _severity: MessageSeverity) -> UserSelection {
+        // The messages are acknowledged, but the questions are declined, as nobody can answer them
+        let option = if options.is_empty() || options.contains(&UserOption::ok()) {
+            UserOption::ok()
+        } else {
+            options.iter()
+                .find(|option| **option == UserOption::no())
+                .or_else(|| options.iter().find(|option| **option == UserOption::cancel()))
+                .cloned()
+                .unwrap_or_else(UserOption::cancel)
+        };
+        UserSelection::UserOption(option)
+    }
+}
+
+/// Finds an Entry by name, preferring the exact match over the case-insensitive one
+pub(crate) fn find_entry<'a>(entries: &'a [Entry], name: &str, user: Option<&str>) -> Option<&'a Entry> {
+    let candidates: Vec<&Entry> = entries.iter()
+        .filter(|entry| user.map(|user| entry.user == user).unwrap_or(true))
+        .collect();
+    candidates.iter().find(|entry| entry.name == name)
+        .or_else(|| candidates.iter().find(|entry| entry.name.eq_ignore_ascii_case(name)))
+        .copied()
+}
+
+#[cfg(test)]
+mod test_loader {
+    use rust_keylock::{Editor, Entry, EntryMeta, EntryPresentationType, Menu, MessageSeverity, UserOption, UserSelection};
+
+    fn entry(name: &str, user: &str, pass: &str) -> Entry {
+        Entry::new(name.to_string(), "https://example.com".to_string(), user.to_string(), pass.to_string(), "desc".to_string(), EntryMeta::default())
+    }
+
+    #[test]
+    fn loader_visits_all_entries() {
+        let loader = super::Loader {
+            password: zeroize::Zeroizing::new("password".to_string()),
+            number: 33,
+            state: Default::default(),
+            result: Default::default(),
+        };
+        assert!(loader.show_password_enter() == UserSelection::new_provided_password("password".to_string(), 33));
+        let entries = vec![entry("a", "u", "encrypted"), entry("b", "u", "encrypted")];
+        assert!(loader.show_entries(entries, "".to_string()) == UserSelection::GoTo(Menu::ShowEntry(0)));
+        assert!(loader.show_entry(entry("a", "u", "pass"), 0, EntryPresentationType::View) == UserSelection::GoTo(Menu::ShowEntry(1)));
+        assert!(loader.show_entry(entry("b", "u", "pass"), 1, EntryPresentationType::View) == UserSelection::GoTo(Menu::Exit));
+        let result = loader.result.lock().unwrap().take().unwrap().unwrap();
+        assert!(result.len() == 2 && result[1].pass == "pass");
+
+        // A wrong password makes the library ask again
+        assert!(loader.show_password_enter() == UserSelection::GoTo(Menu::Exit));
+    }
+
+    #[test]
+    fn loader_declines_the_questions() {
+        let loader = super::Loader {
+            password: zeroize::Zeroizing::new("password".to_string()),
+            number: 33,
+            state: Default::default(),
+            result: Default::default(),
+        };
+        let answer = loader.show_message("leaked", vec![UserOption::yes(), UserOption::no()], MessageSeverity::Warn);
+        assert!(answer == UserSelection::UserOption(UserOption::no()));
+        let answer = loader.show_message("sure?", vec![UserOption::ok(), UserOption::cancel()], MessageSeverity::Info);
+        assert!(answer == UserSelection::UserOption(UserOption::ok()));
+        let answer = loader.show_message("sure?", vec![UserOption::yes(), UserOption::cancel()], MessageSeverity::Info);
+        assert!(answer == UserSelection::UserOption(UserOption::cancel()));
+        let answer = loader.show_message("saved", Vec::new(), MessageSeverity::Info);
+        assert!(answer == UserSelection::UserOption(UserOption::ok()));
+    }
+
+    #[test]
+    fn find_entry() {
+        let entries = vec![entry("GitHub", "aston", "pass1"), entry("github", "other", "pass2")];
+        assert!(super::find_entry(&entries, "github", None).map(|entry| entry.pass.as_str()) == Some("pass2"));
+        assert!(super::find_entry(&entries, "GITHUB", Some("aston")).map(|entry| entry.pass.as_str()) == Some("pass1"));
+        assert!(super::find_entry(&entries, "gitlab", None).is_none());
+    }
+}
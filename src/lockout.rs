@@ -0,0 +1,127 @@
+// Copyright 2017 astonbitecode
+// This file is part of rust-keylock password manager.
+//
+// rust-keylock is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// rust-keylock is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with rust-keylock.  If not, see <http://www.gnu.org/licenses/>.
+//! Slows down guessing the master password.
+//!
+//! The failed unlocks are counted in a file, so that the delay between the attempts survives restarting the shell. The
+//! count is reported and reset after the next successful unlock.
+use std::fs;
+use std::path::PathBuf;
+
+use log::error;
+use serde::{Deserialize, Serialize};
+
+/// Where the failed unlocks are counted
+const FAILURES_FILENAME: &str = ".shell_unlock";
+/// The longest delay between two attempts
+const MAX_BACKOFF_SECONDS: u64 = 300;
+
+/// The unlocks that failed since the last successful one
+#[derive(Debug, Default, PartialEq, Eq, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub(crate) struct UnlockFailures {
+    pub(crate) count: u32,
+    /// When the last unlock failed
+    pub(crate) last_at: i64,
+}
+
+impl UnlockFailures {
+    pub(crate) fn load() -> UnlockFailures {
+        fs::read_to_string(failures_path())
+            .ok()
+            .and_then(|contents| toml::from_str(&contents).map_err(|error| error!("Could not parse the failed unlocks: {}", error)).ok())
+            .unwrap_or_default()
+    }
+
+    pub(crate) fn save(&self) {
+        let res = toml::to_string(self).map_err(|error| error.to_string())
+            .and_then(|contents| fs::write(failures_path(), contents).map_err(|error| error.to_string()));
+        if let Err(error) = res {
+            error!("Could not save the failed unlocks: {}", error);
+        }
+    }
+
+    pub(crate) fn record(&mut self, now: i64) {
+        self.count = self.count.saturating_add(1);
+        self.last_at = now;
+    }
+
+    /// Returns the count and starts over
+    pub(crate) fn take(&mut self) -> u32 {
+        std::mem::take(self).count
+    }
+
+    /// The delay after the last failure: `base_seconds`, doubled with every further failure
+    pub(crate) fn backoff(&self, base_seconds: u64) -> u64 {
+        if self.count == 0 || base_seconds == 0 {
+            return 0;
+        }
+        let factor = 1u64.checked_shl(self.count - 1).unwrap_or(u64::MAX);
+        base_seconds.saturating_mul(factor).min(MAX_BACKOFF_SECONDS)
+    }
+
+    /// How many seconds the User has to wait before the next attempt
+    pub(crate) fn remaining_wait(&self, base_seconds: u64, now: i64) -> u64 {
+        let elapsed = now.saturating_sub(self.last_at).max(0) as u64;
+        self.backoff(base_seconds).saturating_sub(elapsed)
+    }
+}
+
+fn failures_path() -> PathBuf {
+    rust_keylock::default_rustkeylock_location().join(FAILURES_FILENAME)
+}
+
+#[cfg(test)]
+mod test_lockout {
+    use super::UnlockFailures;
+
+    #[test]
+    fn backoff() {
+        let mut failures = UnlockFailures::default();
+        assert!(failures.backoff(2) == 0);
+        failures.record(100);
+        assert!(failures.backoff(2) == 2);
+        failures.record(100);
+        failures.record(100);
+        assert!(failures.backoff(2) == 8);
+        assert!(failures.backoff(0) == 0);
+        for _ in 0..70 {
+            failures.record(100);
+        }
+        assert!(failures.backoff(2) == super::MAX_BACKOFF_SECONDS);
+    }
+
+    #[test]
+    fn remaining_wait() {
+        let mut failures = UnlockFailures::default();
+        failures.record(100);
+        failures.record(100);
+        assert!(failures.remaining_wait(5, 100) == 10);
+        assert!(failures.remaining_wait(5, 104) == 6);
+        assert!(failures.remaining_wait(5, 200) == 0);
+        // A clock that went back does not extend the wait
+        assert!(failures.remaining_wait(5, 50) == 10);
+    }
+
+    #[test]
+    fn take() {
+        let mut failures = UnlockFailures::default();
+        failures.record(100);
+        failures.record(101);
+        assert!(failures.take() == 2);
+        assert!(failures == UnlockFailures::default());
+        assert!(toml::from_str::<UnlockFailures>("count = 3").unwrap().count == 3);
+    }
+}
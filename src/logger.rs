@@ -19,8 +19,9 @@ use chrono;
 use std::fmt;
 use std::error::Error;
 use std::io;
+use std::path::Path;
 
-pub fn init_logging() -> Result<(), ShellLoggerError> {
+pub fn init_logging<P: AsRef<Path>>(log_file: P) -> Result<(), ShellLoggerError> {
     fern::Dispatch::new().format(|out, message, record| {
             out.finish(format_args!("{}[{}][{}] {}",
                                     chrono::Local::now().format("[%Y-%m-%d][%H:%M:%S]"),
@@ -29,7 +30,7 @@ pub fn init_logging() -> Result<(), ShellLoggerError> {
                                     message))
         })
         .level(log::LevelFilter::Debug)
-        .chain(fern::log_file("rust-keylock.log")?)
+        .chain(fern::log_file(log_file)?)
         .apply()?;
 
 	Ok(())
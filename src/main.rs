@@ -19,15 +19,90 @@ extern crate fern;
 extern crate chrono;
 extern crate rpassword;
 
+#[cfg(unix)]
+mod agent;
+mod audit;
+mod browser;
+mod bulk;
+mod cli;
+#[cfg(unix)]
+mod client;
+mod dialog;
+mod dropbox;
+mod exec;
+mod extras;
+mod git_credential;
+mod http;
+mod i18n;
+mod key_source;
+mod loader;
+mod lockout;
 mod logger;
+mod merge;
+mod pager;
+mod policy;
+mod sanitize;
+mod secure;
+mod settings;
 mod shell;
+#[cfg(unix)]
+mod ssh_agent;
+mod ssh_keys;
+mod sync;
+mod theme;
+mod undo;
+mod webdav;
+
+use std::io;
 
 #[allow(dead_code)]
 fn main() {
-    let res = logger::init_logging();
-    if res.is_err() {
-    	println!("Could not initialize logger! Reason: {}", res.err().unwrap())
+    secure::harden_process();
+    let (options, args) = cli::Options::parse(&std::env::args().skip(1).collect::<Vec<String>>());
+    let args = match key_source::parse_args(&args) {
+        Ok((source, ignored, args)) => {
+            // git uses stdin for the credential helper protocol
+            if source == key_source::KeySource::Fd(0) && args.first().map(String::as_str) == Some("git-credential") {
+                cli::exit_on_error(Err(cli::CliError::new(cli::ErrorKind::Usage, "--password-fd 0 cannot be used with git-credential")), &options);
+            }
+            key_source::init(source, ignored);
+            args
+        }
+        Err(error) => {
+            cli::exit_on_error(Err(cli::CliError::new(cli::ErrorKind::Usage, &error)), &options);
+            return;
+        }
+    };
+    match args.first().map(String::as_str) {
+        Some("git-credential") => {
+            // The helper runs in the directories of the git repositories. Do not leave log files there.
+            let mut log_file = rust_keylock::default_rustkeylock_location();
+            log_file.push("rust-keylock.log");
+            let _ = logger::init_logging(log_file);
+            let operation = args.get(1).map(String::as_str).unwrap_or("");
+            cli::exit_on_error(git_credential::run(operation, io::stdin().lock()), &options);
+        }
+        Some("audit") => cli::exit_on_error(audit::run(&args[1..], &options), &options),
+        Some("exec") => cli::exit_on_error(exec::run_exec(&args[1..]), &options),
+        Some("render") => cli::exit_on_error(exec::run_render(&args[1..]), &options),
+        #[cfg(unix)]
+        Some("agent") => cli::exit_on_error(agent::run(&settings::load()), &options),
+        #[cfg(unix)]
+        Some("ssh-agent") => cli::exit_on_error(ssh_agent::run(&settings::load()), &options),
+        #[cfg(unix)]
+        Some(command @ ("status" | "ls" | "get" | "lock" | "unlock")) => cli::exit_on_error(client::run(command, &args[1..], &options), &options),
+        Some(other) => cli::exit_on_error(Err(cli::CliError::new(cli::ErrorKind::Usage, &format!("Unknown command '{}'", other))), &options),
+        None => {
+            let res = logger::init_logging("rust-keylock.log");
+            if res.is_err() {
+            	println!("Could not initialize logger! Reason: {}", res.err().unwrap())
+            }
+            let settings = settings::load();
+            i18n::init(&settings.locale);
+            theme::init(&settings);
+            let shell = shell::new();
+            rust_keylock::execute(Box::new(shell));
+            browser::clear_delayed_copy();
+        }
     }
-    let shell = shell::new();
-    rust_keylock::execute(Box::new(shell));
 }
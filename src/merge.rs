@@ -0,0 +1,368 @@
+// Copyright 2017 astonbitecode
+// This file is part of rust-keylock password manager.
+//
+// rust-keylock is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// rust-keylock is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with rust-keylock.  If not, see <http://www.gnu.org/licenses/>.
+//! The review of the Entries that an import brings in and that clash with the existing ones.
+//!
+//! The library imports by appending every Entry that is not identical to an existing one, so an Entry that changed on
+//! another device ends up twice. The shell remembers the Entries before the import, finds the imported Entries that
+//! clash with them and lets the User merge every pair field by field. Like the bulk actions, the review answers the
+//! following `show_entries` and `show_entry` calls with its next step, until every pair is resolved.
+use std::collections::VecDeque;
+
+use log::warn;
+use rust_keylock::{Entry, Menu, UserSelection};
+
+use crate::extras;
+use crate::i18n;
+
+/// The fields of an Entry that can differ
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub(crate) enum Field {
+    Name,
+    Url,
+    User,
+    Pass,
+    Desc,
+}
+
+pub(crate) const FIELDS: [Field; 5] = [Field::Name, Field::Url, Field::User, Field::Pass, Field::Desc];
+
+impl Field {
+    /// The message id of the name of the field
+    pub(crate) fn label_id(&self) -> &'static str {
+        match self {
+            Field::Name => "field-name",
+            Field::Url => "field-url",
+            Field::User => "field-user",
+            Field::Pass => "field-pass",
+            Field::Desc => "field-desc",
+        }
+    }
+
+    pub(crate) fn is_secret(&self) -> bool {
+        *self == Field::Pass
+    }
+
+    /// The value of the field
+    pub(crate) fn value<'a>(&self, entry: &'a Entry) -> &'a str {
+        match self {
+            Field::Name => &entry.name,
+            Field::Url => &entry.url,
+            Field::User => &entry.user,
+            Field::Pass => &entry.pass,
+            Field::Desc => &entry.desc,
+        }
+    }
+}
+
+/// How a field that differs is merged
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub(crate) enum Choice {
+    KeepMine,
+    TakeTheirs,
+    /// The password of the other Entry goes to the history and the descriptions are joined.
+    /// For the other fields, the other Entry is kept next to the merged one.
+    KeepBoth,
+}
+
+impl Choice {
+    pub(crate) fn label(&self) -> String {
+        i18n::tr(match self {
+            Choice::KeepMine => "merge-keep-mine",
+            Choice::TakeTheirs => "merge-take-theirs",
+            Choice::KeepBoth => "merge-keep-both",
+        })
+    }
+}
+
+/// An existing Entry and an imported one that clash
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub(crate) struct Conflict {
+    pub(crate) mine: Entry,
+    pub(crate) theirs: Entry,
+}
+
+impl Conflict {
+    /// The fields that differ, with the choice that keeps the existing value
+    pub(crate) fn differences(&self) -> Vec<(Field, Choice)> {
+        FIELDS.iter()
+            .filter(|field| field.value(&self.mine) != field.value(&self.theirs))
+            .map(|field| (*field, Choice::KeepMine))
+            .collect()
+    }
+
+    /// The common ancestor of the value of the field, if it is known. Only the history of the passwords tells it, and
+    /// only the existing Entry has one: the data of the shell is not imported.
+    pub(crate) fn base(&self, field: Field) -> Option<String> {
+        if field != Field::Pass {
+            return None;
+        }
+        extras::of(&self.mine).password_history.iter()
+            .find(|previous| previous.pass == self.theirs.pass)
+            .map(|previous| previous.pass.clone())
+    }
+
+    /// Merges the two Entries with the choices and keeps the data of the shell for the merged Entry. Returns the merged
+    /// Entry and whether the imported one is kept as well.
+    pub(crate) fn merge(&self, choices: &[(Field, Choice)], now: i64) -> (Entry, bool) {
+        let mut merged = self.mine.clone();
+        let mut merged_extras = extras::of(&self.mine);
+        let mut keep_theirs = false;
+        for (field, choice) in choices {
+            match (field, choice) {
+                (_, Choice::KeepMine) => {}
+                (Field::Name, Choice::TakeTheirs) => merged.name = self.theirs.name.clone(),
+                (Field::Url, Choice::TakeTheirs) => merged.url = self.theirs.url.clone(),
+                (Field::User, Choice::TakeTheirs) => merged.user = self.theirs.user.clone(),
+                (Field::Pass, Choice::TakeTheirs) => {
+                    merged_extras.push_password(&self.mine.pass, now);
+                    merged_extras.password_changed_at = Some(now);
+                    merged.pass = self.theirs.pass.clone();
+                }
+                (Field::Pass, Choice::KeepBoth) => merged_extras.push_password(&self.theirs.pass, now),
+                (Field::Desc, Choice::TakeTheirs) => merged.desc = self.theirs.desc.clone(),
+                (Field::Desc, Choice::KeepBoth) => merged.desc = format!("{}\n\n{}", self.mine.desc, self.theirs.desc),
+                (_, Choice::KeepBoth) => keep_theirs = true,
+            }
+        }
+        merged_extras.modified_at = Some(now);
+        merged_extras.password_history.retain(|previous| previous.pass != merged.pass);
+        extras::set(&merged, merged_extras);
+        (merged, keep_theirs)
+    }
+}
+
+/// Compares all the fields. The passwords are compared encrypted, as the library encrypts them the same way every time.
+fn same(a: &Entry, b: &Entry) -> bool {
+    a.name == b.name && a.url == b.url && a.user == b.user && a.pass == b.pass && a.desc == b.desc
+}
+
+/// Whether two Entries are versions of the same Entry: they have the same name and username
+fn clash(a: &Entry, b: &Entry) -> bool {
+    a.name.eq_ignore_ascii_case(&b.name) && a.user == b.user
+}
+
+/// Finds the Entries that the import brought in and that clash with the Entries before the import
+pub(crate) fn find_conflicts(before: &[Entry], after: &[Entry]) -> Vec<(Entry, Entry)> {
+    let mut remaining: Vec<&Entry> = before.iter().collect();
+    let mut imported = Vec::new();
+    for entry in after {
+        match remaining.iter().position(|existing| same(existing, entry)) {
+            Some(index) => {
+                remaining.remove(index);
+            }
+            None => imported.push(entry),
+        }
+    }
+    imported.into_iter()
+        .filter_map(|theirs| before.iter().find(|mine| clash(mine, theirs)).map(|mine| (mine.clone(), theirs.clone())))
+        .collect()
+}
+
+/// The next thing the review needs from the library
+#[derive(Debug, PartialEq)]
+pub(crate) enum Step {
+    /// Answer with this selection
+    Select(UserSelection),
+    /// Ask the User to merge the pair. The index is the one of the existing Entry.
+    Resolve(Conflict, usize),
+}
+
+#[derive(Debug)]
+enum Stage {
+    /// The Entries before the import are needed, then the import is started with the selection
+    Snapshot(UserSelection),
+    /// The library imports
+    Importing(Vec<Entry>),
+    /// The pairs are merged one after the other
+    Resolving,
+}
+
+/// A review of the Entries of an import that is in progress
+#[derive(Debug)]
+pub(crate) struct MergeReview {
+    stage: Stage,
+    /// The pairs to merge, as encrypted Entries
+    pairs: VecDeque<(Entry, Entry)>,
+    /// The decrypted Entries of the first pair, as the library presents them for editing
+    decrypted: Vec<Entry>,
+    /// The imported Entry to delete after its pair was merged
+    delete: Option<Entry>,
+    /// How many pairs were merged
+    merged: usize,
+}
+
+impl MergeReview {
+    /// Starts a review for the import selection
+    pub(crate) fn new(import: UserSelection) -> MergeReview {
+        MergeReview { stage: Stage::Snapshot(import), pairs: VecDeque::new(), decrypted: Vec::new(), delete: None, merged: 0 }
+    }
+
+    /// Whether the library imports and the Entries are needed, to find the clashing ones
+    pub(crate) fn is_importing(&self) -> bool {
+        matches!(self.stage, Stage::Importing(_))
+    }
+
+    /// How many pairs were merged
+    pub(crate) fn merged(&self) -> usize {
+        self.merged
+    }
+
+    /// The next step for the presented Entries. Returns `None` when the review is finished.
+    pub(crate) fn next_step(&mut self, entries: &[Entry]) -> Option<Step> {
+        match std::mem::replace(&mut self.stage, Stage::Resolving) {
+            Stage::Snapshot(import) => {
+                self.stage = Stage::Importing(entries.to_vec());
+                return Some(Step::Select(import));
+            }
+            Stage::Importing(before) => self.pairs = find_conflicts(&before, entries).into(),
+            Stage::Resolving => {}
+        }
+        if let Some(imported) = self.delete.take() {
+            match entries.iter().position(|entry| same(entry, &imported)) {
+                Some(index) => return Some(Step::Select(UserSelection::DeleteEntry(index))),
+                None => warn!("The imported Entry '{}' could not be found for deletion", imported.name),
+            }
+        }
+        while let Some((mine, theirs)) = self.pairs.front().cloned() {
+            let (mine_index, theirs_index) = match (entries.iter().position(|entry| same(entry, &mine)), entries.iter().position(|entry| same(entry, &theirs))) {
+                (Some(mine_index), Some(theirs_index)) => (mine_index, theirs_index),
+                _ => {
+                    warn!("The Entry '{}' changed during the review of the import and is not merged", mine.name);
+                    self.pairs.pop_front();
+                    self.decrypted.clear();
+                    continue;
+                }
+            };
+            return match self.decrypted.len() {
+                0 => Some(Step::Select(UserSelection::GoTo(Menu::EditEntry(mine_index)))),
+                1 => Some(Step::Select(UserSelection::GoTo(Menu::EditEntry(theirs_index)))),
+                _ => {
+                    let theirs = self.decrypted.pop().unwrap_or_else(Entry::empty);
+                    let mine = self.decrypted.pop().unwrap_or_else(Entry::empty);
+                    Some(Step::Resolve(Conflict { mine, theirs }, mine_index))
+                }
+            };
+        }
+        None
+    }
+
+    /// Keeps the decrypted Entry that the library presents for editing and returns to the Entries
+    pub(crate) fn entry_selection(&mut self, entry: Entry) -> UserSelection {
+        self.decrypted.push(entry);
+        UserSelection::GoTo(Menu::EntriesList(String::new()))
+    }
+
+    /// Whether the review waits for a decrypted Entry
+    pub(crate) fn awaits_entry(&self) -> bool {
+        matches!(self.stage, Stage::Resolving) && !self.pairs.is_empty() && self.decrypted.len() < 2
+    }
+
+    /// Finishes the first pair. Returns the selection that replaces the existing Entry with the merged one, or `None`
+    /// if the User skipped the pair, so that both Entries stay.
+    pub(crate) fn resolved(&mut self, index: usize, result: Option<(Entry, bool)>) -> Option<UserSelection> {
+        let (_, theirs) = self.pairs.pop_front()?;
+        let (merged, keep_theirs) = result?;
+        self.merged += 1;
+        if !keep_theirs {
+            self.delete = Some(theirs);
+        }
+        Some(UserSelection::ReplaceEntry(index, merged))
+    }
+}
+
+#[cfg(test)]
+mod test_merge {
+    use rust_keylock::{Entry, EntryMeta, Menu, UserSelection};
+
+    use super::{Choice, Conflict, Field, MergeReview, Step};
+    use crate::extras::{self, EntryExtras, PreviousPassword};
+
+    fn entry(name: &str, user: &str, pass: &str, desc: &str) -> Entry {
+        Entry::new(name.to_string(), "https://example.com".to_string(), user.to_string(), pass.to_string(), desc.to_string(), EntryMeta::default())
+    }
+
+    #[test]
+    fn find_conflicts() {
+        let mail = entry("mail", "alice", "one", "");
+        let bank = entry("bank", "alice", "secret", "");
+        let other_mail = entry("mail", "bob", "two", "");
+        let before = vec![mail.clone(), bank.clone(), other_mail.clone()];
+        let changed_mail = entry("Mail", "alice", "three", "");
+        let new = entry("shop", "alice", "four", "");
+        let after = vec![mail.clone(), bank, other_mail, changed_mail.clone(), new];
+        assert!(super::find_conflicts(&before, &after) == vec![(mail, changed_mail)]);
+        assert!(super::find_conflicts(&before, &before).is_empty());
+    }
+
+    #[test]
+    fn differences_and_base() {
+        crate::extras::test_extras::open_store();
+        let mine = entry("bank", "alice", "new", "mine");
+        extras::set(&mine, EntryExtras { password_history: vec![PreviousPassword { pass: "old".to_string(), replaced_at: 10 }], ..Default::default() });
+        let theirs = entry("bank", "alice", "old", "theirs");
+        let conflict = Conflict { mine, theirs };
+        assert!(conflict.differences() == vec![(Field::Pass, Choice::KeepMine), (Field::Desc, Choice::KeepMine)]);
+        assert!(conflict.base(Field::Pass) == Some("old".to_string()));
+        assert!(conflict.base(Field::Desc).is_none());
+    }
+
+    #[test]
+    fn merge() {
+        crate::extras::test_extras::open_store();
+        let conflict = Conflict { mine: entry("forum", "carol", "mine", "my notes"), theirs: entry("forum", "carol2", "theirs", "their notes") };
+        let (merged, keep_theirs) = conflict.merge(&[(Field::User, Choice::TakeTheirs), (Field::Pass, Choice::TakeTheirs), (Field::Desc, Choice::KeepBoth)], 50);
+        assert!(!keep_theirs);
+        assert!(merged.user == "carol2");
+        assert!(merged.pass == "theirs");
+        assert!(merged.desc == "my notes\n\ntheir notes");
+        assert!(extras::of(&merged).password_history[0].pass == "mine");
+        assert!(extras::of(&merged).modified_at == Some(50));
+
+        let (merged, keep_theirs) = conflict.merge(&[(Field::User, Choice::KeepBoth), (Field::Pass, Choice::KeepBoth)], 50);
+        assert!(keep_theirs);
+        assert!(merged.user == "carol");
+        assert!(merged.pass == "mine");
+        assert!(extras::of(&merged).password_history[0].pass == "theirs");
+    }
+
+    #[test]
+    fn review_steps() {
+        let mine = entry("mail", "alice", "one", "");
+        let theirs = entry("mail", "alice", "two", "");
+        let import = UserSelection::new_import_from("/tmp/other.sec".to_string(), "pass".to_string(), 1);
+        let mut review = MergeReview::new(import.clone());
+
+        assert!(review.next_step(std::slice::from_ref(&mine)) == Some(Step::Select(import)));
+        assert!(review.is_importing());
+        let imported = vec![mine.clone(), theirs.clone()];
+        assert!(review.next_step(&imported) == Some(Step::Select(UserSelection::GoTo(Menu::EditEntry(0)))));
+        assert!(review.awaits_entry());
+        assert!(review.entry_selection(mine.clone()) == UserSelection::GoTo(Menu::EntriesList(String::new())));
+        assert!(review.next_step(&imported) == Some(Step::Select(UserSelection::GoTo(Menu::EditEntry(1)))));
+        review.entry_selection(theirs.clone());
+        let conflict = match review.next_step(&imported) {
+            Some(Step::Resolve(conflict, 0)) => conflict,
+            other => panic!("Unexpected step {:?}", other),
+        };
+        let (merged, keep_theirs) = conflict.merge(&[(Field::Pass, Choice::TakeTheirs)], 50);
+        assert!(review.resolved(0, Some((merged.clone(), keep_theirs))) == Some(UserSelection::ReplaceEntry(0, merged.clone())));
+        // The library replaced the Entry
+        let replaced = vec![theirs.clone(), merged.clone()];
+        assert!(review.next_step(&replaced) == Some(Step::Select(UserSelection::DeleteEntry(0))));
+        assert!(review.next_step(&[merged]).is_none());
+        assert!(review.merged() == 1);
+    }
+}
@@ -0,0 +1,141 @@
+// Copyright 2017 astonbitecode
+// This file is part of rust-keylock password manager.
+//
+// rust-keylock is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// rust-keylock is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with rust-keylock.  If not, see <http://www.gnu.org/licenses/>.
+//! Splits the Entries list in pages and columns that fit in the terminal.
+use std::env;
+
+/// The space between the columns
+const COLUMN_GAP: usize = 2;
+/// More columns are hard to read
+const MAX_COLUMNS: usize = 4;
+/// Less rows are not worth paginating
+const MIN_ROWS: usize = 5;
+
+/// The size of the terminal, in rows and columns
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub(crate) struct TerminalSize {
+    pub(crate) rows: usize,
+    pub(crate) columns: usize,
+}
+
+/// Returns the size of the terminal. `LINES` and `COLUMNS` take precedence. `None` if the output is not a terminal.
+pub(crate) fn terminal_size() -> Option<TerminalSize> {
+    let from_env = |name: &str| env::var(name).ok().and_then(|value| value.parse::<usize>().ok()).filter(|value| *value > 0);
+    let queried = query_terminal_size();
+    let rows = from_env("LINES").or(queried.map(|size| size.rows))?;
+    let columns = from_env("COLUMNS").or(queried.map(|size| size.columns))?;
+    Some(TerminalSize { rows, columns })
+}
+
+#[cfg(unix)]
+fn query_terminal_size() -> Option<TerminalSize> {
+    let mut size = libc::winsize { ws_row: 0, ws_col: 0, ws_xpixel: 0, ws_ypixel: 0 };
+    let res = unsafe { libc::ioctl(libc::STDOUT_FILENO, libc::TIOCGWINSZ, &mut size) };
+    if res == 0 && size.ws_row > 0 && size.ws_col > 0 {
+        Some(TerminalSize { rows: size.ws_row as usize, columns: size.ws_col as usize })
+    } else {
+        None
+    }
+}
+
+#[cfg(not(unix))]
+fn query_terminal_size() -> Option<TerminalSize> {
+    None
+}
+
+/// How the items are laid out in a page
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub(crate) struct Layout {
+    pub(crate) rows: usize,
+    pub(crate) columns: usize,
+    /// The width of every column, including the gap
+    pub(crate) column_width: usize,
+}
+
+impl Layout {
+    /// A layout with all the items in one column
+    pub(crate) fn single_column(count: usize) -> Layout {
+        Layout { rows: count.max(1), columns: 1, column_width: 0 }
+    }
+
+    /// Lays out items of up to `item_width` characters in the terminal, leaving `reserved_rows` for the rest
+    pub(crate) fn fit(size: TerminalSize, item_width: usize, reserved_rows: usize) -> Layout {
+        let rows = size.rows.saturating_sub(reserved_rows).max(MIN_ROWS);
+        let column_width = item_width + COLUMN_GAP;
+        let columns = (size.columns / column_width.max(1)).clamp(1, MAX_COLUMNS);
+        Layout { rows, columns, column_width }
+    }
+
+    pub(crate) fn page_size(&self) -> usize {
+        self.rows * self.columns
+    }
+
+    pub(crate) fn pages(&self, count: usize) -> usize {
+        count.div_ceil(self.page_size()).max(1)
+    }
+
+    /// The range of the items of the page, which is limited to the last page
+    pub(crate) fn page_range(&self, count: usize, page: usize) -> std::ops::Range<usize> {
+        let page = page.min(self.pages(count) - 1);
+        let start = page * self.page_size();
+        start..(start + self.page_size()).min(count)
+    }
+
+    /// Arranges the items of a page in rows, filling the columns one after the other
+    pub(crate) fn arrange<T: Clone>(&self, items: &[T]) -> Vec<Vec<T>> {
+        let rows = items.len().div_ceil(self.columns).min(self.rows).max(1);
+        (0..rows)
+            .map(|row| (0..self.columns).filter_map(|column| items.get(column * rows + row).cloned()).collect())
+            .filter(|row: &Vec<T>| !row.is_empty())
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod test_pager {
+    use super::{Layout, TerminalSize};
+
+    #[test]
+    fn fit() {
+        let layout = Layout::fit(TerminalSize { rows: 24, columns: 80 }, 20, 14);
+        assert!(layout == Layout { rows: 10, columns: 3, column_width: 22 });
+        assert!(layout.page_size() == 30);
+
+        let layout = Layout::fit(TerminalSize { rows: 10, columns: 300 }, 10, 14);
+        assert!(layout.rows == 5 && layout.columns == 4);
+
+        let layout = Layout::fit(TerminalSize { rows: 40, columns: 20 }, 60, 10);
+        assert!(layout.columns == 1);
+    }
+
+    #[test]
+    fn pages() {
+        let layout = Layout { rows: 5, columns: 2, column_width: 10 };
+        assert!(layout.pages(0) == 1);
+        assert!(layout.pages(10) == 1);
+        assert!(layout.pages(21) == 3);
+        assert!(layout.page_range(21, 1) == (10..20));
+        assert!(layout.page_range(21, 2) == (20..21));
+        assert!(layout.page_range(21, 7) == (20..21));
+    }
+
+    #[test]
+    fn arrange() {
+        let layout = Layout { rows: 3, columns: 2, column_width: 10 };
+        assert!(layout.arrange(&[1, 2, 3, 4, 5]) == vec![vec![1, 4], vec![2, 5], vec![3]]);
+        assert!(layout.arrange(&[1, 2]) == vec![vec![1, 2]]);
+        assert!(Layout::single_column(3).arrange(&[1, 2, 3]) == vec![vec![1], vec![2], vec![3]]);
+    }
+}
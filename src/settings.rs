@@ -0,0 +1,131 @@
+// Copyright 2017 astonbitecode
+// This file is part of rust-keylock password manager.
+//
+// rust-keylock is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// rust-keylock is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with rust-keylock.  If not, see <http://www.gnu.org/licenses/>.
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::PathBuf;
+
+use log::{debug, error};
+use serde::{Deserialize, Serialize};
+
+use crate::policy::PasswordPolicy;
+use crate::sync::SyncTarget;
+use crate::theme::ThemeDefinition;
+
+const SETTINGS_FILENAME: &str = ".shell_props";
+
+/// Configuration values of the shell. They are stored in plaintext, next to the properties of the library.
+#[derive(Debug, PartialEq, Eq, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub(crate) struct Settings {
+    /// The URL schemes that may be opened in the browser
+    pub(crate) browser_url_schemes: Vec<String>,
+    /// How many seconds after copying the username the password gets copied, when opening a URL
+    pub(crate) password_copy_delay_seconds: u64,
+    /// After how many seconds a password that was copied when opening a URL is cleared from the clipboard
+    pub(crate) clipboard_clear_seconds: u64,
+    /// After how many seconds without requests the agent locks. Zero disables the agent.
+    pub(crate) session_timeout_seconds: u64,
+    /// The delay after a failed unlock, which doubles with every further failure. Zero disables it.
+    pub(crate) unlock_backoff_seconds: u64,
+    /// After how many failed unlocks the shell exits. Zero allows any number of attempts.
+    pub(crate) max_unlock_attempts: u32,
+    /// The names of the Entries whose SSH keys the ssh-agent exposes. If empty, the User selects them when the ssh-agent starts.
+    pub(crate) ssh_agent_entries: Vec<String>,
+    /// The locale of the messages, like `de`. If empty, it is taken from `LC_ALL`, `LC_MESSAGES` or `LANG`.
+    pub(crate) locale: String,
+    /// The name of the colour theme: `default`, `high-contrast`, `monochrome` or one of `themes`
+    pub(crate) theme: String,
+    /// Whether colours are used: `auto`, `always` or `never`. With `auto`, they are used only on terminals and if
+    /// `NO_COLOR` is not set.
+    pub(crate) color: String,
+    /// After how many seconds the Info messages that have a default option close by themselves. Zero disables it.
+    pub(crate) message_timeout_seconds: u64,
+    /// Presents textual markers instead of colour cues and does not clear the screen, for screen readers
+    pub(crate) accessible: bool,
+    /// The rules that a new master password must follow
+    pub(crate) master_password_policy: PasswordPolicy,
+    /// The folders and WebDAV collections the passwords file is synchronised with
+    pub(crate) sync_targets: Vec<SyncTarget>,
+    /// User defined colour themes
+    pub(crate) themes: BTreeMap<String, ThemeDefinition>,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Settings {
+            browser_url_schemes: vec!["http".to_string(), "https".to_string()],
+            password_copy_delay_seconds: 10,
+            clipboard_clear_seconds: 30,
+            session_timeout_seconds: 900,
+            unlock_backoff_seconds: 1,
+            max_unlock_attempts: 0,
+            ssh_agent_entries: Vec::new(),
+            locale: String::new(),
+            theme: "default".to_string(),
+            color: "auto".to_string(),
+            message_timeout_seconds: 0,
+            accessible: false,
+            master_password_policy: PasswordPolicy::default(),
+            sync_targets: Vec::new(),
+            themes: BTreeMap::new(),
+        }
+    }
+}
+
+/// Loads the settings. The defaults are used if there is no settings file or in case of errors. The settings file is
+/// written only when a setting is changed.
+pub(crate) fn load() -> Settings {
+    let path = settings_path();
+    debug!("Loading the shell settings from {:?}", path);
+    if !path.exists() {
+        return Settings::default();
+    }
+    match fs::read_to_string(&path).map_err(|error| error.to_string())
+        .and_then(|contents| toml::from_str(&contents).map_err(|error| error.to_string())) {
+        Ok(settings) => settings,
+        Err(error) => {
+            error!("Could not load the shell settings. Using defaults. The error was: {}", error);
+            Settings::default()
+        }
+    }
+}
+
+pub(crate) fn save(settings: &Settings) {
+    let path = settings_path();
+    let res = toml::to_string(settings).map_err(|error| error.to_string())
+        .and_then(|contents| fs::write(&path, contents).map_err(|error| error.to_string()));
+    if let Err(error) = res {
+        error!("Could not save the shell settings in {:?}: {}", path, error);
+    }
+}
+
+fn settings_path() -> PathBuf {
+    let mut path = rust_keylock::default_rustkeylock_location();
+    path.push(SETTINGS_FILENAME);
+    path
+}
+
+#[cfg(test)]
+mod test_settings {
+    use super::Settings;
+
+    #[test]
+    fn missing_values_get_defaults() {
+        let settings: Settings = toml::from_str("password_copy_delay_seconds = 3").unwrap();
+        assert!(settings.password_copy_delay_seconds == 3);
+        assert!(settings.browser_url_schemes == Settings::default().browser_url_schemes);
+    }
+}
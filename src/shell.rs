@@ -13,27 +13,95 @@
 //
 // You should have received a copy of the GNU General Public License
 // along with rust-keylock.  If not, see <http://www.gnu.org/licenses/>.
-use std::{io, str};
+use std::{fs, io, str};
+use std::collections::BTreeMap;
 use std::io::prelude::*;
+use std::path::Path;
 #[cfg(target_os = "windows")]
 use std::process::Command;
-use std::sync::Mutex;
+use std::sync::{Mutex, MutexGuard};
+use std::thread;
+use std::time::Duration;
 
+use log::error;
 use rpassword;
 use webbrowser;
 
 use rust_keylock::{AllConfigurations, Editor, Entry, EntryPresentationType, Menu, MessageSeverity, UserOption, UserSelection};
 use rust_keylock::dropbox::DropboxConfiguration;
 use rust_keylock::nextcloud::NextcloudConfiguration;
-use termcolor::{Color, ColorChoice, ColorSpec, StandardStream, WriteColor};
+use zeroize::Zeroizing;
+
+use crate::audit::{self, AuditEvent, AuditLog};
+use crate::browser;
+use crate::bulk::{self, BulkAction, BulkOperation, EntryKey};
+use crate::cli::ErrorKind;
+use crate::dialog::{self, Dialog, LoggedMessage, MessageLog};
+use crate::dropbox;
+use crate::extras::{self, EntryExtras, KeyCheck};
+use crate::i18n;
+use crate::key_source;
+use crate::loader;
+use crate::lockout::UnlockFailures;
+use crate::merge::{self, Choice, Conflict, MergeReview, Step};
+use crate::pager;
+use crate::policy::{self, PasswordPolicy};
+use crate::sanitize;
+use crate::secure::Secret;
+use crate::settings::{self, Settings};
+use crate::sync::{self, Merge, Outcome, Preview, Resolution, SyncLogEntry, SyncResult, SyncSession, SyncState, SyncTarget, Synchronisation, TargetKind};
+use crate::theme::{self, Role};
+use crate::undo::{Mutation, UndoHistory};
+use crate::webdav;
 
 /// Editor handler driven by the shell
 pub struct EditorImpl {
+    settings: Settings,
     previous_menu: Mutex<Option<Menu>>,
+    bulk_operation: Mutex<Option<BulkOperation>>,
+    undo_history: Mutex<UndoHistory>,
+    // If true, only the Entries that are due for rotation are presented
+    rotation_filter: Mutex<bool>,
+    // If true, the Entries that are due for rotation are reported the next time the Entries are presented
+    rotation_check_pending: Mutex<bool>,
+    // The page of the Entries list that is presented, for the filter and the rotation filter it was selected with
+    entries_page: Mutex<(String, bool, usize)>,
+    // The messages that were shown in the current session
+    message_log: Mutex<MessageLog>,
+    // The synchronisation with the configured targets
+    sync_session: Mutex<SyncSession>,
+    // The review of the Entries of an import that clash with the existing ones
+    merge_review: Mutex<Option<MergeReview>>,
+    // If true, the library is unlocking with the credentials that were just provided
+    unlock_pending: Mutex<bool>,
+    // The unlocks that failed in this session
+    failed_unlocks: Mutex<u32>,
+    // Verifies the credentials that the library is unlocking with, until it unlocks
+    unlocking_key: Mutex<Option<KeyCheck>>,
+    // Verifies the credentials of the unlocked passwords file, when the master password is changed
+    session_key: Mutex<Option<KeyCheck>>,
+    // The decrypted Entries that are collected before the master password is changed, and how many there are
+    password_change: Mutex<Option<(usize, Vec<Entry>)>>,
 }
 
 pub fn new() -> EditorImpl {
-    EditorImpl { previous_menu: Mutex::new(None) }
+    EditorImpl {
+        settings: settings::load(),
+        previous_menu: Mutex::new(None),
+        bulk_operation: Mutex::new(None),
+        undo_history: Mutex::new(UndoHistory::default()),
+        rotation_filter: Mutex::new(false),
+        rotation_check_pending: Mutex::new(false),
+        entries_page: Mutex::new((String::new(), false, 0)),
+        message_log: Mutex::new(MessageLog::default()),
+        sync_session: Mutex::new(SyncSession::default()),
+        merge_review: Mutex::new(None),
+        unlock_pending: Mutex::new(false),
+        failed_unlocks: Mutex::new(0),
+        unlocking_key: Mutex::new(None),
+        session_key: Mutex::new(None),
+        password_change: Mutex::new(None),
+    }
 }
 
 impl EditorImpl {
@@ -50,7 +118,7 @@ impl EditorImpl {
             }
             Err(error) => {
                 prompt_expect_any(
-                    format!("Warning! Could not update the internal state. Reason: {:?}", error).as_ref(),
+                    &i18n::tr_with("internal-state-update-error", &[("error", &format!("{:?}", error))]),
                     &get_string_from_stdin);
             }
         };
@@ -63,101 +131,741 @@ impl EditorImpl {
             }
             Err(error) => {
                 prompt_expect_any(
-                    format!("Warning! Could not retrieve the internal state. Reason: {:?}", error).as_ref(),
+                    &i18n::tr_with("internal-state-retrieve-error", &[("error", &format!("{:?}", error))]),
                     &get_string_from_stdin);
                 Some(Menu::Main)
             }
         }
     }
+
+    fn start_bulk_operation(&self, entries: &[Entry], presented: &[usize], filter: &str) -> UserSelection {
+        if let Some((action, targets)) = bulk_operation_menu(entries, presented, &get_string_from_stdin) {
+            if let Some(mut bulk_operation_mut) = lock_state(&self.bulk_operation) {
+                *bulk_operation_mut = Some(BulkOperation::new(action, targets));
+            }
+        }
+        self.next_bulk_selection(entries).unwrap_or_else(|| UserSelection::GoTo(Menu::EntriesList(filter.to_string())))
+    }
+
+    /// Collects all the Entries, decrypted, before the master password is changed, so that the new one can be checked
+    /// against their passwords. The library presents them one by one.
+    fn start_password_change(&self) -> UserSelection {
+        if let Some(mut password_change) = lock_state(&self.password_change) {
+            *password_change = Some((0, Vec::new()));
+        }
+        UserSelection::GoTo(Menu::EntriesList(String::new()))
+    }
+
+    /// Starts presenting the Entries to collect, if the master password is to be changed
+    fn password_change_selection(&self, entries: &[Entry]) -> Option<UserSelection> {
+        let mut password_change = lock_state(&self.password_change)?;
+        let (count, _) = password_change.as_mut()?;
+        *count = entries.len();
+        Some(UserSelection::GoTo(if entries.is_empty() { Menu::ChangePass } else { Menu::ShowEntry(0) }))
+    }
+
+    /// Collects an Entry that the library presents, if the master password is to be changed, and presents the next one
+    fn password_change_entry(&self, entry: &Entry, index: usize, presentation_type: &EntryPresentationType) -> Option<UserSelection> {
+        if !matches!(presentation_type, EntryPresentationType::View) {
+            return None;
+        }
+        let mut password_change = lock_state(&self.password_change)?;
+        let (count, entries) = password_change.as_mut()?;
+        entries.push(entry.clone());
+        Some(UserSelection::GoTo(if index + 1 < *count { Menu::ShowEntry(index + 1) } else { Menu::ChangePass }))
+    }
+
+    /// Returns the next step of a bulk action that is in progress, if any.
+    fn next_bulk_selection(&self, entries: &[Entry]) -> Option<UserSelection> {
+        let mut bulk_operation_mut = lock_state(&self.bulk_operation)?;
+        let selection = bulk_operation_mut.as_mut().and_then(|op| op.next_selection(entries));
+        if selection.is_none() {
+            if let Some(op) = bulk_operation_mut.take() {
+                clear();
+                prompt_expect_any(&press_enter_text(&op.summary()), &get_string_from_stdin);
+            }
+        }
+        selection
+    }
+
+    /// Applies a bulk action that is in progress on an Entry that is presented for deletion or editing, if any.
+    fn bulk_entry_selection(&self, entry: &Entry, index: usize) -> Option<UserSelection> {
+        lock_state(&self.bulk_operation)?.as_mut().map(|op| op.entry_selection(entry.clone(), index))
+    }
+
+    /// Keeps track of the selections that change the Entries, so that they can be undone.
+    ///
+    /// `presented` is the Entry that was presented to the User when the selection was made.
+    fn record_mutation(&self, selection: &UserSelection, presented: Option<&Entry>) {
+        if let Some(mut history) = lock_state(&self.undo_history) {
+            match (selection, presented) {
+                (UserSelection::NewEntry(new_entry), _) => history.record(Mutation::Added(new_entry.clone())),
+                (UserSelection::DeleteEntry(_), Some(entry)) => history.record(Mutation::Deleted(entry.clone())),
+                (UserSelection::ReplaceEntry(_, new_entry), Some(entry)) => {
+                    let old = history.take_original().unwrap_or_else(|| entry.clone());
+                    history.record(Mutation::Replaced { old, new: new_entry.clone() });
+                }
+                (UserSelection::GeneratePassphrase(Some(_), _), Some(entry)) => history.remember_original(entry),
+                _ => {
+                    let _ = history.take_original();
+                }
+            }
+        }
+    }
+
+    /// Returns the selection that applies a scheduled undo or redo, if any.
+    ///
+    /// This is also where the last mutation is confirmed, as the library shows the Entries after applying one.
+    fn undo_redo_selection(&self, entries: &[Entry], filter: &str) -> Option<UserSelection> {
+        let mut history = lock_state(&self.undo_history)?;
+        history.confirm();
+        if !history.has_scheduled() {
+            None
+        } else if !filter.is_empty() {
+            // The Entry to revert may be filtered out
+            Some(UserSelection::GoTo(Menu::EntriesList("".to_string())))
+        } else {
+            match history.scheduled_selection(entries)? {
+                Ok(selection) => {
+                    // The data of the shell stays with the Entry, even if the name or the username is reverted
+                    if let UserSelection::ReplaceEntry(index, entry) = &selection {
+                        if let Some(current) = entries.get(*index) {
+                            extras::set(entry, extras::of(current));
+                        }
+                    }
+                    Some(selection)
+                }
+                Err(message) => {
+                    prompt_expect_any(&press_enter_text(&message), &get_string_from_stdin);
+                    None
+                }
+            }
+        }
+    }
+
+    /// Adds the undo and redo options of the Entries menu to the `options` and their inputs to the `expected_inputs`
+    fn undo_redo_options(&self, options: &mut Vec<String>, expected_inputs: &mut Vec<String>) {
+        if let Some(history) = lock_state(&self.undo_history) {
+            if let Some(mutation) = history.next_undo() {
+                let key = i18n::key("key-undo");
+                options.push(i18n::tr_with("entries-undo", &[("key", &key), ("mutation", &mutation.description())]));
+                expected_inputs.push(key);
+            }
+            if let Some(mutation) = history.next_redo() {
+                let key = i18n::key("key-redo");
+                options.push(i18n::tr_with("entries-redo", &[("key", &key), ("mutation", &mutation.description())]));
+                expected_inputs.push(key);
+            }
+        }
+    }
+
+    /// Reports the Entries that are due for rotation, once after unlocking.
+    ///
+    /// The library presents all the Entries right after a successful unlock.
+    fn rotation_check(&self, entries: &[Entry]) {
+        let pending = match lock_state(&self.rotation_check_pending) {
+            Some(mut rotation_check_pending) => std::mem::replace(&mut *rotation_check_pending, false),
+            None => false,
+        };
+        let due = entries.iter().filter(|entry| extras::is_due_for_rotation(entry)).count();
+        if pending && due > 0 {
+            let message = i18n::tr_with("rotation-due-message", &[("count", &due.to_string()), ("key", &i18n::key("key-rotation"))]);
+            let _ = self.show_message(&message, vec![UserOption::ok()], MessageSeverity::Warn);
+        }
+    }
+
+    /// Returns the page of the Entries list to present. Changing the filters starts from the first page.
+    fn entries_page(&self, filter: &str, rotation_filter: bool) -> usize {
+        match lock_state(&self.entries_page) {
+            Some(entries_page) if entries_page.0 == filter && entries_page.1 == rotation_filter => entries_page.2,
+            _ => 0,
+        }
+    }
+
+    fn set_entries_page(&self, filter: &str, rotation_filter: bool, page: usize) {
+        if let Some(mut entries_page) = lock_state(&self.entries_page) {
+            *entries_page = (filter.to_string(), rotation_filter, page);
+        }
+    }
+
+    /// Starts an import. The Entries before it are presented first, to find the imported Entries that clash with them.
+    fn start_import(&self, import: UserSelection) -> UserSelection {
+        if let UserSelection::ImportFrom(path, _, _) = &import {
+            audit::record(AuditEvent::Imported, path);
+        }
+        match lock_state(&self.merge_review) {
+            Some(mut review) => {
+                *review = Some(MergeReview::new(import));
+                UserSelection::GoTo(Menu::EntriesList(String::new()))
+            }
+            None => import,
+        }
+    }
+
+    /// Whether the library imports and the Entries must be presented after it, to review the clashing ones
+    fn import_review_pending(&self) -> bool {
+        lock_state(&self.merge_review).map(|review| review.as_ref().map(MergeReview::is_importing).unwrap_or(false)).unwrap_or(false)
+    }
+
+    /// Returns the next step of the review of an import, if any. The User merges the clashing Entries here.
+    fn merge_review_selection(&self, entries: &[Entry]) -> Option<UserSelection> {
+        let mut review_mut = lock_state(&self.merge_review)?;
+        let review = review_mut.as_mut()?;
+        loop {
+            match review.next_step(entries) {
+                Some(Step::Select(selection)) => return Some(selection),
+                Some(Step::Resolve(conflict, index)) => {
+                    clear();
+                    let result = resolve_entry_conflict(&conflict, &get_string_from_stdin);
+                    if let Some(selection) = review.resolved(index, result) {
+                        return Some(selection);
+                    }
+                }
+                None => {
+                    let merged = review.merged();
+                    *review_mut = None;
+                    if merged > 0 {
+                        clear();
+                        prompt_expect_any(&press_enter_text(&i18n::tr_with("merge-summary", &[("count", &merged.to_string())])), &get_string_from_stdin);
+                    }
+                    return None;
+                }
+            }
+        }
+    }
+
+    /// Keeps an Entry that the library presents decrypted for the review of an import, if the review waits for it
+    fn merge_review_entry_selection(&self, entry: &Entry) -> Option<UserSelection> {
+        lock_state(&self.merge_review)?.as_mut().filter(|review| review.awaits_entry()).map(|review| review.entry_selection(entry.clone()))
+    }
+
+    /// Synchronises the passwords file with the targets before the library loads it, once per session
+    fn synchronise_before_unlock(&self) {
+        let mut session = match lock_state(&self.sync_session) {
+            Some(session) if !session.synchronised_before_unlock => session,
+            _ => return,
+        };
+        session.synchronised_before_unlock = true;
+        let targets = settings::load().sync_targets;
+        if targets.is_empty() {
+            return;
+        }
+        let mut state = SyncState::load();
+        let merge = synchronise(&targets, &mut session.passwords, &mut state, &sync::vault_path(), &sync::merge_path(), true,
+                                &get_string_from_stdin, &get_secret_string_from_stdin);
+        state.save();
+        if let Some(path) = merge {
+            session.merge = Merge::Unlocking(path);
+        }
+    }
+
+    /// The selection that unlocks with the credentials. A copy of a target that is merged is imported with them.
+    fn unlock_selection(&self, password: Zeroizing<String>, number: usize) -> UserSelection {
+        if let Some(mut unlock_pending) = lock_state(&self.unlock_pending) {
+            *unlock_pending = true;
+        }
+        if let Some(mut session) = lock_state(&self.sync_session) {
+            session.merge = match std::mem::take(&mut session.merge) {
+                Merge::Unlocking(path) | Merge::Importing(path, _, _) => Merge::Importing(path, password.clone(), number),
+                other => other,
+            };
+        }
+        extras::open(&password, number);
+        if let Some(mut unlocking_key) = lock_state(&self.unlocking_key) {
+            *unlocking_key = KeyCheck::new(&password, number).inspect_err(|error| error!("{}", error)).ok();
+        }
+        UserSelection::new_provided_password(password, number)
+    }
+
+    /// Records the result of an unlock, if the library is unlocking. It shows a message only if the credentials are wrong.
+    ///
+    /// After a successful unlock, the User is told about the unlocks that failed since the previous one.
+    fn unlock_result(&self, failed: bool) {
+        match lock_state(&self.unlock_pending) {
+            Some(mut unlock_pending) if *unlock_pending => *unlock_pending = false,
+            _ => return,
+        }
+        let key = lock_state(&self.unlocking_key).and_then(|mut unlocking_key| unlocking_key.take());
+        let mut failures = UnlockFailures::load();
+        if failed {
+            audit::record(AuditEvent::UnlockFailed, "");
+            failures.record(chrono::Utc::now().timestamp());
+            failures.save();
+            if let Some(mut failed_unlocks) = lock_state(&self.failed_unlocks) {
+                *failed_unlocks += 1;
+            }
+        } else {
+            audit::record(AuditEvent::Unlocked, "");
+            if let Some(mut session_key) = lock_state(&self.session_key) {
+                *session_key = key;
+            }
+            let count = failures.take();
+            if count > 0 {
+                failures.save();
+                let _ = self.show_message(&failed_unlocks_text(count), vec![UserOption::ok()], MessageSeverity::Warn);
+            }
+        }
+    }
+
+    /// Waits until the delay after the last failed unlock is over
+    fn wait_before_unlock(&self) {
+        let wait = UnlockFailures::load().remaining_wait(self.settings.unlock_backoff_seconds, chrono::Utc::now().timestamp());
+        if wait > 0 {
+            println!("{}", i18n::tr_with("unlock-wait", &[("seconds", &wait.to_string())]));
+            thread::sleep(Duration::from_secs(wait));
+        }
+    }
+
+    /// How many unlocks may still be attempted in this session, if they are limited
+    fn unlock_attempts_left(&self) -> Option<u32> {
+        let max = self.settings.max_unlock_attempts;
+        if max == 0 {
+            return None;
+        }
+        lock_state(&self.failed_unlocks).map(|failed_unlocks| max.saturating_sub(*failed_unlocks))
+    }
+
+    /// Deletes the copy of a target, after the library imported it
+    fn remove_merged_copy(&self) {
+        if let Some(mut session) = lock_state(&self.sync_session) {
+            if let Merge::Imported(path) = &session.merge {
+                if let Err(error) = fs::remove_file(path) {
+                    error!("Could not delete the merged copy {:?}: {}", path, error);
+                }
+                session.merge = Merge::None;
+            }
+        }
+    }
+
+    /// The selection that imports a copy of a target that is merged. After saving, it uploads the passwords file.
+    fn sync_selection(&self) -> Option<UserSelection> {
+        let mut session = lock_state(&self.sync_session)?;
+        if let Merge::Importing(path, password, number) = std::mem::take(&mut session.merge) {
+            session.merge = Merge::Imported(path.clone());
+            return Some(UserSelection::new_import_from(path.display().to_string(), password, number));
+        }
+        if !session.upload_pending {
+            return None;
+        }
+        synchronise_unlocked(&mut session)
+    }
+
+    /// Presents the state of the synchronisation with every target and lets the User synchronise now
+    fn show_synchronisation(&self) -> UserSelection {
+        let (key_sync_now, key_sync_log, key_return) = (i18n::key("key-sync-now"), i18n::key("key-sync-log"), i18n::key("key-return"));
+        let options = vec![
+            (key_sync_now.clone(), i18n::tr("sync-now")),
+            (key_sync_log.clone(), i18n::tr("sync-show-log")),
+            (key_return.clone(), i18n::tr("sync-return")),
+        ];
+        let expected_inputs: Vec<String> = options.iter().map(|(key, _)| key.clone()).collect();
+        loop {
+            let targets = settings::load().sync_targets;
+            println!("{}", sync_status_text(&targets, &SyncState::load(), &sync::vault_path()));
+            let input = prompt_expect(&menu_text("", &options, 1), &expected_inputs, &get_string_from_stdin, true).clone();
+            if input == key_sync_now {
+                if let Some(selection) = lock_state(&self.sync_session).and_then(|mut session| synchronise_unlocked(&mut session)) {
+                    return selection;
+                }
+                prompt_expect_any(&press_enter_text(&i18n::tr("sync-done")), &get_string_from_stdin);
+            } else if input == key_sync_log {
+                show_sync_log(&SyncState::load(), &get_string_from_stdin);
+            } else {
+                return UserSelection::GoTo(Menu::Main);
+            }
+            clear();
+        }
+    }
+
+    fn show_entries_menu(&self, entries: &[Entry], filter: &str) -> UserSelection {
+        let style = theme::current();
+        let mut stdout = style.stdout();
+        let rotation_filter = lock_state(&self.rotation_filter).map(|rf| *rf).unwrap_or(false);
+        let mut header_rows = 0;
+        if !filter.is_empty() {
+            println!("{}\n\n", i18n::tr_with("entries-filtered", &[("filter", filter)]));
+            header_rows += 3;
+        }
+        if rotation_filter {
+            println!("{}\n\n", i18n::tr("entries-due-title"));
+            header_rows += 3;
+        }
+        // The Entries to present, with their label and the role of their colour
+        let mut presented = Vec::new();
+        let mut items = Vec::new();
+        for (index, entry) in entries.iter().enumerate() {
+            let due_for_rotation = extras::is_due_for_rotation(entry);
+            if rotation_filter && !due_for_rotation {
+                continue;
+            }
+            let (role, marker_id) = if entry.meta.leaked_password {
+                (Some(Role::Leaked), "marker-leaked")
+            } else if due_for_rotation {
+                (Some(Role::DueForRotation), "marker-due-for-rotation")
+            } else {
+                (None, "")
+            };
+            let label = if role.is_some() && style.uses_markers() {
+                format!("{}. {} {}", index + 1, sanitize::line(&entry.name), i18n::tr(marker_id))
+            } else {
+                format!("{}. {}", index + 1, sanitize::line(&entry.name))
+            };
+            presented.push(index);
+            items.push((label, role));
+        }
+        // Prompt for user input. The numbers of all the presented Entries may be selected, not only the ones of the current page.
+        let mut expected_inputs = Vec::new();
+        for i in presented.iter() {
+            expected_inputs.push((i + 1).to_string());
+        }
+
+        let (key_new, key_bulk, key_return, key_filter, key_clear_filter, key_rotation) = (
+            i18n::key("key-new"),
+            i18n::key("key-bulk"),
+            i18n::key("key-return"),
+            i18n::key("key-filter"),
+            i18n::key("key-clear-filter"),
+            i18n::key("key-rotation"));
+        let mut options = vec![
+            i18n::tr_with("entries-new", &[("key", &key_new)]),
+        ];
+        expected_inputs.push(key_new.clone());
+        if !presented.is_empty() {
+            options.push(i18n::tr_with("entries-bulk", &[("key", &key_bulk)]));
+            expected_inputs.push(key_bulk.clone());
+        }
+        let rotation_option = if rotation_filter { "entries-show-all" } else { "entries-show-due" };
+        options.push(i18n::tr_with(rotation_option, &[("key", &key_rotation)]));
+        expected_inputs.push(key_rotation.clone());
+        self.undo_redo_options(&mut options, &mut expected_inputs);
+
+        // Paginate, if the Entries do not fit in the terminal
+        let item_width = items.iter().map(|(label, _)| label.chars().count()).max().unwrap_or(0);
+        // The options that follow, the prompt lines and the page indicator
+        let reserved_rows = header_rows + options.len() + 3 + 6;
+        let layout = match pager::terminal_size() {
+            Some(size) => pager::Layout::fit(size, item_width, reserved_rows),
+            None => pager::Layout::single_column(items.len()),
+        };
+        let pages = layout.pages(items.len());
+        let page = self.entries_page(filter, rotation_filter).min(pages - 1);
+        let (key_next_page, key_previous_page, key_goto_page) = (
+            i18n::key("key-next-page"),
+            i18n::key("key-previous-page"),
+            i18n::key("key-goto-page"));
+        if page + 1 < pages {
+            options.push(i18n::tr_with("entries-next-page", &[("key", &key_next_page)]));
+            expected_inputs.push(key_next_page.clone());
+        }
+        if page > 0 {
+            options.push(i18n::tr_with("entries-previous-page", &[("key", &key_previous_page)]));
+            expected_inputs.push(key_previous_page.clone());
+        }
+        if pages > 1 {
+            options.push(i18n::tr_with("entries-goto-page", &[("key", &key_goto_page)]));
+            expected_inputs.push(key_goto_page.clone());
+        }
+
+        options.push(i18n::tr_with("entries-main-menu", &[("key", &key_return)]));
+        expected_inputs.push(key_return.clone());
+        options.push(i18n::tr_with("entries-filter", &[("key", &key_filter)]));
+        expected_inputs.push(key_filter.clone());
+        if !filter.is_empty() {
+            options.push(i18n::tr_with("entries-clear-filter", &[("key", &key_clear_filter)]));
+            expected_inputs.push(key_clear_filter.clone());
+        }
+
+        // Print the Entries of the page
+        for row in layout.arrange(&items[layout.page_range(items.len(), page)]) {
+            let last = row.len() - 1;
+            for (i, (label, role)) in row.iter().enumerate() {
+                match role {
+                    Some(role) => style.set(&mut stdout, *role),
+                    None => style.reset(&mut stdout),
+                }
+                print!("{}", label);
+                style.reset(&mut stdout);
+                if i < last {
+                    print!("{}", " ".repeat(layout.column_width.saturating_sub(label.chars().count())));
+                }
+            }
+            println!();
+        }
+        if pages > 1 {
+            println!("\n{}", i18n::tr_with("entries-page", &[("page", &(page + 1).to_string()), ("pages", &pages.to_string())]));
+        }
+        let message = format!("\n    {},\n{}:\n", i18n::tr("entries-select"), options_text(&options));
+        let input = prompt_expect(&message, &expected_inputs, &get_string_from_stdin, true);
+        // Handle user input
+        match input.as_str() {
+            key if key == key_return => UserSelection::GoTo(Menu::Main),
+            key if key == key_new => UserSelection::GoTo(Menu::NewEntry(None)),
+            key if key == key_filter => {
+                let filter = prompt_expect_any(&prompt_text("filter-prompt"), &get_string_from_stdin);
+                UserSelection::GoTo(Menu::EntriesList(filter))
+            }
+            key if key == key_clear_filter => UserSelection::GoTo(Menu::EntriesList("".to_string())),
+            key if key == key_bulk => self.start_bulk_operation(entries, &presented, filter),
+            key if key == key_rotation => {
+                if let Some(mut rotation_filter_mut) = lock_state(&self.rotation_filter) {
+                    *rotation_filter_mut = !rotation_filter;
+                }
+                UserSelection::GoTo(Menu::EntriesList(filter.to_string()))
+            }
+            key if key == key_next_page => {
+                self.set_entries_page(filter, rotation_filter, page + 1);
+                UserSelection::GoTo(Menu::EntriesList(filter.to_string()))
+            }
+            key if key == key_previous_page => {
+                self.set_entries_page(filter, rotation_filter, page.saturating_sub(1));
+                UserSelection::GoTo(Menu::EntriesList(filter.to_string()))
+            }
+            key if key == key_goto_page => {
+                let requested = prompt_expect_number(&prompt_text_with("page-prompt", &[("pages", &pages.to_string())]), &get_string_from_stdin, false);
+                self.set_entries_page(filter, rotation_filter, requested.clamp(1, pages) - 1);
+                UserSelection::GoTo(Menu::EntriesList(filter.to_string()))
+            }
+            key if key == i18n::key("key-undo") => {
+                if let Some(mut history) = lock_state(&self.undo_history) {
+                    history.schedule_undo();
+                }
+                UserSelection::GoTo(Menu::EntriesList("".to_string()))
+            }
+            key if key == i18n::key("key-redo") => {
+                if let Some(mut history) = lock_state(&self.undo_history) {
+                    history.schedule_redo();
+                }
+                UserSelection::GoTo(Menu::EntriesList("".to_string()))
+            }
+            selection => {
+                let index = selection.parse::<usize>().unwrap() - 1;
+                UserSelection::GoTo(Menu::ShowEntry(index))
+            }
+        }
+    }
 }
 
 impl Editor for EditorImpl {
     fn show_password_enter(&self) -> UserSelection {
         clear();
-        let password = prompt_expect_any("Please provide your password: ", &get_secret_string_from_stdin);
-        let number = prompt_expect_number("What is your favorite number?: ", &get_secret_string_from_stdin, true);
+        // The session is locked. No password may be copied or stay in the clipboard.
+        browser::clear_delayed_copy();
+        self.synchronise_before_unlock();
+        // The session is locked. Nothing can be undone anymore.
+        if let Some(mut history) = lock_state(&self.undo_history) {
+            history.clear();
+        }
+        extras::close();
+        if let Some(mut session_key) = lock_state(&self.session_key) {
+            *session_key = None;
+        }
+        if let Some(mut rotation_check_pending) = lock_state(&self.rotation_check_pending) {
+            *rotation_check_pending = true;
+        }
+        self.wait_before_unlock();
+        let ask_number = || Ok(prompt_expect_number(&prompt_text("number-prompt"), &get_secret_string_from_stdin, true));
+        match key_source::provided_key(&ask_number) {
+            Some(Ok((password, number))) => return self.unlock_selection(password, number),
+            Some(Err(error)) => {
+                eprintln!("{}", error);
+                return UserSelection::GoTo(Menu::Exit);
+            }
+            None => {}
+        }
+        let password = prompt_secret(&prompt_text("password-prompt"));
+        let number = ask_number().unwrap_or_default();
         if password.is_empty() {
-            prompt_expect_any("Password cannot be empty!", &get_secret_string_from_stdin);
+            prompt_expect_any(&i18n::tr("password-empty"), &get_secret_string_from_stdin);
             self.show_password_enter()
         } else {
-            UserSelection::new_provided_password(password, number)
+            self.unlock_selection(password.into_zeroizing(), number)
         }
     }
 
     fn show_change_password(&self) -> UserSelection {
         clear();
-        let password1 = prompt_expect_any("Please provide your password: ", &get_secret_string_from_stdin);
-        let password2 = prompt_expect_any("Please provide your password once again: ", &get_secret_string_from_stdin);
-        if password1 != password2 {
-            let _ = prompt_expect_any("The provided passwords did not match! Press any key to try again", &get_secret_string_from_stdin);
-            self.show_change_password()
-        } else {
-            let number1 = prompt_expect_number("What is your favorite number?: ", &get_secret_string_from_stdin, true);
-            let number2 = prompt_expect_number("Please provide your favorite number once again: ", &get_secret_string_from_stdin, true);
-            if number1 != number2 {
-                let _ = prompt_expect_any("The provided numbers did not match! Press any key to try again", &get_secret_string_from_stdin);
-                self.show_change_password()
+        // Without a passwords file, the library is creating one and there is no current password
+        let changing = sync::vault_path().exists();
+        let entries = lock_state(&self.password_change)
+            .and_then(|mut password_change| password_change.take())
+            .map(|(_, entries)| entries)
+            .unwrap_or_default();
+        // The current credentials are verified with the ones the passwords file was unlocked with
+        let unlock = |password: &str, number: usize| {
+            let verified = lock_state(&self.session_key)
+                .map(|session_key| session_key.as_ref().is_some_and(|key| key.matches(password, number)))
+                .unwrap_or(false);
+            if verified {
+                Ok(entries.clone())
             } else {
-                UserSelection::new_provided_password(password1, number1)
+                audit::record(AuditEvent::UnlockFailed, "master password change");
+                Err(WRONG_CREDENTIALS_MESSAGE.to_string())
+            }
+        };
+        match change_password_wizard(&self.settings.master_password_policy, changing, &unlock, &get_string_from_stdin, &get_secret_string_from_stdin) {
+            Some((password, number)) => {
+                audit::record(AuditEvent::MasterPasswordChanged, "");
+                extras::change_key(&password, number);
+                if let Some(mut session_key) = lock_state(&self.session_key) {
+                    *session_key = KeyCheck::new(&password, number).inspect_err(|error| error!("{}", error)).ok();
+                }
+                UserSelection::new_provided_password(password, number)
             }
+            None => UserSelection::GoTo(Menu::Main),
         }
     }
 
     fn show_menu(&self, menu: &Menu) -> UserSelection {
         clear();
+        // The User left the Entry whose password was to be copied
+        browser::cancel_delayed_copy();
+        self.unlock_result(false);
+        self.remove_merged_copy();
         let selected = match menu {
-            &Menu::Main => show_main_menu(),
+            &Menu::Main if self.import_review_pending() => UserSelection::GoTo(Menu::EntriesList(String::new())),
+            &Menu::Main => match self.sync_selection() {
+                Some(selection) => self.start_import(selection),
+                None => match show_main_menu() {
+                    MainMenuAction::Select(selection) if *selection == UserSelection::GoTo(Menu::ChangePass) => self.start_password_change(),
+                    MainMenuAction::Select(selection) => *selection,
+                    MainMenuAction::Messages => match lock_state(&self.message_log) {
+                        Some(message_log) => show_message_log(&message_log, &get_string_from_stdin),
+                        None => UserSelection::GoTo(Menu::Main),
+                    },
+                    MainMenuAction::Audit => show_audit_log(&AuditLog::default(), &get_string_from_stdin),
+                    MainMenuAction::Synchronisation => {
+                        clear();
+                        match self.show_synchronisation() {
+                            import @ UserSelection::ImportFrom(..) => self.start_import(import),
+                            other => other,
+                        }
+                    }
+                },
+            },
             &Menu::NewEntry(ref entry_opt) => {
+                // An Entry that comes back from the generation of a passphrase brings its data along
+                let entry_extras = entry_opt.as_ref().map(extras::of).unwrap_or_default();
                 let entry = entry_opt.clone().unwrap_or_else(|| Entry::empty());
-                match edit(entry, &get_string_from_stdin) {
-                    EditedEntry::Replace(new_entry) => UserSelection::NewEntry(new_entry),
-                    EditedEntry::GeneratePassphrase(new_entry) => UserSelection::GeneratePassphrase(None, new_entry),
+                match edit(entry, entry_extras, &get_string_from_stdin) {
+                    EditedEntry::Replace(new_entry, mut new_extras) => {
+                        new_extras.touch();
+                        extras::set(&new_entry, new_extras);
+                        UserSelection::NewEntry(new_entry)
+                    }
+                    EditedEntry::GeneratePassphrase(new_entry, new_extras) => {
+                        extras::set(&new_entry, new_extras);
+                        UserSelection::GeneratePassphrase(None, new_entry)
+                    }
                     EditedEntry::Cancel => UserSelection::GoTo(Menu::EntriesList("".to_string())),
                 }
             }
             &Menu::ExportEntries => {
-                let path_input = prompt_expect_any("Please define the path: ", &get_string_from_stdin);
+                let path_input = prompt_expect_any(&prompt_text("path-prompt"), &get_string_from_stdin);
                 UserSelection::ExportTo(path_input)
             }
             &Menu::ImportEntries => {
-                let path_input = prompt_expect_any("Please define the path: ", &get_string_from_stdin);
-                let password = prompt_expect_any("Please provide the password: ", &get_secret_string_from_stdin);
-                let number = prompt_expect_number("What is your favorite number?: ", &get_secret_string_from_stdin, true);
-                UserSelection::new_import_from(path_input, password, number)
+                let path_input = prompt_expect_any(&prompt_text("path-prompt"), &get_string_from_stdin);
+                let password = prompt_secret(&prompt_text("import-password-prompt"));
+                let number = prompt_expect_number(&prompt_text("number-prompt"), &get_secret_string_from_stdin, true);
+                self.start_import(UserSelection::new_import_from(path_input, password.into_zeroizing(), number))
             }
             &Menu::Current => {
+                // The library stays in the current Menu when it rejects a change
+                if let Some(mut history) = lock_state(&self.undo_history) {
+                    history.discard();
+                }
+                extras::discard();
                 UserSelection::GoTo(self.previous_menu().unwrap_or(Menu::Main))
             }
             other => panic!("Menu '{:?}' cannot be used with Entries. Please, consider opening a bug to the developers.", other),
         };
         self.update_internal_state(&selected);
+        self.record_mutation(&selected, None);
+        audit_selection(&selected, None, None);
+        if let UserSelection::GoTo(Menu::Save(_)) = selected {
+            if let Some(mut history) = lock_state(&self.undo_history) {
+                history.clear();
+            }
+            if let Some(mut session) = lock_state(&self.sync_session) {
+                session.upload_pending = true;
+            }
+        }
 
         selected
     }
 
     fn show_entries(&self, entries: Vec<Entry>, filter: String) -> UserSelection {
+        self.unlock_result(false);
+        browser::cancel_delayed_copy();
+        // The library applied the last change
+        extras::confirm();
+        if filter.is_empty() {
+            extras::retain(&entries);
+        }
+        if let Some(selection) = self.password_change_selection(&entries) {
+            return selection;
+        }
+        if let Some(selection) = self.merge_review_selection(&entries).or_else(|| {
+            self.rotation_check(&entries);
+            self.undo_redo_selection(&entries, &filter)
+        }) {
+            let presented = match &selection {
+                UserSelection::DeleteEntry(index) | UserSelection::ReplaceEntry(index, _) => entries.get(*index),
+                _ => None,
+            };
+            audit_selection(&selection, presented, None);
+            return selection;
+        }
+        if let Some(selection) = self.next_bulk_selection(&entries) {
+            return selection;
+        }
         clear();
-        show_entries_menu(&entries, &filter)
+        self.show_entries_menu(&entries, &filter)
     }
 
     fn show_entry(&self, entry: Entry, index: usize, presentation_type: EntryPresentationType) -> UserSelection {
+        if let Some(selection) = self.password_change_entry(&entry, index, &presentation_type) {
+            return selection;
+        }
         clear();
-        match presentation_type {
-            EntryPresentationType::View => show_entry(index, entry),
-            EntryPresentationType::Delete => delete_entry(index),
+        let mut copied = None;
+        let selection = match presentation_type {
+            EntryPresentationType::View => {
+                audit::record(AuditEvent::EntryViewed, &entry.name);
+                let (selection, field) = show_entry(index, entry.clone(), &self.settings);
+                copied = field;
+                selection
+            }
+            EntryPresentationType::Delete => {
+                self.bulk_entry_selection(&entry, index).unwrap_or_else(|| delete_entry(index))
+            }
             EntryPresentationType::Edit => {
-                match edit(entry, &get_string_from_stdin) {
-                    EditedEntry::Replace(new_entry) => UserSelection::ReplaceEntry(index, new_entry),
-                    EditedEntry::GeneratePassphrase(new_entry) => UserSelection::GeneratePassphrase(Some(index), new_entry),
-                    EditedEntry::Cancel => UserSelection::GoTo(Menu::EntriesList("".to_string())),
+                match self.merge_review_entry_selection(&entry).or_else(|| self.bulk_entry_selection(&entry, index)) {
+                    Some(selection) => selection,
+                    None => match edit(entry.clone(), extras::of(&entry), &get_string_from_stdin) {
+                        EditedEntry::Replace(new_entry, new_extras) => {
+                            extras::set(&new_entry, new_extras);
+                            UserSelection::ReplaceEntry(index, new_entry)
+                        }
+                        EditedEntry::GeneratePassphrase(new_entry, new_extras) => {
+                            extras::set(&new_entry, new_extras);
+                            UserSelection::GeneratePassphrase(Some(index), new_entry)
+                        }
+                        EditedEntry::Cancel => UserSelection::GoTo(Menu::EntriesList("".to_string())),
+                    },
                 }
             }
-        }
+        };
+        self.record_mutation(&selection, Some(&entry));
+        audit_selection(&selection, Some(&entry), copied);
+        selection
     }
 
     fn show_configuration(&self, nextcloud: NextcloudConfiguration, dropbox: DropboxConfiguration) -> UserSelection {
         clear();
-        edit_configuration(&nextcloud, &dropbox, &get_string_from_stdin)
+        let selection = edit_configuration(&nextcloud, &dropbox, &get_string_from_stdin);
+        audit_selection(&selection, None, None);
+        selection
     }
 
     fn exit(&self, contents_changed: bool) -> UserSelection {
@@ -165,65 +873,292 @@ impl Editor for EditorImpl {
     }
 
     fn show_message(&self, message: &str, options: Vec<UserOption>, severity: MessageSeverity) -> UserSelection {
-        let mut stdout = StandardStream::stdout(ColorChoice::Always);
-        if severity == MessageSeverity::Error {
-            stdout.set_color(ColorSpec::new().set_fg(Some(Color::Red))).unwrap();
-        } else if severity == MessageSeverity::Warn {
-            stdout.set_color(ColorSpec::new().set_fg(Some(Color::Yellow))).unwrap();
-        } else if severity == MessageSeverity::Info {
-            stdout.set_color(ColorSpec::new().set_fg(Some(Color::Blue))).unwrap();
-        } else {
-            stdout.reset().unwrap();
-        }
-        let mut whole_message = format!("[{:?}] ", severity);
-        whole_message.push_str(message);
-        whole_message.push_str("\n\n\tPress ");
-        let expected_input_tups: Vec<(String, String)> = options.iter()
-            .map(|opt| {
-                if opt.short_label == "o" {
-                    ("Enter".to_string(), opt.label.clone())
-                } else {
-                    (opt.short_label.clone(), opt.label.clone())
-                }
-            })
-            .collect();
+        if message == SAVED_MESSAGE {
+            extras::save();
+        }
+        let unlock_failed = severity == MessageSeverity::Error && message.starts_with(WRONG_CREDENTIALS_MESSAGE);
+        self.unlock_result(unlock_failed);
+        let attempts_left = if unlock_failed { self.unlock_attempts_left() } else { None };
+        let message = match attempts_left {
+            Some(0) => format!("{}\n\n{}", message, i18n::tr("unlock-no-attempts-left")),
+            Some(left) => format!("{}\n\n{}", message, i18n::tr_with("unlock-attempts-left", &[("count", &left.to_string())])),
+            None => message.to_string(),
+        };
+        let style = theme::current();
+        let mut stdout = style.stdout();
+        let dialog = Dialog::new(&message, options, severity);
+        let role = match dialog.severity() {
+            MessageSeverity::Error => Role::Error,
+            MessageSeverity::Warn => Role::Warn,
+            MessageSeverity::Info => Role::Info,
+        };
+        style.set(&mut stdout, role);
+        let mut text = dialog.text();
+        let dismiss_after = dialog.dismisses_after(self.settings.message_timeout_seconds);
+        if let Some(timeout) = dismiss_after {
+            text.push_str("\n\t");
+            text.push_str(&i18n::tr_with("message-dismissed-in", &[("seconds", &timeout.as_secs().to_string())]));
+        }
+        text.push_str("\n\t");
+        text.push_str(&prompt_text("selection-prompt"));
+        let selected = match dismiss_after {
+            Some(timeout) => select_option(&dialog, &text, &|| {
+                // Nothing entered in time selects the default option
+                dialog::read_line_with_timeout(timeout).unwrap_or_else(|| {
+                    println!();
+                    String::new()
+                })
+            }),
+            None => select_option(&dialog, &text, &get_string_from_stdin),
+        };
 
-        for inp in expected_input_tups.iter() {
-            whole_message.push('\'');
-            whole_message.push_str(&inp.0);
-            whole_message.push('\'');
-            whole_message.push_str(" for ");
-            whole_message.push_str(&inp.1);
-            whole_message.push_str(" ");
+        style.reset(&mut stdout);
+        if let Some(mut message_log) = lock_state(&self.message_log) {
+            message_log.record(LoggedMessage {
+                shown_at: chrono::Utc::now().timestamp(),
+                severity: dialog::severity_label(dialog.severity()),
+                message: dialog.message().to_string(),
+                selected: selected.label.clone(),
+            });
+        }
+        if attempts_left == Some(0) {
+            // The library would ask for the password again
+            std::process::exit(ErrorKind::Unlock.exit_code());
         }
 
-        whole_message.push_str("\n\tSelection: ");
-        let expected_inputs: Vec<String> = expected_input_tups.into_iter()
-            .map(|inp| {
-                if inp.0 == "Enter" {
-                    "\n".to_string()
-                } else {
-                    inp.0
-                }
-            })
-            .collect();
-        let selection_string = prompt_expect(&whole_message, &expected_inputs, &get_string_from_stdin_no_trim, true);
-        let user_selection_opt = options.iter().find(|opt| {
-            if selection_string == "\n" {
-                &opt.short_label == "o"
-            } else {
-                &opt.short_label == selection_string
+        UserSelection::UserOption(selected.clone())
+    }
+}
+
+/// Prompts until the input selects an option of the dialog
+fn select_option<'a, T>(dialog: &'a Dialog, text: &str, get_input: &T) -> &'a UserOption
+    where T: Fn() -> String
+{
+    loop {
+        let input = prompt_expect_any(text, get_input);
+        match dialog.select(&input) {
+            Some(option) => return option,
+            None => prompt(&format!("{}\n", i18n::tr_with("wrong-input-value", &[("input", input.trim())]))),
+        }
+    }
+}
+
+/// Presents the messages that were shown in the current session
+fn show_message_log<T>(message_log: &MessageLog, get_input: &T) -> UserSelection
+    where T: Fn() -> String
+{
+    if message_log.is_empty() {
+        println!("{}", i18n::tr("messages-empty"));
+    } else {
+        println!("{}\n", i18n::tr("messages-title"));
+        for logged in message_log.messages() {
+            println!("{} [{}] {} ({})",
+                     extras::format_timestamp(logged.shown_at),
+                     logged.severity,
+                     logged.message,
+                     i18n::tr_with("messages-selected", &[("label", &logged.selected)]));
+        }
+    }
+    prompt_expect_any(&press_enter_text(""), get_input);
+    UserSelection::GoTo(Menu::Main)
+}
+
+/// Presents the last records of the audit log, after verifying it
+fn show_audit_log<T>(log: &AuditLog, get_input: &T) -> UserSelection
+    where T: Fn() -> String
+{
+    println!("{}\n", audit_log_text(log));
+    prompt_expect_any(&press_enter_text(""), get_input);
+    UserSelection::GoTo(Menu::Main)
+}
+
+fn audit_log_text(log: &AuditLog) -> String {
+    let mut text = match log.verify() {
+        Ok(_) => i18n::tr_with("audit-intact", &[("path", &log.path().display().to_string())]),
+        Err(error) => i18n::tr_with("audit-tampered", &[("error", &error)]),
+    };
+    let records = log.records();
+    if records.is_empty() {
+        text.push_str(&format!("\n\n{}", i18n::tr("audit-empty")));
+        return text;
+    }
+    text.push_str(&format!("\n\n{}\n", i18n::tr("audit-title")));
+    for record in records.iter().rev().take(AUDIT_LOG_SHOWN) {
+        text.push_str(&format!("\n{}", record.text()));
+    }
+    if records.len() > AUDIT_LOG_SHOWN {
+        text.push_str(&format!("\n\n{}", i18n::tr_with("audit-older", &[("count", &(records.len() - AUDIT_LOG_SHOWN).to_string())])));
+    }
+    text
+}
+
+/// Records the operations of a selection in the audit log. `presented` is the Entry that the selection concerns and
+/// `copied` the field of it that the selection copies.
+fn audit_selection(selection: &UserSelection, presented: Option<&Entry>, copied: Option<&str>) {
+    if let Some((event, detail)) = audit_event(selection, presented, copied) {
+        audit::record(event, &detail);
+    }
+}
+
+fn audit_event(selection: &UserSelection, presented: Option<&Entry>, copied: Option<&str>) -> Option<(AuditEvent, String)> {
+    match (selection, presented) {
+        (UserSelection::NewEntry(new_entry), _) => Some((AuditEvent::EntryAdded, new_entry.name.clone())),
+        (UserSelection::ReplaceEntry(_, new_entry), Some(entry)) if entry.name != new_entry.name => {
+            Some((AuditEvent::EntryEdited, format!("{} -> {}", entry.name, new_entry.name)))
+        }
+        (UserSelection::ReplaceEntry(_, new_entry), _) => Some((AuditEvent::EntryEdited, new_entry.name.clone())),
+        (UserSelection::DeleteEntry(_), Some(entry)) => Some((AuditEvent::EntryDeleted, entry.name.clone())),
+        // The value itself is never recorded
+        (UserSelection::AddToClipboard(_), Some(entry)) => copied.map(|field| (AuditEvent::EntryCopied, copied_detail(entry, field))),
+        (UserSelection::ExportTo(path), _) => Some((AuditEvent::Exported, path.clone())),
+        (UserSelection::UpdateConfiguration(_), _) => Some((AuditEvent::ConfigurationChanged, String::new())),
+        _ => None,
+    }
+}
+
+/// Asks for a new master password that follows the policy and for the favourite number, and confirms them with a summary.
+///
+/// When `changing`, the current password and number are verified first with `unlock`, which returns the decrypted
+/// Entries, and the User may cancel. Otherwise, the passwords file is new and a master password must be set.
+fn change_password_wizard<T, S, U>(policy: &PasswordPolicy, changing: bool, unlock: &U, get_input: &T, get_secret: &S) -> Option<(Zeroizing<String>, usize)>
+    where T: Fn() -> String,
+          S: Fn() -> String,
+          U: Fn(&str, usize) -> loader::Loaded
+{
+    println!("{}\n\n{}\n", i18n::tr("change-pass-title"), i18n::tr("change-pass-intro"));
+    println!("{}", i18n::tr_with("change-pass-policy", &[("length", &policy.min_length.to_string()), ("score", &policy.min_score.to_string()),
+        ("label", &policy::score_label(policy.min_score))]));
+    if policy.differ_from_entries {
+        println!("{}", i18n::tr("change-pass-policy-entries"));
+    }
+    println!();
+    let entries = if changing { verify_current_password(unlock, get_input, get_secret)? } else { Vec::new() };
+    loop {
+        let password = new_master_password(policy, &entries, changing, get_input, get_secret)?;
+        let number = loop {
+            let number1 = prompt_expect_number(&prompt_text("number-prompt"), get_secret, true);
+            let number2 = prompt_expect_number(&prompt_text("number-again-prompt"), get_secret, true);
+            if number1 == number2 {
+                break number1;
             }
-        });
+            prompt_expect_any(&i18n::tr("numbers-mismatch"), get_input);
+        };
+        println!("\n{}", change_password_summary(&password, policy, changing.then_some(entries.len())));
+        if prompt_yes_no(&format!("\n{}", prompt_text("change-pass-confirm")), get_input) {
+            return Some((password.into_zeroizing(), number));
+        } else if changing {
+            prompt_expect_any(&press_enter_text(&i18n::tr("change-pass-cancelled")), get_input);
+            return None;
+        }
+    }
+}
 
-        stdout.reset().unwrap();
+/// Asks for the current password and number, until they unlock the passwords file. Returns the decrypted Entries.
+fn verify_current_password<T, S, U>(unlock: &U, get_input: &T, get_secret: &S) -> Option<Vec<Entry>>
+    where T: Fn() -> String,
+          S: Fn() -> String,
+          U: Fn(&str, usize) -> loader::Loaded
+{
+    for _ in 0..CURRENT_PASSWORD_ATTEMPTS {
+        let password = Secret::new(prompt_expect_any(&prompt_text("change-pass-current-prompt"), get_secret));
+        if password.is_empty() {
+            return None;
+        }
+        let number = prompt_expect_number(&prompt_text("number-prompt"), get_secret, true);
+        match unlock(&password, number) {
+            Ok(entries) => return Some(entries),
+            Err(error) => println!("{}\n", i18n::tr_with("change-pass-current-wrong", &[("error", &error)])),
+        }
+    }
+    prompt_expect_any(&press_enter_text(&i18n::tr("change-pass-cancelled")), get_input);
+    None
+}
 
-        UserSelection::UserOption(UserOption::from(user_selection_opt.unwrap()))
+/// Asks for a new master password twice, until it follows the policy. Returns `None` if the User cancels, when possible.
+fn new_master_password<T, S>(policy: &PasswordPolicy, entries: &[Entry], can_cancel: bool, get_input: &T, get_secret: &S) -> Option<Secret>
+    where T: Fn() -> String,
+          S: Fn() -> String
+{
+    let prompt_id = if can_cancel { "change-pass-new-prompt-cancel" } else { "change-pass-new-prompt" };
+    loop {
+        let password = Secret::new(prompt_expect_any(&prompt_text(prompt_id), get_secret));
+        if password.is_empty() {
+            if can_cancel {
+                return None;
+            }
+            println!("{}", i18n::tr("password-empty"));
+            continue;
+        }
+        let score = policy::strength_score(&password);
+        println!("{}", i18n::tr_with("change-pass-strength", &[("score", &score.to_string()), ("label", &policy::score_label(score))]));
+        let violations = policy.check(&password, entries);
+        if !violations.is_empty() {
+            println!("{}", i18n::tr("policy-violated"));
+            for violation in violations {
+                println!("  - {}", violation.text());
+            }
+            println!();
+            continue;
+        }
+        let again = Secret::new(prompt_expect_any(&prompt_text("password-again-prompt"), get_secret));
+        if again == password {
+            return Some(password);
+        }
+        prompt_expect_any(&i18n::tr("passwords-mismatch"), get_input);
     }
 }
 
-#[cfg(target_os = "windows")]
+/// What is about to change. `entries` is how many Entry passwords the new password was compared with, when changing.
+fn change_password_summary(password: &str, policy: &PasswordPolicy, entries: Option<usize>) -> String {
+    let score = policy::strength_score(password);
+    let mut text = i18n::tr_with("change-pass-summary", &[
+        ("length", &password.chars().count().to_string()),
+        ("score", &score.to_string()),
+        ("label", &policy::score_label(score))]);
+    if let Some(count) = entries.filter(|_| policy.differ_from_entries) {
+        text.push_str(&format!("\n{}", i18n::tr_with("change-pass-summary-entries", &[("count", &count.to_string())])));
+    }
+    text.push_str(&format!("\n\n{}", i18n::tr("change-pass-summary-save")));
+    text
+}
+
+/// Tells how many unlocks failed since the previous successful one
+fn failed_unlocks_text(count: u32) -> String {
+    if count == 1 {
+        i18n::tr("unlock-failed-since-last-one")
+    } else {
+        i18n::tr_with("unlock-failed-since-last", &[("count", &count.to_string())])
+    }
+}
+
+fn copied_detail(entry: &Entry, field: &str) -> String {
+    format!("{} ({})", entry.name, field)
+}
+
+/// Locks a part of the internal state, warning the User if this is not possible
+fn lock_state<T>(state: &Mutex<T>) -> Option<MutexGuard<'_, T>> {
+    match state.lock() {
+        Ok(guard) => Some(guard),
+        Err(error) => {
+            prompt_expect_any(
+                &i18n::tr_with("internal-state-access-error", &[("error", &format!("{:?}", error))]),
+                &get_string_from_stdin);
+            None
+        }
+    }
+}
+
+/// Clears the screen, unless the accessible mode is on
 fn clear() {
+    if theme::current().clears_screen() {
+        clear_screen();
+    } else {
+        println!();
+    }
+}
+
+#[cfg(target_os = "windows")]
+fn clear_screen() {
     match Command::new("cmd")
         .arg("/c")
         .arg("cls")
@@ -238,139 +1173,216 @@ fn clear() {
 }
 
 #[cfg(not(target_os = "windows"))]
-fn clear() {
+fn clear_screen() {
     print!("{}[2J", 27 as char);
 }
 
-fn show_entries_menu(entries: &[Entry], filter: &str) -> UserSelection {
-    let mut stdout = StandardStream::stdout(ColorChoice::Always);
-    if !filter.is_empty() {
-        println!("Entries filtered by '{}'\n\n", filter);
+/// Presents the Entry and returns the selection of the User, with the field that it copies, if any
+fn show_entry(index: usize, entry: Entry, settings: &Settings) -> (UserSelection, Option<&'static str>) {
+    let entry_extras = extras::of(&entry);
+    print_field("field-name", &entry.name);
+    print_field("field-url", &entry.url);
+    print_field("field-user", &entry.user);
+    print_field("field-pass", &entry.pass);
+    print_field("field-desc", &entry.desc);
+    println!();
+    print_field("field-created", &extras::format_timestamp_opt(entry_extras.created_at));
+    print_field("field-modified", &extras::format_timestamp_opt(entry_extras.modified_at));
+    print_field("field-password-changed", &extras::format_timestamp_opt(entry_extras.password_changed_at));
+    if let Some(days) = entry_extras.rotation_days {
+        let rotation_id = if extras::is_due_for_rotation(&entry) { "rotation-every-due" } else { "rotation-every" };
+        print_field("field-rotation", &i18n::tr_with(rotation_id, &[("days", &days.to_string())]));
     }
-    // Print the entries
-    for (index, entry) in entries.iter().enumerate() {
-        if entry.meta.leaked_password {
-            stdout.set_color(ColorSpec::new().set_fg(Some(Color::Red))).unwrap();
-        } else {
-            stdout.reset().unwrap();
+    if !entry_extras.password_history.is_empty() {
+        print_field("field-previous-passwords", &entry_extras.password_history.len().to_string());
+    }
+
+    let key_return = i18n::key("key-return");
+    let mut options: Vec<(String, String)> = ["entry-edit", "entry-delete", "entry-copy-url", "entry-copy-user", "entry-copy-pass", "entry-history", "entry-open-url"]
+        .iter()
+        .enumerate()
+        .map(|(i, id)| ((i + 1).to_string(), i18n::tr(id)))
+        .collect();
+    options.push((key_return.clone(), i18n::tr("return")));
+    let expected_inputs: Vec<String> = options.iter().map(|(key, _)| key.clone()).collect();
+    let message = menu_text(&i18n::tr("entry-menu-title"), &options, 1);
+    let inner_input = prompt_expect(&message, &expected_inputs, &get_string_from_stdin, true);
+    match inner_input.as_str() {
+        "1" => (UserSelection::GoTo(Menu::EditEntry(index)), None),
+        "2" => (UserSelection::GoTo(Menu::DeleteEntry(index)), None),
+        "3" => (UserSelection::AddToClipboard(entry.url.to_string()), Some("url")),
+        "4" => (UserSelection::AddToClipboard(entry.user.to_string()), Some("user")),
+        "5" => (UserSelection::AddToClipboard(entry.pass.to_string()), Some("pass")),
+        "6" => (show_password_history(index, entry, &get_string_from_stdin), None),
+        "7" => (open_url(index, &entry, settings, &get_string_from_stdin), None),
+        key if key == key_return => (UserSelection::GoTo(Menu::EntriesList("".to_string())), None),
+        other => {
+            panic!("Unexpected user selection '{:?}' in the Show Entry Menu. Please, consider opening a bug to the developers.",
+                   other)
+        }
+    }
+}
+
+/// Opens the URL of an Entry in the browser, optionally copying the username and later the password to the clipboard
+fn open_url<T>(index: usize, entry: &Entry, settings: &Settings, get_input: &T) -> UserSelection
+    where T: Fn() -> String
+{
+    let url = match browser::validate_url(&entry.url, &settings.browser_url_schemes) {
+        Ok(url) => url,
+        Err(error) => {
+            prompt_expect_any(&format!("\n{}", press_enter_text(&error)), get_input);
+            return UserSelection::GoTo(Menu::ShowEntry(index));
+        }
+    };
+
+    let delay = settings.password_copy_delay_seconds;
+    let message = format!("\n{}", prompt_text_with("open-url-copy", &[("delay", &delay.to_string())]));
+    let copy_credentials = prompt_yes_no(&message, get_input);
+    if copy_credentials {
+        match browser::copy_to_clipboard(&entry.user) {
+            Ok(_) => {
+                audit::record(AuditEvent::EntryCopied, &copied_detail(entry, "user"));
+                let clear_after = settings.clipboard_clear_seconds;
+                browser::copy_to_clipboard_later(Zeroizing::new(entry.pass.clone()), Duration::from_secs(delay), Duration::from_secs(clear_after));
+                audit::record(AuditEvent::EntryCopied, &copied_detail(entry, "pass"));
+                println!("{}", i18n::tr_with("open-url-copied", &[("delay", &delay.to_string()), ("clear", &clear_after.to_string())]));
+            }
+            Err(error) => println!("{}", error),
         }
-        println!("{}. {}", index + 1, entry.name);
     }
-    stdout.reset().unwrap();
-    // Prompt for user input
+
+    match browser::open(&url) {
+        Ok(_) => prompt_expect_any(&press_enter_text(&i18n::tr("open-url-opened")), get_input),
+        Err(error) => prompt_expect_any(&press_enter_text(&error), get_input),
+    };
+    UserSelection::GoTo(Menu::ShowEntry(index))
+}
+
+/// Presents the previous passwords of an Entry and lets the User restore one of them
+fn show_password_history<T>(index: usize, entry: Entry, get_input: &T) -> UserSelection
+    where T: Fn() -> String
+{
+    clear();
+    let password_history = extras::of(&entry).password_history;
+    if password_history.is_empty() {
+        prompt_expect_any(&press_enter_text(&i18n::tr("history-empty")), get_input);
+        return UserSelection::GoTo(Menu::ShowEntry(index));
+    }
+
+    println!("{}\n", i18n::tr_with("history-title", &[("name", &sanitize::line(&entry.name))]));
     let mut expected_inputs = Vec::new();
-    for i in 0..entries.len() {
+    for (i, previous) in password_history.iter().enumerate() {
+        println!("{}. {}", i + 1, i18n::tr_with("history-item", &[("pass", &sanitize::line(&previous.pass)), ("at", &extras::format_timestamp(previous.replaced_at))]));
         expected_inputs.push((i + 1).to_string());
     }
+    let key_return = i18n::key("key-return");
+    expected_inputs.push(key_return.clone());
 
-    expected_inputs.push("n".to_string());
-    expected_inputs.push("r".to_string());
-    expected_inputs.push("f".to_string());
-
-    let message = if filter.is_empty() {
-        r#"
-    Please select one of the Entries,
-    press 'n' to crate a new Entry or
-    press 'r' to return to the Main Menu.
-    press 'f' to filter the presented Entries:
- "#
-    } else {
-        expected_inputs.push("c".to_string());
-        r#"
-    Please select one of the Entries,
-    press 'n' to crate a new Entry,
-    press 'r' to return to the Main Menu,
-    press 'f' to filter the presented Entries, or
-    press 'c' to clear the currently applied filter:
-"#
-    };
-    let input = prompt_expect(message, &expected_inputs, &get_string_from_stdin, true);
-    // Handle user input
+    let input = prompt_expect(&format!("\n{}", prompt_text_with("history-select", &[("key", &key_return)])), &expected_inputs, get_input, true);
     match input.as_str() {
-        "r" => UserSelection::GoTo(Menu::Main),
-        "n" => UserSelection::GoTo(Menu::NewEntry(None)),
-        "f" => {
-            let filter = prompt_expect_any("Filter by:", &get_string_from_stdin);
-            UserSelection::GoTo(Menu::EntriesList(filter))
-        }
-        "c" => UserSelection::GoTo(Menu::EntriesList("".to_string())),
+        key if key == key_return => UserSelection::GoTo(Menu::ShowEntry(index)),
         selection => {
-            let index = selection.parse::<usize>().unwrap() - 1;
-            UserSelection::GoTo(Menu::ShowEntry(index))
-        }
-    }
-}
-
-fn show_entry(index: usize, entry: Entry) -> UserSelection {
-    println!("Name: {}", entry.name);
-    println!("URL: {}", entry.url);
-    println!("Username: {}", entry.user);
-    println!("Password: {}", entry.pass);
-    println!("Description: {}", entry.desc);
-
-    let expected_inputs = vec![
-        "1".to_string(),
-        "2".to_string(),
-        "3".to_string(),
-        "4".to_string(),
-        "5".to_string(),
-        "6".to_string()];
-    let message = r#"
-Entry Menu:
-	1: Edit
-	2: Delete
-	3: Copy URL
-	4: Copy username
-	5: Copy Password
-	r: Return
-
-	Selection: "#;
-    let inner_input = prompt_expect(message, &expected_inputs, &get_string_from_stdin, true);
-    match inner_input.as_str() {
-        "1" => UserSelection::GoTo(Menu::EditEntry(index)),
-        "2" => UserSelection::GoTo(Menu::DeleteEntry(index)),
-        "3" => UserSelection::AddToClipboard(entry.url.to_string()),
-        "4" => UserSelection::AddToClipboard(entry.user.to_string()),
-        "5" => UserSelection::AddToClipboard(entry.pass.to_string()),
-        "r" => UserSelection::GoTo(Menu::EntriesList("".to_string())),
-        other => {
-            panic!("Unexpected user selection '{:?}' in the Show Entry Menu. Please, consider opening a bug to the developers.",
-                   other)
+            let mut entry = entry;
+            let mut entry_extras = extras::of(&entry);
+            extras::restore_password(&mut entry, &mut entry_extras, selection.parse::<usize>().unwrap() - 1);
+            entry_extras.touch();
+            extras::set(&entry, entry_extras);
+            UserSelection::ReplaceEntry(index, entry)
         }
     }
 }
 
 fn delete_entry(index: usize) -> UserSelection {
-    let expected_inputs = vec!["y".to_string(), "n".to_string()];
-    let inner_input = prompt_expect("\nAre you sure? (y/n): ", &expected_inputs, &get_string_from_stdin, true);
-    match inner_input.as_str() {
-        "y" => UserSelection::DeleteEntry(index),
-        "n" => UserSelection::GoTo(Menu::EntriesList("".to_string())),
-        other => {
-            panic!("Unexpected user selection '{:?}' in the Delete Entry Menu. Please, consider opening a bug to the developers.",
-                   other)
-        }
+    if prompt_yes_no(&format!("\n{}", prompt_text_with("confirm", &[])), &get_string_from_stdin) {
+        UserSelection::DeleteEntry(index)
+    } else {
+        UserSelection::GoTo(Menu::EntriesList("".to_string()))
     }
 }
 
-fn show_main_menu() -> UserSelection {
-    let message = r#"
-Main Menu:
-	1: Show Existing Entries
-	2: Save changes
-	3: Change Master Password
-	4: Edit Configuration
-	5: Import Encrypted Entries from the filesystem
-	6: Export Entries to the filesystem
-	7: Check the Passwords quality
-	q: Quit
+/// Asks the User to select multiple Entries and an action to apply to all of them.
+///
+/// Returns None if the User cancels.
+fn bulk_operation_menu<T>(entries: &[Entry], presented: &[usize], get_input: &T) -> Option<(BulkAction, Vec<EntryKey>)>
+    where T: Fn() -> String
+{
+    let key_all = i18n::key("key-all");
+    let indexes = loop {
+        let input = prompt_expect_any(&format!("\n{}", prompt_text_with("bulk-select", &[("key", &key_all)])), get_input);
+        if input.is_empty() {
+            return None;
+        } else if input == key_all {
+            break presented.to_vec();
+        }
+        match bulk::parse_selection(&input, entries.len()) {
+            Ok(indexes) => break indexes,
+            Err(error) => prompt(&format!("{}\n", i18n::tr_with("error", &[("error", &error)]))),
+        }
+    };
+
+    let key_return = i18n::key("key-return");
+    let mut options: Vec<(String, String)> = ["bulk-delete", "bulk-tag", "bulk-regenerate"]
+        .iter()
+        .enumerate()
+        .map(|(i, id)| ((i + 1).to_string(), i18n::tr(id)))
+        .collect();
+    options.push((key_return.clone(), i18n::tr("return")));
+    let expected_inputs: Vec<String> = options.iter().map(|(key, _)| key.clone()).collect();
+    let message = menu_text(&i18n::tr_with("bulk-title", &[("count", &indexes.len().to_string())]), &options, 1);
+    let input = prompt_expect(&message, &expected_inputs, get_input, true);
+    let action = match input.as_str() {
+        "1" => BulkAction::Delete,
+        "2" => {
+            let tag = prompt_expect_any(&prompt_text("tag-prompt"), get_input);
+            let tag = tag.trim_start_matches('#');
+            if tag.is_empty() || tag.contains(char::is_whitespace) {
+                prompt_expect_any(&press_enter_text(&i18n::tr("tag-invalid")), get_input);
+                return None;
+            }
+            BulkAction::Tag(tag.to_string())
+        }
+        "3" => BulkAction::RegeneratePassword,
+        key if key == key_return => return None,
+        other => panic!("Unexpected user selection '{:?}' in the Bulk Actions Menu. Please, consider opening a bug to the developers.", other),
+    };
+
+    println!("\n{}", i18n::tr_with("bulk-confirm-list", &[("action", &action.description()), ("count", &indexes.len().to_string())]));
+    for index in indexes.iter() {
+        println!("\t{}. {}", index + 1, sanitize::line(&entries[*index].name));
+    }
+    if prompt_yes_no(&format!("\n{}", prompt_text_with("confirm", &[])), get_input) {
+        Some((action, indexes.iter().map(|index| EntryKey::new(&entries[*index])).collect()))
+    } else {
+        None
+    }
+}
 
-	Selection: "#;
+/// What the User selected in the Main Menu
+enum MainMenuAction {
+    Select(Box<UserSelection>),
+    /// The messages of the session, which the shell presents
+    Messages,
+    /// The synchronisation panel of the shell
+    Synchronisation,
+    /// The audit log of the shell
+    Audit,
+}
 
-    let expected_inputs_main =
-        vec!["1".to_string(), "2".to_string(), "3".to_string(), "4".to_string(), "5".to_string(), "6".to_string(), "7".to_string(), "q".to_string()];
-    let input = prompt_expect(message, &expected_inputs_main, &get_string_from_stdin, true);
-    match input.as_str() {
+/// Presents the Main Menu
+fn show_main_menu() -> MainMenuAction {
+    let key_quit = i18n::key("key-quit");
+    let mut options: Vec<(String, String)> = ["main-entries", "main-save", "main-change-pass", "main-config", "main-import", "main-export", "main-check", "main-messages",
+        "main-sync", "main-audit"]
+        .iter()
+        .enumerate()
+        .map(|(i, id)| ((i + 1).to_string(), i18n::tr(id)))
+        .collect();
+    options.push((key_quit.clone(), i18n::tr("main-quit")));
+    let message = menu_text(&i18n::tr("main-menu-title"), &options, 1);
+
+    let expected_inputs_main: Vec<String> = options.iter().map(|(key, _)| key.clone()).collect();
+    let input = prompt_expect(&message, &expected_inputs_main, &get_string_from_stdin, true);
+    let selection = match input.as_str() {
         "1" => UserSelection::GoTo(Menu::EntriesList("".to_string())),
         "2" => UserSelection::GoTo(Menu::Save(false)),
         "3" => UserSelection::GoTo(Menu::ChangePass),
@@ -378,9 +1390,13 @@ Main Menu:
         "5" => UserSelection::GoTo(Menu::ImportEntries),
         "6" => UserSelection::GoTo(Menu::ExportEntries),
         "7" => UserSelection::CheckPasswords,
-        "q" => UserSelection::GoTo(Menu::Exit),
+        "8" => return MainMenuAction::Messages,
+        "9" => return MainMenuAction::Synchronisation,
+        "10" => return MainMenuAction::Audit,
+        key if key == key_quit => UserSelection::GoTo(Menu::Exit),
         other => panic!("Unexpected user selection '{:?}' in the Main Menu. Please, consider opening a bug to the developers.", other),
-    }
+    };
+    MainMenuAction::Select(Box::new(selection))
 }
 
 fn prompt(message: &str) {
@@ -388,98 +1404,100 @@ fn prompt(message: &str) {
     io::stdout().flush().unwrap();
 }
 
-fn edit<T>(entry: Entry, get_input: &T) -> EditedEntry
+/// Edits an Entry and the data that the shell keeps for it
+fn edit<T>(entry: Entry, entry_extras: EntryExtras, get_input: &T) -> EditedEntry
     where T: Fn() -> String
 {
     clear();
-    let mut entry = entry;
-    println!("Name: {}", entry.name);
-    println!("URL: {}", entry.url);
-    println!("Username: {}", entry.user);
-    println!("Password: {}", entry.pass);
-    println!("Description: {}", entry.desc);
-
-    let expected_inputs = vec![
-        "1".to_string(),
-        "2".to_string(),
-        "3".to_string(),
-        "4".to_string(),
-        "5".to_string(),
-        "6".to_string(),
-        "a".to_string(),
-        "c".to_string()];
-    let message = r#"
-Entry Menu:
-	1: Change Name         5: Generate new passphrase
-	2: Change URL          6: Change Description
-	3: Change Username     a: Accept changes
-	4: Change Password     C: Cancel
-
-	Selection: "#;
-    let inner_input = prompt_expect(message, &expected_inputs, &get_input, true);
+    let (mut entry, mut entry_extras) = (entry, entry_extras);
+    print_field("field-name", &entry.name);
+    print_field("field-url", &entry.url);
+    print_field("field-user", &entry.user);
+    print_field("field-pass", &entry.pass);
+    print_field("field-desc", &entry.desc);
+
+    let (key_accept, key_cancel) = (i18n::key("key-accept"), i18n::key("key-cancel"));
+    let mut options: Vec<(String, String)> = ["edit-name", "edit-url", "edit-user", "edit-pass", "edit-generate", "edit-desc", "edit-rotation"]
+        .iter()
+        .enumerate()
+        .map(|(i, id)| ((i + 1).to_string(), i18n::tr(id)))
+        .collect();
+    options.push((key_accept.clone(), i18n::tr("edit-accept")));
+    options.push((key_cancel.clone(), i18n::tr("edit-cancel")));
+    let expected_inputs: Vec<String> = options.iter().map(|(key, _)| key.clone()).collect();
+    let message = menu_text(&i18n::tr("entry-menu-title"), &options, 2);
+    let inner_input = prompt_expect(&message, &expected_inputs, &get_input, true);
     match inner_input.as_str() {
         "1" => {
-            prompt(format!("Changing Name ({}): ", entry.name).as_str());
+            prompt(&changing_text("field-name", &entry.name));
             let line = get_input();
-            let name = if line.is_empty() {
-                entry.name.clone()
-            } else {
-                line.to_string()
-            };
-            entry.name = name;
-            edit(entry, get_input)
+            if !line.is_empty() {
+                entry.name = line;
+                entry_extras.touch();
+            }
+            edit(entry, entry_extras, get_input)
         }
         "2" => {
-            prompt(format!("Changing URL ({}): ", entry.url).as_str());
+            prompt(&changing_text("field-url", &entry.url));
             let line = get_input();
-            let url = if line.is_empty() {
-                entry.url.clone()
-            } else {
-                line.to_string()
-            };
-            entry.url = url;
-            edit(entry, get_input)
+            if !line.is_empty() {
+                entry.url = line;
+                entry_extras.touch();
+            }
+            edit(entry, entry_extras, get_input)
         }
         "3" => {
-            prompt(format!("Changing Username ({}): ", entry.user).as_str());
+            prompt(&changing_text("field-user", &entry.user));
             let line = get_input();
-            let user = if line.is_empty() {
-                entry.name.clone()
-            } else {
-                line.to_string()
-            };
-            entry.user = user;
-            edit(entry, get_input)
+            if !line.is_empty() {
+                entry.user = line;
+                entry_extras.touch();
+            }
+            edit(entry, entry_extras, get_input)
         }
         "4" => {
-            prompt(format!("Changing Password ({}): ", entry.pass).as_str());
+            prompt(&changing_text("field-pass", &entry.pass));
             let line = get_input();
-            let pass = if line.is_empty() {
-                entry.pass.clone()
-            } else {
-                line.to_string()
-            };
-            entry.pass = pass;
-            edit(entry, get_input)
+            if !line.is_empty() {
+                extras::change_password(&mut entry, &mut entry_extras, line);
+                entry_extras.touch();
+            }
+            edit(entry, entry_extras, get_input)
         }
         "5" => {
-            EditedEntry::GeneratePassphrase(entry)
+            entry_extras.retire_password(&entry.pass);
+            entry_extras.touch();
+            EditedEntry::GeneratePassphrase(entry, entry_extras)
         }
         "6" => {
-            prompt(format!("Changing Description ({}): ", entry.desc).as_str());
+            prompt(&changing_text("field-desc", &entry.desc));
             let line = get_input();
-            let desc = if line.is_empty() {
-                entry.desc.clone()
-            } else {
-                line.to_string()
+            if !line.is_empty() {
+                entry.desc = line;
+                entry_extras.touch();
+            }
+            edit(entry, entry_extras, get_input)
+        }
+        "7" => {
+            let current = entry_extras.rotation_days.unwrap_or(0);
+            let days = loop {
+                let days = prompt_expect_number(
+                    &prompt_text_with("rotation-prompt", &[("current", &current.to_string())]),
+                    get_input,
+                    false);
+                match u32::try_from(days) {
+                    Ok(days) => break days,
+                    Err(_) => prompt(&format!("{}\n", i18n::tr_with("wrong-input-value", &[("input", &days.to_string())]))),
+                }
             };
-            entry.desc = desc;
-            edit(entry, get_input)
+            entry_extras.set_rotation_days(days);
+            entry_extras.touch();
+            edit(entry, entry_extras, get_input)
         }
-        "a" => {
-            EditedEntry::Replace(entry)
+        key if key == key_accept => {
+            EditedEntry::Replace(entry, entry_extras)
         }
-        "c" => {
+        key if key == key_cancel => {
             EditedEntry::Cancel
         }
         other => {
@@ -492,117 +1510,46 @@ Entry Menu:
 fn edit_configuration<T>(nextcloud: &NextcloudConfiguration, dropbox: &DropboxConfiguration, get_input: &T) -> UserSelection
     where T: Fn() -> String
 {
-    let mut ncc = NextcloudConfiguration::new(
-        nextcloud.server_url.to_owned(),
-        nextcloud.username.to_owned(),
-        nextcloud.decrypted_password().unwrap().to_string(),
-        nextcloud.use_self_signed_certificate).unwrap();
+    // The password stays encrypted in the copy
+    let ncc = nextcloud.clone();
     let dbxc = DropboxConfiguration::new(dropbox.decrypted_token().unwrap()).unwrap();
 
-    let message = r#"
-1: Nextcloud configuration
-2: Dropbox configuration
-3: Clear all configurations
-r: Return to Main Menu
-
-	Selection: "#;
-
-    let expected_inputs_main = vec!["1".to_string(), "2".to_string(), "3".to_string(), "r".to_string()];
-    let input = prompt_expect(message, &expected_inputs_main, &get_string_from_stdin, true);
+    let key_return = i18n::key("key-return");
+    let options = vec![
+        ("1".to_string(), i18n::tr("config-nextcloud")),
+        ("2".to_string(), i18n::tr("config-dropbox")),
+        ("3".to_string(), i18n::tr("config-clear")),
+        ("4".to_string(), i18n::tr("config-sync-targets")),
+        (key_return.clone(), i18n::tr("config-return")),
+    ];
+    let message = menu_text("", &options, 1);
+
+    let expected_inputs_main: Vec<String> = options.iter().map(|(key, _)| key.clone()).collect();
+    let input = prompt_expect(&message, &expected_inputs_main, &get_string_from_stdin, true);
     match input.as_str() {
         "1" => {
-            prompt(format!("Server URL ({}): ", nextcloud.server_url).as_str());
-            let mut line = get_input();
-            let url = if line.is_empty() {
-                nextcloud.server_url.clone()
-            } else {
-                line.to_string()
-            };
-
-            prompt(format!("Username ({}): ", nextcloud.username).as_str());
-            line = get_input();
-            let user = if line.is_empty() {
-                nextcloud.username.clone()
-            } else {
-                line.to_string()
-            };
-
-            prompt(format!("password ({}): ", nextcloud.decrypted_password().unwrap().as_str()).as_str());
-            line = get_input();
-            let pass = if line.is_empty() {
-                nextcloud.decrypted_password().unwrap().to_string()
-            } else {
-                line.to_string()
-            };
-
-            let y_n = if nextcloud.use_self_signed_certificate {
-                "y"
-            } else {
-                "n"
-            };
-            prompt(format!("Use a self-signed certificate? (y/n) ({}): ", y_n).as_str());
-            line = get_input();
-            let use_self_signed = if line.is_empty() {
-                nextcloud.use_self_signed_certificate
-            } else {
-                line == "y"
-            };
-
-            ncc = NextcloudConfiguration::new(url, user, pass, use_self_signed).unwrap();
-            UserSelection::UpdateConfiguration(AllConfigurations::new(ncc, dbxc))
+            match edit_nextcloud_configuration(nextcloud, get_input) {
+                Some(ncc) => UserSelection::UpdateConfiguration(AllConfigurations::new(ncc, dbxc)),
+                None => UserSelection::GoTo(Menu::ShowConfiguration),
+            }
         }
         "2" => {
-            let expected_inputs_y_n = vec!["y".to_string(), "n".to_string()];
-            let dbx_url = DropboxConfiguration::dropbox_url();
-
             if !dropbox.is_filled() {
-                let input = prompt_expect("Acquire an authentication token? (y/n):", &expected_inputs_y_n, &get_string_from_stdin, true);
-                match input.as_str() {
-                    "y" => {
-                        match webbrowser::open(&dbx_url) {
-                            Ok(_) => {
-                                prompt("A URL has been opened in your browser. \n\
-                            Please log in your Dropbox account and do the required actions to acquire a Dropbox authentication token.\n");
-                                UserSelection::GoTo(Menu::WaitForDbxTokenCallback(dbx_url))
-                            }
-                            Err(_) => {
-                                prompt_expect_any("Could not open the browser. Press any key to continue.", &get_string_from_stdin);
-                                UserSelection::UpdateConfiguration(AllConfigurations::new(
-                                    ncc,
-                                    DropboxConfiguration::default()))
-                            }
-                        }
-                    }
-                    "n" => {
-                        UserSelection::UpdateConfiguration(AllConfigurations::new(
-                            ncc,
-                            DropboxConfiguration::default()))
-                    }
-                    other => panic!("Unexpected user selection '{:?}' in the Configuration Menu. Please, consider opening a bug to the developers.", other),
+                if prompt_yes_no(&prompt_text_with("dropbox-acquire", &[]), &get_string_from_stdin) {
+                    acquire_dropbox_token(get_input).unwrap_or_else(|| {
+                        UserSelection::UpdateConfiguration(AllConfigurations::new(ncc, DropboxConfiguration::default()))
+                    })
+                } else {
+                    UserSelection::UpdateConfiguration(AllConfigurations::new(
+                        ncc,
+                        DropboxConfiguration::default()))
                 }
+            } else if prompt_yes_no(&prompt_text_with("dropbox-renew", &[]), &get_string_from_stdin) {
+                acquire_dropbox_token(get_input).unwrap_or_else(|| UserSelection::UpdateConfiguration(AllConfigurations::new(ncc, dbxc)))
             } else {
-                let input = prompt_expect("A token is acquired. Do you want to renew? (y/n):", &expected_inputs_y_n, &get_string_from_stdin, true);
-                match input.as_str() {
-                    "y" => {
-                        match webbrowser::open(&dbx_url) {
-                            Ok(_) => {
-                                prompt("A URL has been opened in your browser. \n\
-                            Please log in your Dropbox account and do the required actions to acquire a Dropbox authentication token.\n");
-                                UserSelection::GoTo(Menu::WaitForDbxTokenCallback(dbx_url))
-                            }
-                            Err(_) => {
-                                prompt_expect_any("Could not open the browser. Press any key to continue.", &get_string_from_stdin);
-                                UserSelection::UpdateConfiguration(AllConfigurations::new(ncc, dbxc))
-                            }
-                        }
-                    }
-                    "n" => {
-                        UserSelection::UpdateConfiguration(AllConfigurations::new(
-                            ncc,
-                            DropboxConfiguration::new(dropbox.decrypted_token().unwrap()).unwrap()))
-                    }
-                    other => panic!("Unexpected user selection '{:?}' in the Configuration Menu. Please, consider opening a bug to the developers.", other),
-                }
+                UserSelection::UpdateConfiguration(AllConfigurations::new(
+                    ncc,
+                    DropboxConfiguration::new(dropbox.decrypted_token().unwrap()).unwrap()))
             }
         }
         "3" => {
@@ -610,13 +1557,413 @@ r: Return to Main Menu
                 NextcloudConfiguration::default(),
                 DropboxConfiguration::default()))
         }
-        "r" => {
+        "4" => {
+            let mut settings = settings::load();
+            if edit_sync_targets(&mut settings.sync_targets, get_input) {
+                settings::save(&settings);
+                audit::record(AuditEvent::ConfigurationChanged, "sync targets");
+            }
+            UserSelection::GoTo(Menu::ShowConfiguration)
+        }
+        key if key == key_return => {
             UserSelection::GoTo(Menu::Main)
         }
         other => panic!("Unexpected user selection '{:?}' in the Edit Configuration Menu. Please, consider opening a bug to the developers.", other),
     }
 }
 
+/// Acquires a Dropbox token with the browser, or by letting the User paste the code if there is no browser here.
+/// Returns `None` if the User cancels.
+fn acquire_dropbox_token<T>(get_input: &T) -> Option<UserSelection>
+    where T: Fn() -> String
+{
+    let dbx_url = DropboxConfiguration::dropbox_url();
+    if !dropbox::is_headless() && webbrowser::open(&dbx_url).is_ok() {
+        prompt(&format!("{}\n", i18n::tr("dropbox-browser-opened")));
+        return Some(UserSelection::GoTo(Menu::WaitForDbxTokenCallback(dbx_url)));
+    }
+    acquire_dropbox_token_headless(&dbx_url, dropbox::TOKEN_URL, get_input)
+}
+
+/// Presents the authorization URL and exchanges the code that the User pastes for a token
+fn acquire_dropbox_token_headless<T>(dbx_url: &str, token_url: &str, get_input: &T) -> Option<UserSelection>
+    where T: Fn() -> String
+{
+    let authorization = match dropbox::Authorization::from_url(dbx_url) {
+        Ok(authorization) => authorization,
+        Err(error) => {
+            prompt_expect_any(&press_enter_text(&error), get_input);
+            return None;
+        }
+    };
+    println!("{}\n\n{}\n", i18n::tr("dropbox-headless"), dbx_url);
+    loop {
+        let input = prompt_expect_any(&prompt_text("dropbox-code-prompt"), get_input);
+        if input.trim().is_empty() {
+            return None;
+        }
+        match dropbox::parse_code(&input).and_then(|code| dropbox::exchange_code(token_url, &authorization, &code)) {
+            Ok(token) => return Some(UserSelection::GoTo(Menu::SetDbxToken(token))),
+            Err(error) => println!("{}", error),
+        }
+    }
+}
+
+/// Asks for the Nextcloud server and account, testing the connection if the User wants to.
+/// Returns `None` if the User cancels.
+fn edit_nextcloud_configuration<T>(nextcloud: &NextcloudConfiguration, get_input: &T) -> Option<NextcloudConfiguration>
+    where T: Fn() -> String
+{
+    let current_password = nextcloud.decrypted_password().unwrap_or_default();
+    if nextcloud.server_url.is_empty() {
+        println!("{}", i18n::tr("config-nextcloud-empty-url"));
+    }
+    let url = prompt_field("config-server-url", &nextcloud.server_url, &nextcloud.server_url, get_input, |input| {
+        if input.is_empty() && nextcloud.server_url.is_empty() {
+            Ok(None)
+        } else {
+            webdav::validate_server_url(input).map(Some)
+        }
+    })?;
+    let user = prompt_field("config-username", &nextcloud.username, &nextcloud.username, get_input, |input| {
+        webdav::validate_username(input).map(Some)
+    })?;
+    let masked = if current_password.is_empty() { "" } else { MASKED_SECRET };
+    let pass = Zeroizing::new(prompt_field("config-password", masked, &current_password, get_input, |input| {
+        webdav::validate_password(input).map(|_| Some(input.to_string()))
+    })?);
+    let y_n = if nextcloud.use_self_signed_certificate {
+        i18n::key("key-yes")
+    } else {
+        i18n::key("key-no")
+    };
+    prompt(&current_value_text("config-self-signed", &y_n));
+    let line = get_input();
+    let use_self_signed = if line.is_empty() {
+        nextcloud.use_self_signed_certificate
+    } else {
+        line == i18n::key("key-yes")
+    };
+
+    let (key_test, key_accept, key_cancel) = (i18n::key("key-test"), i18n::key("key-accept"), i18n::key("key-cancel"));
+    let options = vec![
+        (key_test.clone(), i18n::tr("config-test")),
+        (key_accept.clone(), i18n::tr("config-accept")),
+        (key_cancel.clone(), i18n::tr("config-cancel")),
+    ];
+    let message = menu_text("", &options, 1);
+    let expected_inputs: Vec<String> = options.iter().map(|(key, _)| key.clone()).collect();
+    loop {
+        match prompt_expect(&message, &expected_inputs, get_input, true) {
+            key if key == &key_test => {
+                let files_url = webdav::nextcloud_files_url(&url, &user);
+                println!("{}", i18n::tr_with("config-testing", &[("url", &files_url)]));
+                let diagnosis = webdav::test_connection(&files_url, &user, &pass, use_self_signed);
+                println!("{}", diagnosis);
+            }
+            key if key == &key_accept => {
+                match NextcloudConfiguration::new(url.clone(), user.clone(), pass.to_string(), use_self_signed) {
+                    Ok(ncc) => return Some(ncc),
+                    Err(error) => println!("{}", i18n::tr_with("config-invalid", &[("error", &error.to_string())])),
+                }
+            }
+            _ => return None,
+        }
+    }
+}
+
+/// Lists the synchronisation targets and lets the User add and remove them. Returns whether they changed.
+fn edit_sync_targets<T>(targets: &mut Vec<SyncTarget>, get_input: &T) -> bool
+    where T: Fn() -> String
+{
+    let (key_add_folder, key_add_webdav, key_return) = (i18n::key("key-add-folder"), i18n::key("key-add-webdav"), i18n::key("key-return"));
+    let mut changed = false;
+    loop {
+        let mut options: Vec<(String, String)> = targets.iter()
+            .enumerate()
+            .map(|(i, target)| {
+                let id = if target.kind == TargetKind::Folder { "sync-target-folder" } else { "sync-target-webdav" };
+                ((i + 1).to_string(), i18n::tr_with(id, &[("name", &target.name), ("location", &target.location)]))
+            })
+            .collect();
+        if targets.is_empty() {
+            println!("{}", i18n::tr("sync-targets-empty"));
+        }
+        options.push((key_add_folder.clone(), i18n::tr("sync-add-folder")));
+        options.push((key_add_webdav.clone(), i18n::tr("sync-add-webdav")));
+        options.push((key_return.clone(), i18n::tr("config-return-configuration")));
+        let message = menu_text(&i18n::tr("sync-targets-title"), &options, 1);
+        let expected_inputs: Vec<String> = options.iter().map(|(key, _)| key.clone()).collect();
+        let input = prompt_expect(&message, &expected_inputs, get_input, true).clone();
+        let new_target = if input == key_add_folder {
+            add_sync_target(targets, TargetKind::Folder, get_input)
+        } else if input == key_add_webdav {
+            add_sync_target(targets, TargetKind::Webdav, get_input)
+        } else if input == key_return {
+            return changed;
+        } else {
+            let index = input.parse::<usize>().unwrap_or_default().saturating_sub(1);
+            if prompt_yes_no(&prompt_text_with("sync-remove", &[("name", &targets[index].name)]), get_input) {
+                targets.remove(index);
+                changed = true;
+            }
+            None
+        };
+        if let Some(target) = new_target {
+            targets.push(target);
+            changed = true;
+        }
+    }
+}
+
+/// Asks for a new synchronisation target. Returns `None` if the User cancels with an empty name.
+fn add_sync_target<T>(targets: &[SyncTarget], kind: TargetKind, get_input: &T) -> Option<SyncTarget>
+    where T: Fn() -> String
+{
+    let name = prompt_field("sync-target-name", "", "", get_input, |input| {
+        let name = input.trim();
+        if name.is_empty() {
+            Ok(None)
+        } else if targets.iter().any(|target| target.name == name) {
+            Err(i18n::tr_with("sync-target-name-taken", &[("name", name)]))
+        } else {
+            Ok(Some(name.to_string()))
+        }
+    })?;
+    let target = match kind {
+        TargetKind::Folder => {
+            let location = prompt_field("sync-folder-path", "", "", get_input, |input| {
+                if Path::new(input.trim()).is_dir() {
+                    Ok(Some(input.trim().to_string()))
+                } else {
+                    Err(i18n::tr_with("sync-folder-missing", &[("path", input.trim())]))
+                }
+            })?;
+            SyncTarget { name, kind, location, ..Default::default() }
+        }
+        TargetKind::Webdav => {
+            let location = prompt_field("sync-webdav-url", "", "", get_input, |input| webdav::validate_server_url(input).map(Some))?;
+            let username = prompt_field("config-username", "", "", get_input, |input| webdav::validate_username(input).map(Some))?;
+            let self_signed = prompt_yes_no(&prompt_text("config-self-signed"), get_input);
+            SyncTarget { name, kind, location, username, self_signed }
+        }
+    };
+    Some(target)
+}
+
+/// Synchronises the passwords file with the targets, resolving the conflicts with the User.
+/// Returns the copy of a target that the User selected to merge. The remaining targets are synchronised the next time.
+#[allow(clippy::too_many_arguments)]
+fn synchronise<T, S>(targets: &[SyncTarget], passwords: &mut BTreeMap<String, Zeroizing<String>>, state: &mut SyncState, local_path: &Path,
+                     merge_path: &Path, can_download: bool, get_input: &T, get_secret: &S) -> Option<std::path::PathBuf>
+    where T: Fn() -> String,
+          S: Fn() -> String
+{
+    for target in targets {
+        let password = if target.needs_password() {
+            if !passwords.contains_key(&target.name) {
+                let input = prompt_expect_any(&prompt_text_with("sync-password-prompt", &[("name", &target.name), ("username", &target.username)]), get_secret);
+                if input.is_empty() {
+                    state.log(SyncLogEntry { at: chrono::Utc::now().timestamp(), target: target.name.clone(), result: SyncResult::Skipped, detail: String::new() });
+                    continue;
+                }
+                passwords.insert(target.name.clone(), Zeroizing::new(input));
+            }
+            passwords.get(&target.name).map(|password| password.as_str())
+        } else {
+            None
+        };
+        let storage = sync::storage(target, password);
+        let mut synchronisation = Synchronisation {
+            name: &target.name,
+            storage: storage.as_ref(),
+            local_path,
+            state: &mut *state,
+            can_download,
+            now: chrono::Utc::now().timestamp(),
+        };
+        let outcome = match synchronisation.run() {
+            Ok(Outcome::Conflict(preview)) => {
+                let resolution = resolve_conflict(&preview, can_download, get_input);
+                synchronisation.resolve(resolution, merge_path)
+            }
+            other => other,
+        };
+        let result = match &outcome {
+            Ok(outcome) => outcome.result(),
+            Err(_) => Some(SyncResult::Failed),
+        };
+        if let Some(result) = result {
+            let detail = outcome.as_ref().err().cloned().unwrap_or_default();
+            state.log(SyncLogEntry { at: chrono::Utc::now().timestamp(), target: target.name.clone(), result, detail });
+        }
+        let args = [("name", target.name.as_str())];
+        match outcome {
+            Ok(Outcome::Uploaded) => println!("{}", i18n::tr_with("sync-uploaded", &args)),
+            Ok(Outcome::Downloaded) => println!("{}", i18n::tr_with("sync-downloaded", &args)),
+            Ok(Outcome::TargetNewer) => println!("{}", i18n::tr_with("sync-target-newer", &args)),
+            Ok(Outcome::MergePending(path)) => return Some(path),
+            Ok(_) => {}
+            Err(error) => {
+                // The password may be wrong. It is asked again the next time.
+                passwords.remove(&target.name);
+                prompt_expect_any(&press_enter_text(&i18n::tr_with("sync-failed", &[("name", &target.name), ("error", &error)])), get_input);
+            }
+        }
+    }
+    None
+}
+
+/// Presents the fields in which an existing and an imported Entry differ and lets the User choose how to merge each one.
+/// Returns the merged Entry and whether the imported one is kept too, or `None` if the User leaves both as they are.
+fn resolve_entry_conflict<T>(conflict: &Conflict, get_input: &T) -> Option<(Entry, bool)>
+    where T: Fn() -> String
+{
+    let mut choices = conflict.differences();
+    if choices.is_empty() {
+        return Some(conflict.merge(&choices, chrono::Utc::now().timestamp()));
+    }
+    let mut revealed = false;
+    let (key_reveal, key_accept, key_cancel) = (i18n::key("key-reveal"), i18n::key("key-accept"), i18n::key("key-cancel"));
+    loop {
+        let mut options: Vec<(String, String)> = choices.iter()
+            .enumerate()
+            .map(|(i, (field, _))| ((i + 1).to_string(), i18n::tr_with("merge-change-field", &[("field", &i18n::tr(field.label_id()))])))
+            .collect();
+        options.push((key_reveal.clone(), i18n::tr(if revealed { "merge-hide" } else { "merge-reveal" })));
+        options.push((key_accept.clone(), i18n::tr("merge-accept")));
+        options.push((key_cancel.clone(), i18n::tr("merge-skip")));
+        println!("{}\n", i18n::tr_with("merge-title", &[("name", &sanitize::line(&conflict.mine.name))]));
+        println!("{}", conflict_text(conflict, &choices, revealed));
+        let expected_inputs: Vec<String> = options.iter().map(|(key, _)| key.clone()).collect();
+        let input = prompt_expect(&menu_text("", &options, 1), &expected_inputs, get_input, true).clone();
+        if input == key_reveal {
+            revealed = !revealed;
+        } else if input == key_accept {
+            return Some(conflict.merge(&choices, chrono::Utc::now().timestamp()));
+        } else if input == key_cancel {
+            return None;
+        } else if let Some((field, choice)) = input.parse::<usize>().ok().and_then(|number| choices.get_mut(number - 1)) {
+            let options = [(i18n::key("key-keep-mine"), Choice::KeepMine), (i18n::key("key-take-theirs"), Choice::TakeTheirs), (i18n::key("key-keep-both"), Choice::KeepBoth)];
+            let menu: Vec<(String, String)> = options.iter().map(|(key, choice)| (key.clone(), choice.label())).collect();
+            let expected_inputs: Vec<String> = options.iter().map(|(key, _)| key.clone()).collect();
+            let selected = prompt_expect(&menu_text(&i18n::tr(field.label_id()), &menu, 1), &expected_inputs, get_input, true);
+            *choice = options.iter().find(|(key, _)| key == selected).map(|(_, choice)| *choice).unwrap_or(*choice);
+        }
+        clear();
+    }
+}
+
+/// The values of the fields that differ: the common one, if known, the existing and the imported one, and the choice
+fn conflict_text(conflict: &Conflict, choices: &[(merge::Field, Choice)], revealed: bool) -> String {
+    let shown = |field: &merge::Field, value: &str| {
+        if field.is_secret() && !revealed && !value.is_empty() { MASKED_SECRET.to_string() } else { sanitize::line(value).into_owned() }
+    };
+    let labels = [i18n::tr("merge-base"), i18n::tr("merge-mine"), i18n::tr("merge-theirs"), i18n::tr("merge-choice")];
+    let width = labels.iter().map(|label| label.chars().count()).max().unwrap_or(0) + 2;
+    let mut text = String::new();
+    for (i, (field, choice)) in choices.iter().enumerate() {
+        text.push_str(&format!("{}. {}\n", i + 1, i18n::tr(field.label_id())));
+        let base = conflict.base(*field).map(|base| shown(field, &base)).unwrap_or_else(|| i18n::tr("merge-base-unknown"));
+        let values = [base, shown(field, field.value(&conflict.mine)), shown(field, field.value(&conflict.theirs)), choice.label()];
+        for (label, value) in labels.iter().zip(values.iter()) {
+            text.push_str(&format!("     {:width$}{}\n", format!("{}:", label), value, width = width));
+        }
+    }
+    text
+}
+
+/// Synchronises the unlocked passwords file with the targets. Returns the selection that imports a copy that is merged.
+fn synchronise_unlocked(session: &mut SyncSession) -> Option<UserSelection> {
+    session.upload_pending = false;
+    let targets = settings::load().sync_targets;
+    let mut state = SyncState::load();
+    let merge = synchronise(&targets, &mut session.passwords, &mut state, &sync::vault_path(), &sync::merge_path(), false,
+                            &get_string_from_stdin, &get_secret_string_from_stdin);
+    state.save();
+    let path = merge?;
+    let password = prompt_secret(&prompt_text("import-password-prompt"));
+    let number = prompt_expect_number(&prompt_text("number-prompt"), &get_secret_string_from_stdin, true);
+    session.merge = Merge::Imported(path.clone());
+    Some(UserSelection::new_import_from(path.display().to_string(), password.into_zeroizing(), number))
+}
+
+/// The last synchronisation with every target and whether the passwords file changed since then
+fn sync_status_text(targets: &[SyncTarget], state: &SyncState, local_path: &Path) -> String {
+    if targets.is_empty() {
+        return i18n::tr("sync-targets-empty");
+    }
+    let mut text = format!("{}\n", i18n::tr("sync-status-title"));
+    for target in targets {
+        let id = if target.kind == TargetKind::Folder { "sync-target-folder" } else { "sync-target-webdav" };
+        text.push_str(&format!("\n{}\n", i18n::tr_with(id, &[("name", &target.name), ("location", &target.location)])));
+        let synced_at = state.targets.get(&target.name).map(|record| record.synced_at);
+        text.push_str(&format!("    {}\n", i18n::tr_with("sync-status-synced", &[("time", &extras::format_timestamp_opt(synced_at))])));
+        if let Some(last) = state.last_result(&target.name) {
+            let result = if last.detail.is_empty() { last.result.text() } else { format!("{}: {}", last.result.text(), last.detail) };
+            text.push_str(&format!("    {}\n", i18n::tr_with("sync-status-last", &[("time", &extras::format_timestamp(last.at)), ("result", &result)])));
+        }
+        if state.has_local_changes(&target.name, local_path) {
+            text.push_str(&format!("    {}\n", i18n::tr("sync-status-pending")));
+        }
+    }
+    text
+}
+
+/// Presents the logged synchronisations, the latest first
+fn show_sync_log<T>(state: &SyncState, get_input: &T)
+    where T: Fn() -> String
+{
+    if state.log.is_empty() {
+        println!("{}", i18n::tr("sync-log-empty"));
+    } else {
+        println!("{}\n", i18n::tr("sync-log-title"));
+        for entry in state.log.iter().rev() {
+            let mut line = format!("{} {} {}", extras::format_timestamp(entry.at), entry.target, entry.result.text());
+            if !entry.detail.is_empty() {
+                line.push_str(&format!(": {}", entry.detail));
+            }
+            println!("{}", line);
+        }
+    }
+    prompt_expect_any(&press_enter_text(""), get_input);
+}
+
+/// Presents both versions of a conflict and asks how to resolve it. The copy of the target can be taken only before unlocking.
+fn resolve_conflict<T>(preview: &Preview, can_take_target: bool, get_input: &T) -> Resolution
+    where T: Fn() -> String
+{
+    let args = [("name", preview.target.as_str())];
+    let mut options = vec![(i18n::key("key-keep-local"), i18n::tr_with("sync-keep-local", &args), Resolution::KeepLocal)];
+    if can_take_target {
+        options.push((i18n::key("key-take-remote"), i18n::tr_with("sync-take-remote", &args), Resolution::TakeTarget));
+    }
+    options.push((i18n::key("key-merge"), i18n::tr_with("sync-merge", &args), Resolution::Merge));
+    options.push((i18n::key("key-cancel"), i18n::tr("sync-skip"), Resolution::Skip));
+    let menu: Vec<(String, String)> = options.iter().map(|(key, label, _)| (key.clone(), label.clone())).collect();
+    let message = format!("{}\n\n{}", preview.text(), menu_text(&i18n::tr("sync-conflict-title"), &menu, 1));
+    let expected_inputs: Vec<String> = options.iter().map(|(key, _, _)| key.clone()).collect();
+    let input = prompt_expect(&message, &expected_inputs, get_input, true);
+    options.iter().find(|(key, _, _)| key == input).map(|(_, _, resolution)| *resolution).unwrap_or(Resolution::Skip)
+}
+
+/// Prompts for a configuration field until the input is valid. An empty input keeps the current value.
+/// The validation returns `None` to cancel.
+fn prompt_field<T, V>(id: &str, shown: &str, current: &str, get_input: &T, validate: V) -> Option<String>
+    where T: Fn() -> String,
+          V: Fn(&str) -> Result<Option<String>, String>
+{
+    loop {
+        prompt(&current_value_text(id, shown));
+        let line = get_input();
+        let input = if line.is_empty() { current } else { line.as_str() };
+        match validate(input) {
+            Ok(value) => return value,
+            Err(error) => println!("{}", error),
+        }
+    }
+}
+
 fn prompt_expect_any<T>(message: &str, get_input: &T) -> String
     where T: Fn() -> String
 {
@@ -627,14 +1974,14 @@ fn prompt_expect_any<T>(message: &str, get_input: &T) -> String
 fn prompt_expect_number<T>(message: &str, get_input: &T, hide_input_on_error: bool) -> usize
     where T: Fn() -> String
 {
-    let input = prompt_expect_any(message, get_input);
+    let input = Zeroizing::new(prompt_expect_any(message, get_input));
     match input.parse::<usize>() {
         Ok(num) => num,
         Err(_) => {
             let error_message = if hide_input_on_error {
-                "Error: Wrong input\n".to_string()
+                format!("{}\n", i18n::tr("wrong-input"))
             } else {
-                format!("Error: Wrong input '{}'\n", &input)
+                format!("{}\n", i18n::tr_with("wrong-input-value", &[("input", &input)]))
             };
             prompt(error_message.as_str());
             prompt_expect_number(message, get_input, hide_input_on_error)
@@ -652,7 +1999,7 @@ fn prompt_expect<'a, T>(message: &str, expected_inputs: &'a [String], get_input:
     if found.is_some() {
         found.unwrap()
     } else {
-        let error_message = format!("Error: Wrong input '{}'\n", input_str);
+        let error_message = format!("{}\n", i18n::tr_with("wrong-input-value", &[("input", input_str)]));
 
         if retry {
             prompt(error_message.as_str());
@@ -665,21 +2012,11 @@ fn prompt_expect<'a, T>(message: &str, expected_inputs: &'a [String], get_input:
 
 fn show_exit_menu(contents_changed: bool) -> UserSelection {
     if contents_changed {
-        let expected_inputs_main = vec!["y".to_string(), "n".to_string()];
-        let input = prompt_expect("WARNING!\nThere are changes that are not saved! Are you sure you want to Exit? (y/n)",
-                                  &expected_inputs_main,
-                                  &get_string_from_stdin,
-                                  true);
-        match input.as_str() {
-            "n" => UserSelection::GoTo(Menu::Main),
-            "y" => {
-                clear();
-                UserSelection::GoTo(Menu::ForceExit)
-            }
-            other => {
-                panic!("Unexpected user selection '{:?}' in the Show Exit Menu. Please, consider opening a bug to the developers.",
-                       other)
-            }
+        if prompt_yes_no(&tr_with_yes_no("exit-unsaved", &[]), &get_string_from_stdin) {
+            clear();
+            UserSelection::GoTo(Menu::ForceExit)
+        } else {
+            UserSelection::GoTo(Menu::Main)
         }
     } else {
         clear();
@@ -687,55 +2024,156 @@ fn show_exit_menu(contents_changed: bool) -> UserSelection {
     }
 }
 
-fn get_string_from_stdin() -> String {
-    let stdin = io::stdin();
-    let mut line = String::new();
-    stdin.lock().read_line(&mut line).unwrap();
-    line.trim().to_string()
+/// Asks a yes or no question with the shortcut keys of the locale, until one of them is given
+fn prompt_yes_no<T>(message: &str, get_input: &T) -> bool
+    where T: Fn() -> String
+{
+    let key_yes = i18n::key("key-yes");
+    let expected_inputs = vec![key_yes.clone(), i18n::key("key-no")];
+    prompt_expect(message, &expected_inputs, get_input, true) == &key_yes
 }
 
-fn get_string_from_stdin_no_trim() -> String {
-    let stdin = io::stdin();
-    let mut line = String::new();
-    stdin.lock().read_line(&mut line).unwrap();
-    line = line.replace("\r\n", "\n");
-    match line.as_bytes().split_last() {
-        Some((_last, rest)) => {
-            if rest.is_empty() {
-                "\n".to_string()
+/// Returns the message, followed by a space, for prompting
+fn prompt_text(id: &str) -> String {
+    prompt_text_with(id, &[])
+}
+
+/// Returns the message with its arguments, followed by a space, for prompting
+fn prompt_text_with(id: &str, args: &[(&str, &str)]) -> String {
+    format!("{} ", tr_with_yes_no(id, args))
+}
+
+/// Returns the message with its arguments and the `yes` and `no` shortcut keys of the locale
+fn tr_with_yes_no(id: &str, args: &[(&str, &str)]) -> String {
+    let (key_yes, key_no) = (i18n::key("key-yes"), i18n::key("key-no"));
+    let mut all_args = vec![("yes", key_yes.as_str()), ("no", key_no.as_str())];
+    all_args.extend_from_slice(args);
+    i18n::tr_with(id, &all_args)
+}
+
+fn press_enter_text(message: &str) -> String {
+    format!("{}. {}", message.trim_end_matches('.'), i18n::tr("press-enter"))
+}
+
+/// Prints a field. Line feeds are kept, but the other control characters of the value are escaped.
+fn print_field(id: &str, value: &str) {
+    println!("{}: {}", i18n::tr(id), sanitize::lines(value));
+}
+
+/// The prompt for a new value of a field of an Entry, presenting the current value
+fn changing_text(field_id: &str, current: &str) -> String {
+    format!("{} ", i18n::tr_with("changing-field", &[("field", &i18n::tr(field_id)), ("current", &sanitize::line(current))]))
+}
+
+/// The prompt for a configuration value, presenting the current value
+fn current_value_text(id: &str, current: &str) -> String {
+    format!("{} ", i18n::tr_with("current-value", &[("label", &tr_with_yes_no(id, &[])), ("current", current)]))
+}
+
+/// Formats the options of the Entries list, one per line, joined with ',' and with 'or' before the last one
+fn options_text(options: &[String]) -> String {
+    let or = i18n::tr("list-or");
+    options.iter()
+        .enumerate()
+        .map(|(i, option)| {
+            if i + 1 == options.len() {
+                format!("    {}", option)
+            } else if i + 2 == options.len() {
+                format!("    {}, {}", option, or)
             } else {
-                String::from(str::from_utf8(rest).unwrap_or(""))
+                format!("    {},", option)
             }
-        }
-        None => "".to_string(),
+        })
+        .collect::<Vec<String>>()
+        .join("\n")
+}
+
+/// Formats a menu with the shortcut keys and the labels of its options, in the given number of columns
+fn menu_text(title: &str, options: &[(String, String)], columns: usize) -> String {
+    let items: Vec<String> = options.iter().map(|(key, label)| format!("{}: {}", key, label)).collect();
+    let per_column = items.len().div_ceil(columns.max(1)).max(1);
+    let column_items: Vec<&[String]> = items.chunks(per_column).collect();
+    let width = column_items.first().map(|column| column.iter().map(|item| item.chars().count()).max().unwrap_or(0)).unwrap_or(0) + 3;
+    let mut message = String::from("\n");
+    if !title.is_empty() {
+        message.push_str(title);
+        message.push('\n');
     }
+    for row in 0..per_column {
+        let line: Vec<String> = column_items.iter()
+            .filter_map(|column| column.get(row))
+            .map(|item| format!("{:<width$}", item, width = width))
+            .collect();
+        message.push('\t');
+        message.push_str(line.join("").trim_end());
+        message.push('\n');
+    }
+    message.push_str("\n\t");
+    message.push_str(&prompt_text("selection-prompt"));
+    message
+}
+
+fn get_string_from_stdin() -> String {
+    let stdin = io::stdin();
+    let mut line = String::new();
+    stdin.lock().read_line(&mut line).unwrap();
+    line.trim().to_string()
 }
 
 fn get_secret_string_from_stdin() -> String {
     rpassword::prompt_password("").unwrap()
 }
 
+/// Reads a secret without echoing it. Its memory is locked and zeroed when dropped.
+fn prompt_secret(message: &str) -> Secret {
+    Secret::new(prompt_expect_any(message, &get_secret_string_from_stdin))
+}
+
+/// Presented instead of a secret that is already set
+const MASKED_SECRET: &str = "********";
+/// How the library tells that the passwords file could not be unlocked
+const WRONG_CREDENTIALS_MESSAGE: &str = "Wrong password or number";
+/// How the library tells that the passwords file was saved
+const SAVED_MESSAGE: &str = "Encrypted and saved successfully!";
+/// How many records of the audit log are presented
+const AUDIT_LOG_SHOWN: usize = 100;
+/// How many times the current password may be wrong, when changing the master password
+const CURRENT_PASSWORD_ATTEMPTS: usize = 3;
+
 #[derive(Debug, PartialEq, Eq)]
 enum EditedEntry {
-    Replace(Entry),
-    GeneratePassphrase(Entry),
+    Replace(Entry, EntryExtras),
+    GeneratePassphrase(Entry, EntryExtras),
     Cancel,
 }
 
 #[cfg(test)]
 mod test_shell {
+    use std::collections::BTreeMap;
+    use std::fs;
     use std::sync::{Arc, Mutex};
 
-    use rust_keylock::{Editor, Entry, EntryMeta};
-
+    use rust_keylock::{Editor, Entry, EntryMeta, EntryPresentationType, Menu, UserSelection};
+    use rust_keylock::nextcloud::NextcloudConfiguration;
+    use zeroize::Zeroizing;
+
+    use crate::audit::{AuditEvent, AuditLog};
+    use crate::bulk::BulkAction;
+    use crate::dropbox::test_dropbox;
+    use crate::extras::{self, EntryExtras};
+    use crate::i18n;
+    use crate::merge::Conflict;
+    use crate::policy::PasswordPolicy;
     use crate::shell::EditedEntry;
+    use crate::sync::{SyncResult, SyncState, SyncTarget, TargetKind};
+    use crate::webdav::test_webdav;
 
     #[test]
     fn edit_change() {
         let entry = Entry::new("name".to_string(), "url".to_string(), "user".to_string(), "pass".to_string(), "desc".to_string(), EntryMeta::default());
         let i = Arc::new(Mutex::new(0));
         let ci = Arc::clone(&i);
-        let edited_entry = super::edit(entry, &|| {
+        let edited_entry = super::edit(entry, EntryExtras::default(), &|| {
             let mut ii = ci.lock().unwrap();
             *ii += 1;
             if *ii == 1 {
@@ -765,12 +2203,14 @@ mod test_shell {
             }
         });
         match edited_entry {
-            EditedEntry::Replace(new_entry) => {
+            EditedEntry::Replace(new_entry, new_extras) => {
                 assert!(new_entry.name == "newname");
                 assert!(new_entry.url == "newurl");
                 assert!(new_entry.user == "newusername");
                 assert!(new_entry.pass == "newpass");
                 assert!(new_entry.desc == "newdescription");
+                assert!(new_extras.password_history[0].pass == "pass");
+                assert!(new_extras.modified_at.is_some());
             }
             _ => assert!(false)
         }
@@ -779,19 +2219,284 @@ mod test_shell {
     #[test]
     fn edit_leave_unchanged() {
         let entry = Entry::new("name".to_string(), "url".to_string(), "user".to_string(), "pass".to_string(), "desc".to_string(), EntryMeta::default());
-        let edited_entry = super::edit(entry, &|| "a".to_string());
+        let edited_entry = super::edit(entry, EntryExtras::default(), &|| "a".to_string());
         match edited_entry {
-            EditedEntry::Replace(new_entry) => {
+            EditedEntry::Replace(new_entry, new_extras) => {
                 assert!(new_entry.name == "name");
                 assert!(new_entry.url == "url");
                 assert!(new_entry.user == "user");
                 assert!(new_entry.pass == "pass");
                 assert!(new_entry.desc == "desc");
+                assert!(new_extras == EntryExtras::default());
             }
             _ => assert!(false)
         }
     }
 
+    #[test]
+    fn edit_nextcloud_configuration() {
+        let (url, server) = test_webdav::serve_once("/remote.php/dav/files/alice/", "alice", "secret");
+        // Inputs are popped from the end
+        let inputs = Mutex::new(vec!["a".to_string(), "t".to_string(), "n".to_string(), "secret".to_string(), "".to_string(), "alice".to_string(),
+                                     format!("{}/", url), "ftp://cloud".to_string()]);
+        let configuration = super::edit_nextcloud_configuration(&NextcloudConfiguration::default(), &|| inputs.lock().unwrap().pop().unwrap());
+        let configuration = configuration.unwrap();
+        assert!(configuration.server_url == url);
+        assert!(configuration.username == "alice");
+        assert!(configuration.decrypted_password().unwrap().as_str() == "secret");
+        assert!(!configuration.use_self_signed_certificate);
+        assert!(server.join().unwrap().starts_with("PROPFIND /remote.php/dav/files/alice/ "));
+
+        // An empty Server URL goes back when nothing is configured yet
+        assert!(super::edit_nextcloud_configuration(&NextcloudConfiguration::default(), &|| "".to_string()).is_none());
+    }
+
+    #[test]
+    fn acquire_dropbox_token_headless() {
+        let (token_url, server) = test_dropbox::serve_once("200 OK", r#"{"refresh_token":"long"}"#);
+        let inputs = Mutex::new(vec!["http://localhost:8899/?code=thecode", "http://localhost:8899/"]);
+        let get_input = || inputs.lock().unwrap().pop().unwrap().to_string();
+        let selection = super::acquire_dropbox_token_headless(test_dropbox::AUTHORIZATION_URL, &token_url, &get_input);
+        assert!(selection == Some(UserSelection::GoTo(Menu::SetDbxToken(Zeroizing::new("long".to_string())))));
+        assert!(server.join().unwrap().starts_with("code=thecode&"));
+
+        assert!(super::acquire_dropbox_token_headless(test_dropbox::AUTHORIZATION_URL, &token_url, &|| "".to_string()).is_none());
+    }
+
+    #[test]
+    fn edit_sync_targets() {
+        let folder = std::env::temp_dir();
+        let mut targets = vec![SyncTarget { name: "old".to_string(), location: "/nonexistent".to_string(), ..Default::default() }];
+        let inputs = Mutex::new(vec!["r", "y", "1", "n", "alice", "https://dav.example.com/keylock/", "dav", "w", &folder.to_str().unwrap(), "/nonexistent", "nas", "old", "f"]);
+        let changed = super::edit_sync_targets(&mut targets, &|| inputs.lock().unwrap().pop().unwrap().to_string());
+        assert!(changed);
+        assert!(targets.len() == 2);
+        assert!(targets[0] == SyncTarget { name: "nas".to_string(), kind: TargetKind::Folder, location: folder.to_str().unwrap().to_string(), ..Default::default() });
+        assert!(targets[1] == SyncTarget {
+            name: "dav".to_string(),
+            kind: TargetKind::Webdav,
+            location: "https://dav.example.com/keylock".to_string(),
+            username: "alice".to_string(),
+            self_signed: false,
+        });
+        assert!(!super::edit_sync_targets(&mut targets, &|| "r".to_string()));
+    }
+
+    #[test]
+    fn synchronise_conflict() {
+        let dir = std::env::temp_dir().join(format!("rust-keylock-shell-sync-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(dir.join("nas")).unwrap();
+        let (local_path, merge_path) = (dir.join(".sec"), dir.join("merge.sec"));
+        fs::write(&local_path, b"local").unwrap();
+        fs::write(dir.join("nas").join("rust-keylock.sec"), b"remote").unwrap();
+        let targets = vec![SyncTarget { name: "nas".to_string(), location: dir.join("nas").display().to_string(), ..Default::default() }];
+        let mut state = SyncState::default();
+        let mut passwords = BTreeMap::new();
+
+        // Taking the copy of the target is not offered after unlocking
+        let inputs = Mutex::new(vec!["c", "t"]);
+        let get_input = || inputs.lock().unwrap().pop().unwrap().to_string();
+        assert!(super::synchronise(&targets, &mut passwords, &mut state, &local_path, &merge_path, false, &get_input, &get_input).is_none());
+        assert!(state.targets.is_empty());
+
+        let merge = super::synchronise(&targets, &mut passwords, &mut state, &local_path, &merge_path, true, &|| "m".to_string(), &|| "".to_string());
+        assert!(merge == Some(merge_path.clone()));
+        assert!(fs::read(&merge_path).unwrap() == b"remote");
+
+        let merge = super::synchronise(&targets, &mut passwords, &mut state, &local_path, &merge_path, true, &|| "t".to_string(), &|| "".to_string());
+        assert!(merge.is_none());
+        assert!(fs::read(&local_path).unwrap() == b"remote");
+        let results: Vec<SyncResult> = state.log.iter().map(|entry| entry.result).collect();
+        assert!(results == vec![SyncResult::Skipped, SyncResult::Merging, SyncResult::Downloaded]);
+
+        let status = super::sync_status_text(&targets, &state, &local_path);
+        assert!(status.contains("nas (folder "));
+        assert!(status.contains("Last attempt at"));
+        assert!(!status.contains("changed since then"));
+        fs::write(&local_path, b"changed").unwrap();
+        fs::File::options().write(true).open(&local_path).unwrap().set_modified(std::time::UNIX_EPOCH).unwrap();
+        assert!(super::sync_status_text(&targets, &state, &local_path).contains("changed since then"));
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn resolve_entry_conflict() {
+        crate::extras::test_extras::open_store();
+        let mine = Entry::new("shop".to_string(), "url".to_string(), "alice".to_string(), "mine".to_string(), "my notes".to_string(), EntryMeta::default());
+        let theirs = Entry::new("shop".to_string(), "url".to_string(), "bob".to_string(), "theirs".to_string(), "my notes".to_string(), EntryMeta::default());
+        let conflict = Conflict { mine, theirs };
+        assert!(!super::conflict_text(&conflict, &conflict.differences(), false).contains("theirs"));
+        assert!(super::conflict_text(&conflict, &conflict.differences(), true).contains("theirs"));
+
+        // Inputs are popped from the end
+        let inputs = Mutex::new(vec!["a", "b", "1", "t", "2", "v"]);
+        let (merged, keep_theirs) = super::resolve_entry_conflict(&conflict, &|| inputs.lock().unwrap().pop().unwrap().to_string()).unwrap();
+        assert!(keep_theirs);
+        assert!(merged.user == "alice");
+        assert!(merged.pass == "theirs");
+        assert!(extras::of(&merged).password_history[0].pass == "mine");
+
+        assert!(super::resolve_entry_conflict(&conflict, &|| "c".to_string()).is_none());
+    }
+
+    #[test]
+    fn malicious_entries_are_escaped() {
+        let entries = crate::sanitize::test_sanitize::malicious_entries();
+        for entry in entries.iter() {
+            assert!(!super::changing_text("field-name", &entry.name).contains(['\x1b', '\u{9b}']));
+            assert!(!super::changing_text("field-url", &entry.url).contains(['\x1b', '\u{9b}']));
+            assert!(!super::changing_text("field-user", &entry.user).contains(['\r', '\u{9b}']));
+            assert!(!super::changing_text("field-desc", &entry.desc).contains(['\x1b', '\n', '\x7f']));
+        }
+        let conflict = Conflict { mine: entries[3].clone(), theirs: entries[2].clone() };
+        let text = super::conflict_text(&conflict, &conflict.differences(), true);
+        assert!(!text.contains(['\x1b', '\x08', '\r', '\u{9b}', '\u{202e}']));
+        assert!(text.contains("admin\\x9b1;31m\\rroot"));
+    }
+
+    #[test]
+    fn audit_log_text() {
+        let path = std::env::temp_dir().join(format!("rust-keylock-shell-test-audit-text-{}", std::process::id()));
+        let _ = fs::remove_file(&path);
+        let log = AuditLog::new(path.clone());
+        assert!(super::audit_log_text(&log).contains(&i18n::tr("audit-empty")));
+        log.append(AuditEvent::EntryViewed, "mail\x1b]0;title\x07", 10).unwrap();
+        log.append(AuditEvent::EntryDeleted, "bank", 11).unwrap();
+        let text = super::audit_log_text(&log);
+        assert!(text.starts_with(&i18n::tr_with("audit-intact", &[("path", &path.display().to_string())])));
+        assert!(text.find("#2").unwrap() < text.find("#1").unwrap());
+        assert!(!text.contains('\x1b'));
+
+        let contents = fs::read_to_string(&path).unwrap();
+        fs::write(&path, contents.replace("bank", "shop")).unwrap();
+        assert!(super::audit_log_text(&log).starts_with("WARNING!"));
+        let _ = fs::remove_file(&path);
+    }
+
+    #[test]
+    fn audit_event_of_a_copy() {
+        // The username and the password are the same, but the field that was asked for is recorded
+        let entry = Entry::new("router".to_string(), "http://192.168.1.1".to_string(), "admin".to_string(), "admin".to_string(), "".to_string(),
+                               EntryMeta::default());
+        let copy = UserSelection::AddToClipboard("admin".to_string());
+        assert!(super::audit_event(&copy, Some(&entry), Some("pass")) == Some((AuditEvent::EntryCopied, "router (pass)".to_string())));
+        assert!(super::audit_event(&copy, Some(&entry), Some("user")) == Some((AuditEvent::EntryCopied, "router (user)".to_string())));
+        assert!(super::audit_event(&copy, Some(&entry), None).is_none());
+        assert!(super::audit_event(&UserSelection::DeleteEntry(0), Some(&entry), None) == Some((AuditEvent::EntryDeleted, "router".to_string())));
+    }
+
+    #[test]
+    fn edit_rotation_days() {
+        let entry = Entry::new("name".to_string(), "url".to_string(), "user".to_string(), "pass".to_string(), "desc".to_string(), EntryMeta::default());
+        let inputs = Mutex::new(vec!["a", "30", "4294967296", "7"]);
+        match super::edit(entry, EntryExtras::default(), &|| inputs.lock().unwrap().pop().unwrap().to_string()) {
+            EditedEntry::Replace(_, new_extras) => assert!(new_extras.rotation_days == Some(30)),
+            _ => panic!("Unexpected edited entry"),
+        }
+    }
+
+    #[test]
+    fn bulk_operation_menu() {
+        let entries = vec![
+            Entry::new("name1".to_string(), "url".to_string(), "user".to_string(), "pass".to_string(), "desc".to_string(), EntryMeta::default()),
+            Entry::new("name2".to_string(), "url".to_string(), "user".to_string(), "pass".to_string(), "desc".to_string(), EntryMeta::default()),
+            Entry::new("name3".to_string(), "url".to_string(), "user".to_string(), "pass".to_string(), "desc".to_string(), EntryMeta::default()),
+        ];
+        let inputs = Mutex::new(vec!["y", "work", "2", "1,3"]);
+        let selected = super::bulk_operation_menu(&entries, &[0, 1, 2], &|| inputs.lock().unwrap().pop().unwrap().to_string());
+        match selected {
+            Some((BulkAction::Tag(tag), targets)) => {
+                assert!(tag == "work");
+                assert!(targets.len() == 2);
+                assert!(targets[0].name() == "name1");
+                assert!(targets[1].name() == "name3");
+            }
+            _ => panic!("Unexpected bulk operation"),
+        }
+
+        let inputs = Mutex::new(vec!["n", "1", "a"]);
+        assert!(super::bulk_operation_menu(&entries, &[0, 2], &|| inputs.lock().unwrap().pop().unwrap().to_string()).is_none());
+
+        // There is no bulk export
+        let inputs = Mutex::new(vec!["r", "4", "a"]);
+        assert!(super::bulk_operation_menu(&entries, &[0, 2], &|| inputs.lock().unwrap().pop().unwrap().to_string()).is_none());
+    }
+
+    #[test]
+    fn change_password_wizard() {
+        let policy = PasswordPolicy::default();
+        let unlock = |password: &str, number: usize| {
+            if password == "old" && number == 1 {
+                Ok(vec![Entry::new("mail".to_string(), "url".to_string(), "user".to_string(), "Dxq7-kelp-Vram-92".to_string(),
+                                   "desc".to_string(), EntryMeta::default())])
+            } else {
+                Err("Wrong password or number".to_string())
+            }
+        };
+
+        // Inputs are popped from the end
+        let inputs = Mutex::new(vec!["y", "7", "7", "correct horse battery staple", "correct horse battery staple", "",
+                                     "correct horse battery stapel", "correct horse battery staple", "short", "Dxq7-kelp-Vram-92", "1", "old", "2",
+                                     "wrong"]);
+        let get_input = || inputs.lock().unwrap().pop().unwrap().to_string();
+        let (password, number) = super::change_password_wizard(&policy, true, &unlock, &get_input, &get_input).unwrap();
+        assert!(password.as_str() == "correct horse battery staple");
+        assert!(number == 7);
+        assert!(inputs.lock().unwrap().is_empty());
+
+        // Cancelled in the summary, or after too many wrong current passwords
+        let inputs = Mutex::new(vec!["", "n", "7", "7", "correct horse battery staple", "correct horse battery staple", "1", "old"]);
+        let get_input = || inputs.lock().unwrap().pop().unwrap().to_string();
+        assert!(super::change_password_wizard(&policy, true, &unlock, &get_input, &get_input).is_none());
+        let inputs = Mutex::new(vec!["", "1", "a", "1", "b", "1", "c"]);
+        let get_input = || inputs.lock().unwrap().pop().unwrap().to_string();
+        assert!(super::change_password_wizard(&policy, true, &unlock, &get_input, &get_input).is_none());
+
+        // A new passwords file needs a password and does not ask for the current one
+        let inputs = Mutex::new(vec!["y", "3", "3", "correct horse battery staple", "correct horse battery staple", ""]);
+        let get_input = || inputs.lock().unwrap().pop().unwrap().to_string();
+        let (_, number) = super::change_password_wizard(&policy, false, &unlock, &get_input, &get_input).unwrap();
+        assert!(number == 3);
+    }
+
+    #[test]
+    fn entries_are_collected_for_a_password_change() {
+        let editor = super::new();
+        let entry = |name: &str| Entry::new(name.to_string(), "url".to_string(), "user".to_string(), format!("{} pass", name), "".to_string(),
+                                            EntryMeta::default());
+        let entries = vec![entry("mail"), entry("bank")];
+        assert!(editor.password_change_selection(&entries).is_none());
+        assert!(editor.password_change_entry(&entries[0], 0, &EntryPresentationType::View).is_none());
+
+        assert!(editor.start_password_change() == UserSelection::GoTo(Menu::EntriesList("".to_string())));
+        assert!(editor.password_change_selection(&entries) == Some(UserSelection::GoTo(Menu::ShowEntry(0))));
+        assert!(editor.password_change_entry(&entries[0], 0, &EntryPresentationType::Edit).is_none());
+        assert!(editor.password_change_entry(&entries[0], 0, &EntryPresentationType::View) == Some(UserSelection::GoTo(Menu::ShowEntry(1))));
+        assert!(editor.password_change_entry(&entries[1], 1, &EntryPresentationType::View) == Some(UserSelection::GoTo(Menu::ChangePass)));
+        let collected = editor.password_change.lock().unwrap().take().unwrap().1;
+        assert!(collected.iter().map(|entry| entry.pass.as_str()).collect::<Vec<_>>() == vec!["mail pass", "bank pass"]);
+
+        assert!(editor.start_password_change() == UserSelection::GoTo(Menu::EntriesList("".to_string())));
+        assert!(editor.password_change_selection(&[]) == Some(UserSelection::GoTo(Menu::ChangePass)));
+    }
+
+    #[test]
+    fn change_password_summary() {
+        let policy = PasswordPolicy::default();
+        let summary = super::change_password_summary("correct horse battery staple", &policy, Some(5));
+        assert!(summary.contains("28 characters, strength 4 of 4"));
+        assert!(summary.contains("the 5 saved Entries"));
+        assert!(!summary.contains("correct"));
+        assert!(!super::change_password_summary("correct horse battery staple", &policy, None).contains("Entries"));
+    }
+
+    #[test]
+    fn failed_unlocks_text() {
+        assert!(super::failed_unlocks_text(1) == "1 failed attempt to unlock since the last login.");
+        assert!(super::failed_unlocks_text(3) == "3 failed attempts to unlock since the last login.");
+    }
+
     #[test]
     fn prompt_expect_any() {
         let inner_input = super::prompt_expect_any("", &input_y);
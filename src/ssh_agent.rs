@@ -0,0 +1,296 @@
+// Copyright 2017 astonbitecode
+// This file is part of rust-keylock password manager.
+//
+// rust-keylock is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// rust-keylock is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with rust-keylock.  If not, see <http://www.gnu.org/licenses/>.
+//! The ssh-agent mode: `rust-keylock ssh-agent`.
+//!
+//! The SSH private keys that are stored in the description of Entries are served over a Unix domain socket that speaks
+//! the ssh-agent protocol. Only the Entries that are listed in `ssh_agent_entries`, or that the User selects when the
+//! agent starts, are exposed. Every signature needs a confirmation in the terminal where the agent runs.
+use std::io::{self, BufRead, Read, Write};
+use std::os::unix::net::UnixStream;
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+
+use log::{error, info, warn};
+use rust_keylock::Entry;
+use zeroize::Zeroizing;
+
+use crate::agent;
+use crate::bulk;
+use crate::loader;
+use crate::sanitize;
+use crate::settings::Settings;
+use crate::ssh_keys::{self, put_string, put_u32, SshKey, SshReader};
+
+const SOCKET_FILENAME: &str = "rust-keylock-ssh-agent.sock";
+/// The maximum size of a message, as in OpenSSH
+const MAX_MESSAGE_SIZE: usize = 256 * 1024;
+/// How long a connection may stay idle
+const CONNECTION_TIMEOUT: Duration = Duration::from_secs(60);
+
+const SSH_AGENT_FAILURE: u8 = 5;
+const SSH_AGENTC_REQUEST_IDENTITIES: u8 = 11;
+const SSH_AGENT_IDENTITIES_ANSWER: u8 = 12;
+const SSH_AGENTC_SIGN_REQUEST: u8 = 13;
+const SSH_AGENT_SIGN_RESPONSE: u8 = 14;
+
+/// Serves the keys. `confirm` is asked before every signature.
+pub(crate) struct SshAgent<F> where F: Fn(&SshKey) -> bool {
+    keys: Vec<SshKey>,
+    confirm: F,
+    // The connections are served in parallel, but the signatures are confirmed one at a time
+    confirming: Mutex<()>,
+}
+
+impl<F> SshAgent<F> where F: Fn(&SshKey) -> bool {
+    pub(crate) fn new(keys: Vec<SshKey>, confirm: F) -> SshAgent<F> {
+        SshAgent { keys, confirm, confirming: Mutex::new(()) }
+    }
+
+    /// Handles a message without its length and returns the response, without its length
+    fn handle(&self, message: &[u8]) -> Vec<u8> {
+        let mut reader = SshReader::new(message);
+        let res = match reader.u8() {
+            Ok(SSH_AGENTC_REQUEST_IDENTITIES) => self.identities(),
+            Ok(SSH_AGENTC_SIGN_REQUEST) => self.sign(&mut reader),
+            // Adding and removing keys, locking etc. are managed in the passwords file
+            Ok(other) => Err(format!("Unsupported ssh-agent message {}", other)),
+            Err(error) => Err(error),
+        };
+        res.unwrap_or_else(|error| {
+            warn!("{}", error);
+            vec![SSH_AGENT_FAILURE]
+        })
+    }
+
+    fn identities(&self) -> Result<Vec<u8>, String> {
+        let mut response = vec![SSH_AGENT_IDENTITIES_ANSWER];
+        put_u32(&mut response, self.keys.len() as u32);
+        for key in &self.keys {
+            put_string(&mut response, &key.public_blob()?);
+            put_string(&mut response, key.comment.as_bytes());
+        }
+        Ok(response)
+    }
+
+    fn sign(&self, reader: &mut SshReader) -> Result<Vec<u8>, String> {
+        let blob = reader.string()?;
+        let data = reader.string()?;
+        let flags = reader.u32().unwrap_or(0);
+        let key = self.keys.iter()
+            .find(|key| key.public_blob().map(|public| public == blob).unwrap_or(false))
+            .ok_or_else(|| "A signature was requested for an unknown key".to_string())?;
+        let confirmed = match self.confirming.lock() {
+            Ok(_guard) => (self.confirm)(key),
+            Err(_) => false,
+        };
+        if !confirmed {
+            info!("The User denied a signature with the key of '{}'", sanitize::line(&key.entry_name));
+            return Err("The signature was denied".to_string());
+        }
+        let mut response = vec![SSH_AGENT_SIGN_RESPONSE];
+        put_string(&mut response, &key.sign(data, flags)?);
+        Ok(response)
+    }
+
+    /// Serves the messages of a connection until the client closes it
+    pub(crate) fn serve_connection(&self, mut stream: &UnixStream) -> Result<(), String> {
+        stream.set_read_timeout(Some(CONNECTION_TIMEOUT)).map_err(|error| error.to_string())?;
+        stream.set_write_timeout(Some(CONNECTION_TIMEOUT)).map_err(|error| error.to_string())?;
+        loop {
+            let mut length = [0u8; 4];
+            match stream.read_exact(&mut length) {
+                Ok(_) => { /* ignore */ }
+                Err(error) if error.kind() == io::ErrorKind::UnexpectedEof => return Ok(()),
+                Err(error) => return Err(error.to_string()),
+            }
+            let length = u32::from_be_bytes(length) as usize;
+            if length > MAX_MESSAGE_SIZE {
+                return Err(format!("Refusing a message of {} bytes", length));
+            }
+            let mut message = Zeroizing::new(vec![0u8; length]);
+            stream.read_exact(&mut message).map_err(|error| error.to_string())?;
+            let response = self.handle(&message);
+            let mut framed = Vec::with_capacity(response.len() + 4);
+            put_string(&mut framed, &response);
+            stream.write_all(&framed).map_err(|error| error.to_string())?;
+        }
+    }
+}
+
+/// Starts the ssh-agent. It runs in the foreground, because it asks for confirmation before every signature.
+pub(crate) fn run(settings: &Settings) -> Result<(), String> {
+    let mut path = agent::runtime_dir();
+    path.push(SOCKET_FILENAME);
+
+    let (password, number) = loader::ask_key()?;
+    let entries = loader::load_entries(&password, number)?;
+    drop(password);
+    let keys = select_keys(&entries, &settings.ssh_agent_entries, &get_string_from_stdin)?;
+    drop(entries);
+    if keys.is_empty() {
+        return Err("No SSH keys are exposed".to_string());
+    }
+    println!("Exposing the SSH keys of:");
+    for key in &keys {
+        println!("\t{} ({}, {})", sanitize::line(&key.entry_name), sanitize::line(&key.comment), key.fingerprint());
+    }
+
+    let listener = agent::bind(&path)?;
+    println!("\nIn order to use the keys, issue in another terminal:\n\n\tSSH_AUTH_SOCK={}; export SSH_AUTH_SOCK;\n", path.display());
+    let ssh_agent = Arc::new(SshAgent::new(keys, confirm_in_terminal));
+    let own_uid = unsafe { libc::getuid() };
+    for stream in listener.incoming() {
+        match stream {
+            Ok(stream) => match agent::peer_uid(&stream) {
+                Ok(uid) if uid == own_uid => {
+                    // A client that stalls must not block the others
+                    let ssh_agent = ssh_agent.clone();
+                    thread::spawn(move || {
+                        if let Err(error) = ssh_agent.serve_connection(&stream) {
+                            warn!("ssh-agent connection failed: {}", error);
+                        }
+                    });
+                }
+                Ok(uid) => warn!("Refusing a connection from user ID {}", uid),
+                Err(error) => warn!("Refusing a connection, as its user ID is unknown: {}", error),
+            },
+            Err(error) => error!("Could not accept a connection: {}", error),
+        }
+    }
+    Ok(())
+}
+
+/// Returns the keys of the Entries that are configured to be exposed. If none are configured, the User selects them.
+fn select_keys<T>(entries: &[Entry], configured: &[String], get_input: &T) -> Result<Vec<SshKey>, String>
+    where T: Fn() -> String
+{
+    let candidates: Vec<&Entry> = entries.iter()
+        .filter(|entry| ssh_keys::contains_private_key(&entry.desc))
+        .collect();
+    let selected: Vec<&Entry> = if configured.is_empty() {
+        if candidates.is_empty() {
+            return Err("No Entries with SSH private keys in their description were found".to_string());
+        }
+        println!("Entries with SSH private keys:");
+        for (index, entry) in candidates.iter().enumerate() {
+            println!("\t{}: {}", index + 1, sanitize::line(&entry.name));
+        }
+        print!("\nWhich keys should be exposed? (e.g. 1-3,5 or 'a' for all): ");
+        let _ = io::stdout().flush();
+        bulk::parse_selection(&get_input(), candidates.len())?.into_iter().map(|index| candidates[index]).collect()
+    } else {
+        for name in configured {
+            if !candidates.iter().any(|entry| &entry.name == name) {
+                warn!("The Entry '{}' of ssh_agent_entries does not contain an SSH private key", sanitize::line(name));
+                println!("Warning: The Entry '{}' does not contain an SSH private key", sanitize::line(name));
+            }
+        }
+        candidates.into_iter().filter(|entry| configured.contains(&entry.name)).collect()
+    };
+
+    let mut keys = Vec::new();
+    for entry in selected {
+        match SshKey::parse(&entry.name, &entry.desc) {
+            Ok(key) => keys.push(key),
+            Err(error) => println!("Warning: The key of '{}' cannot be used: {}", sanitize::line(&entry.name), sanitize::line(&error)),
+        }
+    }
+    Ok(keys)
+}
+
+fn confirm_in_terminal(key: &SshKey) -> bool {
+    print!("\nAllow a signature with the key of '{}' ({})? [y/n]: ", sanitize::line(&key.entry_name), key.fingerprint());
+    let _ = io::stdout().flush();
+    get_string_from_stdin().eq_ignore_ascii_case("y")
+}
+
+fn get_string_from_stdin() -> String {
+    let mut line = String::new();
+    let _ = io::stdin().lock().read_line(&mut line);
+    line.trim().to_string()
+}
+
+#[cfg(test)]
+mod test_ssh_agent {
+    use std::os::unix::net::UnixStream;
+    use std::io::{Read, Write};
+    use std::thread;
+
+    use openssl::pkey::PKey;
+    use rust_keylock::{Entry, EntryMeta};
+
+    use super::SshAgent;
+    use crate::ssh_keys::{put_string, put_u32, SshKey, SshReader};
+
+    fn key_entry(name: &str) -> Entry {
+        let pem = String::from_utf8(PKey::generate_ed25519().unwrap().private_key_to_pem_pkcs8().unwrap()).unwrap();
+        Entry::new(name.to_string(), "".to_string(), "".to_string(), "".to_string(), format!("A key\n{}", pem), EntryMeta::default())
+    }
+
+    fn exchange(stream: &mut UnixStream, message: &[u8]) -> Vec<u8> {
+        let mut framed = Vec::new();
+        put_string(&mut framed, message);
+        stream.write_all(&framed).unwrap();
+        let mut length = [0u8; 4];
+        stream.read_exact(&mut length).unwrap();
+        let mut response = vec![0u8; u32::from_be_bytes(length) as usize];
+        stream.read_exact(&mut response).unwrap();
+        response
+    }
+
+    #[test]
+    fn select_keys() {
+        let entries = vec![key_entry("a"), Entry::empty(), key_entry("b"), key_entry("c")];
+        let keys = super::select_keys(&entries, &[], &|| "2-3".to_string()).unwrap();
+        assert!(keys.iter().map(|key| key.entry_name.as_str()).collect::<Vec<_>>() == vec!["b", "c"]);
+        let keys = super::select_keys(&entries, &["a".to_string(), "missing".to_string()], &|| panic!("No input expected")).unwrap();
+        assert!(keys.len() == 1 && keys[0].entry_name == "a");
+        assert!(super::select_keys(&[Entry::empty()], &[], &|| "a".to_string()).is_err());
+    }
+
+    #[test]
+    fn protocol() {
+        let entries = [key_entry("allowed"), key_entry("denied")];
+        let keys: Vec<SshKey> = entries.iter().map(|entry| SshKey::parse(&entry.name, &entry.desc).unwrap()).collect();
+        let blobs: Vec<Vec<u8>> = keys.iter().map(|key| key.public_blob().unwrap()).collect();
+        let (mut client, server) = UnixStream::pair().unwrap();
+        let handle = thread::spawn(move || {
+            let agent = SshAgent::new(keys, |key: &SshKey| key.entry_name == "allowed");
+            agent.serve_connection(&server).unwrap();
+        });
+
+        let response = exchange(&mut client, &[super::SSH_AGENTC_REQUEST_IDENTITIES]);
+        let mut reader = SshReader::new(&response);
+        assert!(reader.u8().unwrap() == super::SSH_AGENT_IDENTITIES_ANSWER);
+        assert!(reader.u32().unwrap() == 2);
+        assert!(reader.string().unwrap() == blobs[0].as_slice());
+        assert!(reader.string().unwrap() == b"allowed");
+
+        for (blob, expected) in [(&blobs[0], super::SSH_AGENT_SIGN_RESPONSE), (&blobs[1], super::SSH_AGENT_FAILURE)] {
+            let mut request = vec![super::SSH_AGENTC_SIGN_REQUEST];
+            put_string(&mut request, blob);
+            put_string(&mut request, b"data to sign");
+            put_u32(&mut request, 0);
+            assert!(exchange(&mut client, &request)[0] == expected);
+        }
+        // Adding keys is not supported
+        assert!(exchange(&mut client, &[17]) == vec![super::SSH_AGENT_FAILURE]);
+
+        drop(client);
+        handle.join().unwrap();
+    }
+}
@@ -0,0 +1,877 @@
+// Copyright 2017 astonbitecode
+// This file is part of rust-keylock password manager.
+//
+// rust-keylock is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// rust-keylock is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with rust-keylock.  If not, see <http://www.gnu.org/licenses/>.
+//! Synchronisation of the passwords file with a folder or a WebDAV server.
+//!
+//! The file is copied encrypted, as it is. The modification times of both copies are compared with the ones of the
+//! last synchronisation: the side that changed is copied over the other and if both changed, there is a conflict that
+//! the User resolves.
+use std::collections::BTreeMap;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::UNIX_EPOCH;
+
+use log::{debug, error};
+use openssl::base64;
+use serde::{Deserialize, Serialize};
+use zeroize::Zeroizing;
+
+use crate::extras;
+use crate::http::{self, HttpError};
+use crate::i18n;
+
+/// The passwords file of the library, in its default location
+const VAULT_FILENAME: &str = ".sec";
+/// The name of the copy in the targets
+const TARGET_FILENAME: &str = "rust-keylock.sec";
+/// Where the times of the last synchronisations are kept
+const STATE_FILENAME: &str = ".shell_sync";
+/// The copy of a target that is merged after unlocking
+const MERGE_FILENAME: &str = ".sync_merge.sec";
+/// How many synchronisations the log keeps
+const SYNC_LOG_CAPACITY: usize = 200;
+
+#[derive(Debug, Default, PartialEq, Eq, Clone, Copy, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub(crate) enum TargetKind {
+    /// A local or mounted folder, like a Syncthing or an NFS directory
+    #[default]
+    Folder,
+    /// A WebDAV collection
+    Webdav,
+}
+
+/// Where the passwords file is synchronised to
+#[derive(Debug, Default, PartialEq, Eq, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub(crate) struct SyncTarget {
+    pub(crate) name: String,
+    pub(crate) kind: TargetKind,
+    /// The path of the folder, or the URL of the WebDAV collection
+    pub(crate) location: String,
+    /// The WebDAV username. The password is asked once per session and never stored.
+    pub(crate) username: String,
+    /// Whether a self-signed certificate of the WebDAV server is accepted
+    pub(crate) self_signed: bool,
+}
+
+impl SyncTarget {
+    pub(crate) fn needs_password(&self) -> bool {
+        self.kind == TargetKind::Webdav
+    }
+}
+
+/// The modification time and the size of a copy of the passwords file
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub(crate) struct FileVersion {
+    /// Seconds since the epoch
+    pub(crate) modified: i64,
+    /// `None` if the target does not tell it
+    pub(crate) size: Option<u64>,
+}
+
+/// A copy of the passwords file in a target
+pub(crate) trait Storage {
+    /// The version of the copy, or `None` if there is no copy yet
+    fn version(&self) -> Result<Option<FileVersion>, String>;
+    fn read(&self) -> Result<Vec<u8>, String>;
+    fn write(&self, contents: &[u8]) -> Result<(), String>;
+}
+
+struct FolderStorage {
+    file: PathBuf,
+}
+
+impl Storage for FolderStorage {
+    fn version(&self) -> Result<Option<FileVersion>, String> {
+        file_version(&self.file)
+    }
+
+    fn read(&self) -> Result<Vec<u8>, String> {
+        fs::read(&self.file).map_err(|error| format!("{}: {}", self.file.display(), error))
+    }
+
+    fn write(&self, contents: &[u8]) -> Result<(), String> {
+        write_atomically(&self.file, contents)
+    }
+}
+
+struct WebdavStorage {
+    url: String,
+    authorization: Zeroizing<String>,
+    self_signed: bool,
+}
+
+impl WebdavStorage {
+    fn request(&self, method: &str, headers: &[(&str, &str)], body: &[u8]) -> Result<http::Response, String> {
+        let mut all_headers = vec![("Authorization", self.authorization.as_str())];
+        all_headers.extend_from_slice(headers);
+        http::request(method, &self.url, &all_headers, body, self.self_signed).map_err(|error| match error {
+            HttpError::Url(error) | HttpError::Connection(error) | HttpError::Tls(error) | HttpError::InvalidResponse(error) => error,
+            HttpError::Dns(host) => i18n::tr_with("webdav-dns", &[("host", &host)]),
+        })
+    }
+}
+
+impl Storage for WebdavStorage {
+    fn version(&self) -> Result<Option<FileVersion>, String> {
+        let response = self.request("PROPFIND", &[("Depth", "0")], &[])?;
+        match response.status {
+            404 => Ok(None),
+            200..=299 => parse_propfind(&String::from_utf8_lossy(&response.body)).map(Some),
+            other => Err(i18n::tr_with("webdav-status", &[("status", &other.to_string())])),
+        }
+    }
+
+    fn read(&self) -> Result<Vec<u8>, String> {
+        let response = self.request("GET", &[], &[])?;
+        match response.status {
+            200 => Ok(response.body),
+            other => Err(i18n::tr_with("webdav-status", &[("status", &other.to_string())])),
+        }
+    }
+
+    fn write(&self, contents: &[u8]) -> Result<(), String> {
+        let response = self.request("PUT", &[("Content-Type", "application/octet-stream")], contents)?;
+        match response.status {
+            200..=299 => Ok(()),
+            other => Err(i18n::tr_with("webdav-status", &[("status", &other.to_string())])),
+        }
+    }
+}
+
+/// Reads the modification time and the size from a `PROPFIND` response
+fn parse_propfind(body: &str) -> Result<FileVersion, String> {
+    let property = |name: &str| dav_property(body, name);
+    let modified = property("getlastmodified")
+        .and_then(|value| chrono::DateTime::parse_from_rfc2822(&value).ok())
+        .map(|date_time| date_time.timestamp())
+        .ok_or_else(|| i18n::tr("sync-no-modification-time"))?;
+    let size = property("getcontentlength").and_then(|value| value.parse::<u64>().ok());
+    Ok(FileVersion { modified, size })
+}
+
+/// Returns the text of the first element with the name, whatever the prefix of its namespace is (`D:`, `lp1:` or none)
+fn dav_property(body: &str, name: &str) -> Option<String> {
+    let mut rest = body;
+    while let Some(start) = rest.find('<') {
+        rest = &rest[start + 1..];
+        let tag_end = rest.find('>')?;
+        let tag = &rest[..tag_end];
+        rest = &rest[tag_end + 1..];
+        // Closing tags, processing instructions, comments and empty elements have no text
+        if tag.starts_with(['/', '?', '!']) || tag.ends_with('/') {
+            continue;
+        }
+        let qualified_name = tag.split(|c: char| c.is_whitespace()).next().unwrap_or("");
+        if qualified_name.rsplit(':').next() == Some(name) {
+            let text_end = rest.find('<').unwrap_or(rest.len());
+            return Some(rest[..text_end].trim().to_string());
+        }
+    }
+    None
+}
+
+/// Returns the storage of the target. WebDAV targets need the password.
+pub(crate) fn storage(target: &SyncTarget, password: Option<&str>) -> Box<dyn Storage> {
+    match target.kind {
+        TargetKind::Folder => Box::new(FolderStorage { file: Path::new(&target.location).join(TARGET_FILENAME) }),
+        TargetKind::Webdav => {
+            let credentials = Zeroizing::new(format!("{}:{}", target.username, password.unwrap_or_default()));
+            Box::new(WebdavStorage {
+                url: format!("{}/{}", target.location.trim_end_matches('/'), TARGET_FILENAME),
+                authorization: Zeroizing::new(format!("Basic {}", base64::encode_block(credentials.as_bytes()))),
+                self_signed: target.self_signed,
+            })
+        }
+    }
+}
+
+fn file_version(path: &Path) -> Result<Option<FileVersion>, String> {
+    match fs::metadata(path) {
+        Ok(metadata) => {
+            let modified = metadata.modified()
+                .ok()
+                .and_then(|modified| modified.duration_since(UNIX_EPOCH).ok())
+                .map(|duration| duration.as_secs() as i64)
+                .unwrap_or(0);
+            Ok(Some(FileVersion { modified, size: Some(metadata.len()) }))
+        }
+        Err(error) if error.kind() == io::ErrorKind::NotFound => Ok(None),
+        Err(error) => Err(format!("{}: {}", path.display(), error)),
+    }
+}
+
+pub(crate) fn write_atomically(path: &Path, contents: &[u8]) -> Result<(), String> {
+    let mut temporary = path.as_os_str().to_owned();
+    temporary.push(".part");
+    fs::write(&temporary, contents)
+        .and_then(|_| fs::rename(&temporary, path))
+        .map_err(|error| format!("{}: {}", path.display(), error))
+}
+
+fn hash(contents: &[u8]) -> String {
+    openssl::sha::sha256(contents).iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+/// The state of both copies after the last synchronisation with a target
+#[derive(Debug, Default, PartialEq, Eq, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub(crate) struct SyncRecord {
+    /// Seconds since the epoch
+    pub(crate) synced_at: i64,
+    pub(crate) local_modified: i64,
+    pub(crate) local_hash: String,
+    pub(crate) target_modified: i64,
+    /// Whether the copy of the target is being merged. The local copy contains it only after it is saved again.
+    pub(crate) merging: bool,
+}
+
+/// The result of a synchronisation, as it is logged
+#[derive(Debug, Default, PartialEq, Eq, Clone, Copy, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub(crate) enum SyncResult {
+    #[default]
+    UpToDate,
+    Uploaded,
+    Downloaded,
+    TargetNewer,
+    Merging,
+    Skipped,
+    Failed,
+}
+
+impl SyncResult {
+    pub(crate) fn text(&self) -> String {
+        i18n::tr(match self {
+            SyncResult::UpToDate => "sync-result-up-to-date",
+            SyncResult::Uploaded => "sync-result-uploaded",
+            SyncResult::Downloaded => "sync-result-downloaded",
+            SyncResult::TargetNewer => "sync-result-target-newer",
+            SyncResult::Merging => "sync-result-merging",
+            SyncResult::Skipped => "sync-result-skipped",
+            SyncResult::Failed => "sync-result-failed",
+        })
+    }
+}
+
+/// A synchronisation with a target
+#[derive(Debug, Default, PartialEq, Eq, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub(crate) struct SyncLogEntry {
+    /// Seconds since the epoch
+    pub(crate) at: i64,
+    pub(crate) target: String,
+    pub(crate) result: SyncResult,
+    /// The error of a failed synchronisation
+    pub(crate) detail: String,
+}
+
+/// The last synchronisations with the targets, by the name of the target, and the log of all the synchronisations
+#[derive(Debug, Default, PartialEq, Eq, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub(crate) struct SyncState {
+    pub(crate) targets: BTreeMap<String, SyncRecord>,
+    /// The oldest first
+    pub(crate) log: Vec<SyncLogEntry>,
+}
+
+impl SyncState {
+    pub(crate) fn log(&mut self, entry: SyncLogEntry) {
+        if self.log.len() >= SYNC_LOG_CAPACITY {
+            self.log.drain(..=self.log.len() - SYNC_LOG_CAPACITY);
+        }
+        self.log.push(entry);
+    }
+
+    /// The last logged synchronisation with the target
+    pub(crate) fn last_result(&self, target: &str) -> Option<&SyncLogEntry> {
+        self.log.iter().rev().find(|entry| entry.target == target)
+    }
+
+    /// Whether the passwords file changed since it was synchronised with the target
+    pub(crate) fn has_local_changes(&self, target: &str, local_path: &Path) -> bool {
+        let local = local_file(local_path).ok().flatten();
+        match (local, self.targets.get(target)) {
+            (Some(local), Some(record)) => {
+                record.merging || local.hash != record.local_hash
+            }
+            (Some(_), None) => true,
+            (None, _) => false,
+        }
+    }
+
+    pub(crate) fn load() -> SyncState {
+        fs::read_to_string(state_path())
+            .ok()
+            .and_then(|contents| toml::from_str(&contents).map_err(|error| error!("Could not parse the synchronisation state: {}", error)).ok())
+            .unwrap_or_default()
+    }
+
+    pub(crate) fn save(&self) {
+        let res = toml::to_string(self).map_err(|error| error.to_string())
+            .and_then(|contents| fs::write(state_path(), contents).map_err(|error| error.to_string()));
+        if let Err(error) = res {
+            error!("Could not save the synchronisation state: {}", error);
+        }
+    }
+}
+
+fn state_path() -> PathBuf {
+    rust_keylock::default_rustkeylock_location().join(STATE_FILENAME)
+}
+
+/// The passwords file of the library
+pub(crate) fn vault_path() -> PathBuf {
+    rust_keylock::default_rustkeylock_location().join(VAULT_FILENAME)
+}
+
+/// Where the copy of a target is kept, until it is merged
+pub(crate) fn merge_path() -> PathBuf {
+    rust_keylock::default_rustkeylock_location().join(MERGE_FILENAME)
+}
+
+/// The local copy of the passwords file
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub(crate) struct LocalFile {
+    pub(crate) version: FileVersion,
+    pub(crate) hash: String,
+}
+
+fn local_file(path: &Path) -> Result<Option<LocalFile>, String> {
+    match file_version(path)? {
+        Some(version) => {
+            let contents = fs::read(path).map_err(|error| format!("{}: {}", path.display(), error))?;
+            Ok(Some(LocalFile { version, hash: hash(&contents) }))
+        }
+        None => Ok(None),
+    }
+}
+
+/// What a synchronisation has to do
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub(crate) enum Plan {
+    /// There is no copy anywhere
+    Nothing,
+    UpToDate,
+    Upload,
+    Download,
+    /// Both copies changed since the last synchronisation, or they were never synchronised
+    Conflict,
+}
+
+/// Decides what to do from the versions of the copies and the last synchronisation
+pub(crate) fn plan(local: Option<&LocalFile>, target: Option<&FileVersion>, record: Option<&SyncRecord>) -> Plan {
+    match (local, target, record) {
+        (None, None, _) => Plan::Nothing,
+        (Some(_), None, _) => Plan::Upload,
+        (None, Some(_), _) => Plan::Download,
+        (Some(_), Some(_), None) => Plan::Conflict,
+        (Some(local), Some(target), Some(record)) => {
+            // The contents decide: saving without changes rewrites the file, and a change may keep the second of the
+            // modification time
+            let local_changed = local.hash != record.local_hash;
+            let target_changed = target.modified != record.target_modified;
+            match (local_changed, target_changed) {
+                // The merged Entries were not saved
+                (false, false) if record.merging => Plan::Conflict,
+                (false, false) => Plan::UpToDate,
+                (true, false) => Plan::Upload,
+                (false, true) => Plan::Download,
+                (true, true) => Plan::Conflict,
+            }
+        }
+    }
+}
+
+/// What the User sees to resolve a conflict: both versions and the last synchronisation
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub(crate) struct Preview {
+    pub(crate) target: String,
+    pub(crate) local: FileVersion,
+    pub(crate) remote: FileVersion,
+    pub(crate) last_synced: Option<i64>,
+}
+
+impl Preview {
+    /// Presents the versions side by side
+    pub(crate) fn text(&self) -> String {
+        let last_synced = match self.last_synced {
+            Some(synced_at) => i18n::tr_with("sync-conflict-since", &[("target", &self.target), ("time", &extras::format_timestamp(synced_at))]),
+            None => i18n::tr_with("sync-conflict-never", &[("target", &self.target)]),
+        };
+        let this_device = i18n::tr("sync-this-device");
+        let width = this_device.chars().count().max(self.target.chars().count()) + 2;
+        let line = |name: &str, version: &FileVersion| {
+            format!("  {:width$}{:18}{}", name, extras::format_timestamp(version.modified),
+                    version.size.map(|size| i18n::tr_with("sync-bytes", &[("size", &size.to_string())])).unwrap_or_default(), width = width)
+        };
+        let newer = if self.local.modified >= self.remote.modified { &this_device } else { &self.target };
+        format!("{}\n\n{}\n{}\n\n{}", last_synced, line(&this_device, &self.local), line(&self.target, &self.remote),
+                i18n::tr_with("sync-newer", &[("name", newer)]))
+    }
+}
+
+/// How the User resolves a conflict
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub(crate) enum Resolution {
+    /// The local copy replaces the one of the target
+    KeepLocal,
+    /// The copy of the target replaces the local one, which is kept as a backup
+    TakeTarget,
+    /// The copy of the target is kept for the library to import its Entries into the local ones
+    Merge,
+    /// Nothing is done
+    Skip,
+}
+
+/// What a synchronisation did
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub(crate) enum Outcome {
+    Nothing,
+    UpToDate,
+    Uploaded,
+    Downloaded,
+    /// The target has a newer copy, which can only be taken before the passwords file is loaded
+    TargetNewer,
+    Conflict(Preview),
+    /// The copy of the target was kept for merging at this path
+    MergePending(PathBuf),
+    Skipped,
+}
+
+impl Outcome {
+    /// How the outcome is logged. Conflicts are logged after they are resolved.
+    pub(crate) fn result(&self) -> Option<SyncResult> {
+        match self {
+            Outcome::Nothing | Outcome::Conflict(_) => None,
+            Outcome::UpToDate => Some(SyncResult::UpToDate),
+            Outcome::Uploaded => Some(SyncResult::Uploaded),
+            Outcome::Downloaded => Some(SyncResult::Downloaded),
+            Outcome::TargetNewer => Some(SyncResult::TargetNewer),
+            Outcome::MergePending(_) => Some(SyncResult::Merging),
+            Outcome::Skipped => Some(SyncResult::Skipped),
+        }
+    }
+}
+
+/// Synchronises the passwords file with a target
+pub(crate) struct Synchronisation<'a> {
+    pub(crate) name: &'a str,
+    pub(crate) storage: &'a dyn Storage,
+    pub(crate) local_path: &'a Path,
+    pub(crate) state: &'a mut SyncState,
+    /// Taking the copy of the target is possible only before the library loads the passwords file
+    pub(crate) can_download: bool,
+    pub(crate) now: i64,
+}
+
+impl Synchronisation<'_> {
+    /// Does what the plan says, or returns the conflict for the User to resolve
+    pub(crate) fn run(&mut self) -> Result<Outcome, String> {
+        let local = local_file(self.local_path)?;
+        let target = self.storage.version()?;
+        let record = self.state.targets.get(self.name).cloned();
+        let plan = plan(local.as_ref(), target.as_ref(), record.as_ref());
+        debug!("Synchronisation with '{}': {:?}", self.name, plan);
+        match (plan, local, target) {
+            (Plan::Nothing, _, _) => Ok(Outcome::Nothing),
+            (Plan::UpToDate, _, _) => Ok(Outcome::UpToDate),
+            (Plan::Upload, _, _) => self.upload(),
+            (Plan::Download, _, _) if self.can_download => self.download(),
+            (Plan::Download, _, _) => Ok(Outcome::TargetNewer),
+            (Plan::Conflict, Some(local), Some(remote)) => {
+                // Copies that were never synchronised may be the same
+                if record.is_none() && hash(&self.storage.read()?) == local.hash {
+                    self.record(&local.version, &local.hash, &remote);
+                    return Ok(Outcome::UpToDate);
+                }
+                Ok(Outcome::Conflict(Preview {
+                    target: self.name.to_string(),
+                    local: local.version,
+                    remote,
+                    last_synced: record.map(|record| record.synced_at),
+                }))
+            }
+            (Plan::Conflict, _, _) => Ok(Outcome::Nothing),
+        }
+    }
+
+    /// Applies the choice of the User for a conflict
+    pub(crate) fn resolve(&mut self, resolution: Resolution, merge_path: &Path) -> Result<Outcome, String> {
+        match resolution {
+            Resolution::KeepLocal => self.upload(),
+            Resolution::TakeTarget if self.can_download => self.download(),
+            Resolution::TakeTarget => Ok(Outcome::TargetNewer),
+            Resolution::Merge => {
+                let (contents, remote) = self.read_complete()?;
+                write_atomically(merge_path, &contents)?;
+                // The local copy changes when the merged Entries are saved. Then it is uploaded.
+                if let Some(local) = local_file(self.local_path)? {
+                    self.record(&local.version, &local.hash, &remote);
+                    if let Some(record) = self.state.targets.get_mut(self.name) {
+                        record.merging = true;
+                    }
+                }
+                Ok(Outcome::MergePending(merge_path.to_path_buf()))
+            }
+            Resolution::Skip => Ok(Outcome::Skipped),
+        }
+    }
+
+    fn upload(&mut self) -> Result<Outcome, String> {
+        let contents = fs::read(self.local_path).map_err(|error| format!("{}: {}", self.local_path.display(), error))?;
+        self.storage.write(&contents)?;
+        let local = local_file(self.local_path)?.ok_or_else(|| i18n::tr("sync-local-missing"))?;
+        let remote = self.storage.version()?.ok_or_else(|| i18n::tr("sync-upload-missing"))?;
+        self.record(&local.version, &local.hash, &remote);
+        Ok(Outcome::Uploaded)
+    }
+
+    /// Reads the copy of the target and checks that its size is the one the target tells, so that an incomplete
+    /// download never replaces a file
+    fn read_complete(&self) -> Result<(Vec<u8>, FileVersion), String> {
+        let contents = self.storage.read()?;
+        let remote = self.storage.version()?.ok_or_else(|| i18n::tr("sync-upload-missing"))?;
+        // Without a size from the target, the download cannot be checked
+        if let Some(size) = remote.size.filter(|size| *size != contents.len() as u64) {
+            return Err(i18n::tr_with("sync-incomplete-download", &[("received", &contents.len().to_string()), ("size", &size.to_string())]));
+        }
+        Ok((contents, remote))
+    }
+
+    fn download(&mut self) -> Result<Outcome, String> {
+        let (contents, remote) = self.read_complete()?;
+        if self.local_path.exists() {
+            let mut backup = self.local_path.as_os_str().to_owned();
+            backup.push(format!(".{}.bak", self.now));
+            fs::copy(self.local_path, &backup).map_err(|error| format!("{}: {}", self.local_path.display(), error))?;
+        }
+        write_atomically(self.local_path, &contents)?;
+        let local = local_file(self.local_path)?.ok_or_else(|| i18n::tr("sync-local-missing"))?;
+        self.record(&local.version, &local.hash, &remote);
+        Ok(Outcome::Downloaded)
+    }
+
+    fn record(&mut self, local: &FileVersion, local_hash: &str, remote: &FileVersion) {
+        self.state.targets.insert(self.name.to_string(), SyncRecord {
+            synced_at: self.now,
+            local_modified: local.modified,
+            local_hash: local_hash.to_string(),
+            target_modified: remote.modified,
+            merging: false,
+        });
+    }
+}
+
+/// Where the merge of a copy of a target is
+#[derive(Debug, Default)]
+pub(crate) enum Merge {
+    #[default]
+    None,
+    /// The copy is merged with the master password, once it is entered
+    Unlocking(PathBuf),
+    /// The copy is imported with these credentials, when the Main Menu is presented
+    Importing(PathBuf, Zeroizing<String>, usize),
+    /// The copy is imported and is deleted after the library reports the result
+    Imported(PathBuf),
+}
+
+/// The synchronisation state of the current session
+#[derive(Debug, Default)]
+pub(crate) struct SyncSession {
+    /// The WebDAV passwords, by the name of the target. They are asked once per session and never stored.
+    pub(crate) passwords: BTreeMap<String, Zeroizing<String>>,
+    /// Whether the targets were synchronised before unlocking
+    pub(crate) synchronised_before_unlock: bool,
+    /// Whether the passwords file was saved and is not uploaded yet
+    pub(crate) upload_pending: bool,
+    pub(crate) merge: Merge,
+}
+
+#[cfg(test)]
+mod test_sync {
+    use std::fs;
+    use std::path::{Path, PathBuf};
+
+    use super::{FileVersion, LocalFile, Outcome, Plan, Resolution, Storage, SyncLogEntry, SyncRecord, SyncResult, SyncState, SyncTarget, Synchronisation,
+                TargetKind};
+
+    /// A target whose downloads break off
+    struct TruncatingStorage;
+
+    impl Storage for TruncatingStorage {
+        fn version(&self) -> Result<Option<FileVersion>, String> {
+            Ok(Some(FileVersion { modified: 5000, size: Some(10) }))
+        }
+
+        fn read(&self) -> Result<Vec<u8>, String> {
+            Ok(b"versi".to_vec())
+        }
+
+        fn write(&self, _contents: &[u8]) -> Result<(), String> {
+            Err("Not expected".to_string())
+        }
+    }
+
+    /// A target that does not tell the size of its copy
+    struct UnsizedStorage;
+
+    impl Storage for UnsizedStorage {
+        fn version(&self) -> Result<Option<FileVersion>, String> {
+            Ok(Some(FileVersion { modified: 5000, size: None }))
+        }
+
+        fn read(&self) -> Result<Vec<u8>, String> {
+            Ok(b"version 1".to_vec())
+        }
+
+        fn write(&self, _contents: &[u8]) -> Result<(), String> {
+            Err("Not expected".to_string())
+        }
+    }
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("rust-keylock-sync-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(dir.join("target")).unwrap();
+        dir
+    }
+
+    fn set_modified(path: &Path, seconds: u64) {
+        let file = fs::File::options().write(true).open(path).unwrap();
+        file.set_modified(std::time::UNIX_EPOCH + std::time::Duration::from_secs(seconds)).unwrap();
+    }
+
+    fn folder_target(dir: &Path) -> SyncTarget {
+        SyncTarget { name: "nas".to_string(), kind: TargetKind::Folder, location: dir.join("target").display().to_string(), ..Default::default() }
+    }
+
+    #[test]
+    fn plan() {
+        let local = LocalFile { version: FileVersion { modified: 20, size: Some(1) }, hash: "b".to_string() };
+        let target = FileVersion { modified: 10, size: Some(1) };
+        let record = SyncRecord { synced_at: 10, local_modified: 10, local_hash: "a".to_string(), target_modified: 10, merging: false };
+        assert!(super::plan(None, None, None) == Plan::Nothing);
+        assert!(super::plan(Some(&local), None, Some(&record)) == Plan::Upload);
+        assert!(super::plan(None, Some(&target), None) == Plan::Download);
+        assert!(super::plan(Some(&local), Some(&target), None) == Plan::Conflict);
+        assert!(super::plan(Some(&local), Some(&target), Some(&record)) == Plan::Upload);
+        let touched = LocalFile { hash: "a".to_string(), ..local.clone() };
+        assert!(super::plan(Some(&touched), Some(&target), Some(&record)) == Plan::UpToDate);
+        let newer_target = FileVersion { modified: 30, size: Some(1) };
+        assert!(super::plan(Some(&touched), Some(&newer_target), Some(&record)) == Plan::Download);
+        assert!(super::plan(Some(&local), Some(&newer_target), Some(&record)) == Plan::Conflict);
+        let merging = SyncRecord { merging: true, ..record.clone() };
+        assert!(super::plan(Some(&touched), Some(&target), Some(&merging)) == Plan::Conflict);
+        assert!(super::plan(Some(&local), Some(&target), Some(&merging)) == Plan::Upload);
+        // Changed within the same second as the last synchronisation
+        let same_second = LocalFile { version: FileVersion { modified: 10, size: Some(1) }, hash: "b".to_string() };
+        assert!(super::plan(Some(&same_second), Some(&target), Some(&record)) == Plan::Upload);
+        assert!(super::plan(Some(&same_second), Some(&newer_target), Some(&record)) == Plan::Conflict);
+    }
+
+    #[test]
+    fn folder_upload_download_and_conflict() {
+        let dir = temp_dir("folder");
+        let local_path = dir.join(".sec");
+        let target = folder_target(&dir);
+        let storage = super::storage(&target, None);
+        let mut state = SyncState::default();
+        fs::write(&local_path, b"version 1").unwrap();
+        set_modified(&local_path, 1000);
+
+        let mut synchronisation = Synchronisation { name: "nas", storage: storage.as_ref(), local_path: &local_path, state: &mut state, can_download: true, now: 2000 };
+        assert!(synchronisation.run() == Ok(Outcome::Uploaded));
+        assert!(synchronisation.run() == Ok(Outcome::UpToDate));
+        let target_file = dir.join("target").join(super::TARGET_FILENAME);
+        assert!(fs::read(&target_file).unwrap() == b"version 1");
+
+        // Another device changes the copy of the target
+        fs::write(&target_file, b"version 2").unwrap();
+        set_modified(&target_file, 3000);
+        synchronisation.can_download = false;
+        assert!(synchronisation.run() == Ok(Outcome::TargetNewer));
+        synchronisation.can_download = true;
+        assert!(synchronisation.run() == Ok(Outcome::Downloaded));
+        assert!(fs::read(&local_path).unwrap() == b"version 2");
+        assert!(fs::read(dir.join(".sec.2000.bak")).unwrap() == b"version 1");
+
+        // Both change
+        fs::write(&local_path, b"version 3").unwrap();
+        set_modified(&local_path, 4000);
+        fs::write(&target_file, b"version 4").unwrap();
+        set_modified(&target_file, 5000);
+        match synchronisation.run() {
+            Ok(Outcome::Conflict(preview)) => {
+                assert!(preview.local == FileVersion { modified: 4000, size: Some(9) });
+                assert!(preview.remote == FileVersion { modified: 5000, size: Some(9) });
+                assert!(preview.last_synced == Some(2000));
+                assert!(preview.text().contains("nas"));
+            }
+            other => panic!("Unexpected outcome {:?}", other),
+        }
+        assert!(synchronisation.resolve(Resolution::KeepLocal, &dir.join("merge")) == Ok(Outcome::Uploaded));
+        assert!(fs::read(&target_file).unwrap() == b"version 3");
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn incomplete_downloads_are_rejected() {
+        let dir = temp_dir("incomplete");
+        let local_path = dir.join(".sec");
+        let merge_path = dir.join("merge.sec");
+        let mut state = SyncState::default();
+        let mut synchronisation = Synchronisation { name: "dav", storage: &TruncatingStorage, local_path: &local_path, state: &mut state, can_download: true, now: 2000 };
+        assert!(synchronisation.run().is_err());
+        assert!(!local_path.exists());
+
+        fs::write(&local_path, b"local").unwrap();
+        assert!(synchronisation.resolve(Resolution::TakeTarget, &merge_path).is_err());
+        assert!(synchronisation.resolve(Resolution::Merge, &merge_path).is_err());
+        assert!(fs::read(&local_path).unwrap() == b"local");
+        assert!(!merge_path.exists());
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn downloads_without_a_size() {
+        let dir = temp_dir("unsized");
+        let local_path = dir.join(".sec");
+        let mut state = SyncState::default();
+        let mut synchronisation = Synchronisation { name: "dav", storage: &UnsizedStorage, local_path: &local_path, state: &mut state, can_download: true, now: 2000 };
+        assert!(synchronisation.run() == Ok(Outcome::Downloaded));
+        assert!(fs::read(&local_path).unwrap() == b"version 1");
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn merge_uploads_after_saving() {
+        let dir = temp_dir("merge");
+        let local_path = dir.join(".sec");
+        let merge_path = dir.join("merge.sec");
+        let target = folder_target(&dir);
+        let storage = super::storage(&target, None);
+        let mut state = SyncState::default();
+        fs::write(&local_path, b"local").unwrap();
+        set_modified(&local_path, 1000);
+        fs::write(dir.join("target").join(super::TARGET_FILENAME), b"remote").unwrap();
+
+        let mut synchronisation = Synchronisation { name: "nas", storage: storage.as_ref(), local_path: &local_path, state: &mut state, can_download: true, now: 2000 };
+        assert!(matches!(synchronisation.run(), Ok(Outcome::Conflict(_))));
+        assert!(synchronisation.resolve(Resolution::Merge, &merge_path) == Ok(Outcome::MergePending(merge_path.clone())));
+        assert!(fs::read(&merge_path).unwrap() == b"remote");
+        // Until the merged Entries are saved, the conflict remains
+        assert!(matches!(synchronisation.run(), Ok(Outcome::Conflict(_))));
+        // The merged Entries are saved
+        fs::write(&local_path, b"local and remote").unwrap();
+        set_modified(&local_path, 3000);
+        assert!(synchronisation.run() == Ok(Outcome::Uploaded));
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn same_contents_are_not_a_conflict() {
+        let dir = temp_dir("same");
+        let local_path = dir.join(".sec");
+        let target = folder_target(&dir);
+        let storage = super::storage(&target, None);
+        let mut state = SyncState::default();
+        fs::write(&local_path, b"same").unwrap();
+        fs::write(dir.join("target").join(super::TARGET_FILENAME), b"same").unwrap();
+        let mut synchronisation = Synchronisation { name: "nas", storage: storage.as_ref(), local_path: &local_path, state: &mut state, can_download: true, now: 2000 };
+        assert!(synchronisation.run() == Ok(Outcome::UpToDate));
+        assert!(state.targets.contains_key("nas"));
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn sync_log() {
+        let mut state = SyncState::default();
+        for i in 0..super::SYNC_LOG_CAPACITY + 3 {
+            let result = if i % 2 == 0 { SyncResult::Uploaded } else { SyncResult::Failed };
+            state.log(SyncLogEntry { at: i as i64, target: format!("t{}", i % 2), result, detail: String::new() });
+        }
+        assert!(state.log.len() == super::SYNC_LOG_CAPACITY);
+        assert!(state.log[0].at == 3);
+        assert!(state.last_result("t0").map(|entry| entry.at) == Some(super::SYNC_LOG_CAPACITY as i64 + 2));
+        assert!(state.last_result("t1").map(|entry| entry.result) == Some(SyncResult::Failed));
+        assert!(state.last_result("t2").is_none());
+        let saved = toml::to_string(&state).unwrap();
+        assert!(toml::from_str::<SyncState>(&saved).unwrap() == state);
+    }
+
+    #[test]
+    fn local_changes() {
+        let dir = temp_dir("changes");
+        let local_path = dir.join(".sec");
+        let target = folder_target(&dir);
+        let storage = super::storage(&target, None);
+        let mut state = SyncState::default();
+        assert!(!state.has_local_changes("nas", &local_path));
+        fs::write(&local_path, b"version 1").unwrap();
+        set_modified(&local_path, 1000);
+        assert!(state.has_local_changes("nas", &local_path));
+        let mut synchronisation = Synchronisation { name: "nas", storage: storage.as_ref(), local_path: &local_path, state: &mut state, can_download: true, now: 2000 };
+        assert!(synchronisation.run() == Ok(Outcome::Uploaded));
+        assert!(!state.has_local_changes("nas", &local_path));
+        // Saved again without changes
+        set_modified(&local_path, 3000);
+        assert!(!state.has_local_changes("nas", &local_path));
+        // Changed within the second of the upload
+        fs::write(&local_path, b"version 2").unwrap();
+        set_modified(&local_path, 1000);
+        assert!(state.has_local_changes("nas", &local_path));
+        set_modified(&local_path, 3000);
+        assert!(state.has_local_changes("nas", &local_path));
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn parse_propfind() {
+        let body = r#"<?xml version="1.0"?><d:multistatus xmlns:d="DAV:"><d:response><d:propstat><d:prop>
+            <d:getlastmodified>Tue, 15 Nov 1994 08:12:31 GMT</d:getlastmodified><d:getcontentlength>1234</d:getcontentlength>
+            </d:prop></d:propstat></d:response></d:multistatus>"#;
+        assert!(super::parse_propfind(body) == Ok(FileVersion { modified: 784887151, size: Some(1234) }));
+        let body = r#"<D:multistatus xmlns:D="DAV:"><D:response><D:propstat><D:prop>
+            <lp1:getcontentlength xmlns:lp1="DAV:">99</lp1:getcontentlength><D:resourcetype/>
+            <lp1:getlastmodified xmlns:lp1="DAV:" b:dt="dateTime.rfc1123">Tue, 15 Nov 1994 08:12:31 GMT</lp1:getlastmodified>
+            </D:prop></D:propstat></D:response></D:multistatus>"#;
+        assert!(super::parse_propfind(body) == Ok(FileVersion { modified: 784887151, size: Some(99) }));
+        let body = "<multistatus><getlastmodified>Tue, 15 Nov 1994 08:12:31 GMT</getlastmodified><getcontentlength/></multistatus>";
+        assert!(super::parse_propfind(body) == Ok(FileVersion { modified: 784887151, size: None }));
+        assert!(super::parse_propfind("<multistatus/>").is_err());
+    }
+
+    #[test]
+    fn targets_in_settings() {
+        let settings: crate::settings::Settings = toml::from_str(r#"
+[[sync_targets]]
+name = "nas"
+location = "/mnt/nas/keylock"
+
+[[sync_targets]]
+name = "dav"
+kind = "webdav"
+location = "https://dav.example.com/keylock"
+username = "alice"
+"#).unwrap();
+        assert!(settings.sync_targets.len() == 2);
+        assert!(settings.sync_targets[0].kind == TargetKind::Folder);
+        assert!(settings.sync_targets[1].needs_password());
+        assert!(toml::to_string(&settings).is_ok());
+    }
+}
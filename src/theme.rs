@@ -0,0 +1,271 @@
+// Copyright 2017 astonbitecode
+// This file is part of rust-keylock password manager.
+//
+// rust-keylock is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// rust-keylock is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with rust-keylock.  If not, see <http://www.gnu.org/licenses/>.
+//! The colours of the shell and the accessible output mode.
+//!
+//! A theme maps the roles of the presented text to colour specifications like `bold bright red`. When colours are not
+//! used, or in the accessible mode, the cues that were given only by colour are presented as textual markers.
+use std::collections::BTreeMap;
+use std::io::IsTerminal;
+use std::sync::OnceLock;
+
+use log::warn;
+use serde::{Deserialize, Serialize};
+use termcolor::{Color, ColorChoice, ColorSpec, StandardStream, WriteColor};
+
+use crate::settings::Settings;
+
+/// The name of the theme that is used if the configured one does not exist
+const DEFAULT_THEME: &str = "default";
+
+static STYLE: OnceLock<Style> = OnceLock::new();
+
+/// What a piece of text presents
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub(crate) enum Role {
+    Error,
+    Warn,
+    Info,
+    /// An Entry whose password was found in leaks
+    Leaked,
+    /// An Entry whose password is due for rotation
+    DueForRotation,
+}
+
+/// The colour specifications of a theme, like `bold bright red`. An empty specification means the default colours.
+#[derive(Debug, Default, PartialEq, Eq, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub(crate) struct ThemeDefinition {
+    pub(crate) error: String,
+    pub(crate) warn: String,
+    pub(crate) info: String,
+    pub(crate) leaked: String,
+    pub(crate) due_for_rotation: String,
+}
+
+impl ThemeDefinition {
+    fn new(error: &str, warn: &str, info: &str, leaked: &str, due_for_rotation: &str) -> ThemeDefinition {
+        ThemeDefinition {
+            error: error.to_string(),
+            warn: warn.to_string(),
+            info: info.to_string(),
+            leaked: leaked.to_string(),
+            due_for_rotation: due_for_rotation.to_string(),
+        }
+    }
+
+    fn spec(&self, role: Role) -> &str {
+        match role {
+            Role::Error => &self.error,
+            Role::Warn => &self.warn,
+            Role::Info => &self.info,
+            Role::Leaked => &self.leaked,
+            Role::DueForRotation => &self.due_for_rotation,
+        }
+    }
+}
+
+/// The themes that are always available. The themes of the settings may override them.
+fn built_in_themes() -> BTreeMap<String, ThemeDefinition> {
+    let mut themes = BTreeMap::new();
+    themes.insert(DEFAULT_THEME.to_string(), ThemeDefinition::new("red", "yellow", "blue", "red", "yellow"));
+    themes.insert("high-contrast".to_string(),
+                  ThemeDefinition::new("bold bright red", "bold bright yellow", "bold bright cyan", "bold bright red underline", "bold bright yellow"));
+    themes.insert("monochrome".to_string(), ThemeDefinition::new("bold", "bold", "", "bold underline", "underline"));
+    themes
+}
+
+/// Parses a colour specification: a color name, optionally with `bright`, `bold`, `underline` or `on <color>`
+pub(crate) fn parse_spec(spec: &str) -> Result<ColorSpec, String> {
+    let mut color_spec = ColorSpec::new();
+    let mut background = false;
+    for word in spec.split_whitespace() {
+        match word.to_lowercase().as_str() {
+            "bold" => {
+                color_spec.set_bold(true);
+            }
+            "underline" => {
+                color_spec.set_underline(true);
+            }
+            "bright" | "intense" => {
+                color_spec.set_intense(true);
+            }
+            "on" => background = true,
+            "none" => {}
+            name => {
+                let color = match name {
+                    "black" => Color::Black,
+                    "red" => Color::Red,
+                    "green" => Color::Green,
+                    "yellow" => Color::Yellow,
+                    "blue" => Color::Blue,
+                    "magenta" => Color::Magenta,
+                    "cyan" => Color::Cyan,
+                    "white" => Color::White,
+                    other => return Err(format!("Unknown colour '{}'", other)),
+                };
+                if background {
+                    color_spec.set_bg(Some(color));
+                } else {
+                    color_spec.set_fg(Some(color));
+                }
+            }
+        }
+    }
+    Ok(color_spec)
+}
+
+/// Decides whether colours are used. `setting` is `auto`, `always` or `never`.
+pub(crate) fn color_choice(setting: &str, no_color: Option<String>, term: Option<String>, is_terminal: bool) -> ColorChoice {
+    match setting {
+        "always" => ColorChoice::Always,
+        "never" => ColorChoice::Never,
+        _ => {
+            // https://no-color.org: any non empty value disables the colours
+            let no_color = no_color.map(|value| !value.is_empty()).unwrap_or(false);
+            let dumb = term.map(|term| term == "dumb").unwrap_or(false);
+            if no_color || dumb || !is_terminal {
+                ColorChoice::Never
+            } else {
+                ColorChoice::Always
+            }
+        }
+    }
+}
+
+/// How the shell presents its output
+#[derive(Debug)]
+pub(crate) struct Style {
+    theme: ThemeDefinition,
+    color_choice: ColorChoice,
+    accessible: bool,
+}
+
+impl Default for Style {
+    fn default() -> Self {
+        Style { theme: built_in_themes().remove(DEFAULT_THEME).unwrap_or_default(), color_choice: ColorChoice::Never, accessible: false }
+    }
+}
+
+impl Style {
+    fn new(settings: &Settings, color_choice: ColorChoice) -> Style {
+        let mut themes = built_in_themes();
+        themes.extend(settings.themes.clone());
+        let theme = match themes.remove(&settings.theme) {
+            Some(theme) => theme,
+            None => {
+                warn!("Unknown theme '{}'. Using the default one.", settings.theme);
+                themes.remove(DEFAULT_THEME).unwrap_or_default()
+            }
+        };
+        Style { theme, color_choice, accessible: settings.accessible }
+    }
+
+    /// The standard output, with colours if they are used
+    pub(crate) fn stdout(&self) -> StandardStream {
+        StandardStream::stdout(self.color_choice)
+    }
+
+    /// Sets the colours of the role to the stream
+    pub(crate) fn set(&self, stream: &mut StandardStream, role: Role) {
+        let spec = parse_spec(self.theme.spec(role)).unwrap_or_else(|error| {
+            warn!("Invalid colour of {:?}: {}", role, error);
+            ColorSpec::new()
+        });
+        let _ = stream.set_color(&spec);
+    }
+
+    pub(crate) fn reset(&self, stream: &mut StandardStream) {
+        let _ = stream.reset();
+    }
+
+    /// Whether the cues that are given by colour should be given as text as well
+    pub(crate) fn uses_markers(&self) -> bool {
+        self.accessible || self.color_choice == ColorChoice::Never
+    }
+
+    /// Whether the screen may be cleared. Clearing the screen confuses screen readers.
+    pub(crate) fn clears_screen(&self) -> bool {
+        !self.accessible
+    }
+}
+
+/// Sets the style of the shell from the settings and the environment. Only the first call has an effect.
+pub(crate) fn init(settings: &Settings) {
+    let choice = color_choice(&settings.color,
+                              std::env::var("NO_COLOR").ok(),
+                              std::env::var("TERM").ok(),
+                              std::io::stdout().is_terminal());
+    let _ = STYLE.set(Style::new(settings, choice));
+}
+
+pub(crate) fn current() -> &'static Style {
+    STYLE.get_or_init(Style::default)
+}
+
+#[cfg(test)]
+mod test_theme {
+    use termcolor::{Color, ColorChoice};
+
+    use crate::settings::Settings;
+
+    use super::{Role, Style, ThemeDefinition};
+
+    #[test]
+    fn parse_spec() {
+        let spec = super::parse_spec("bold bright red on white").unwrap();
+        assert!(spec.bold() && spec.intense());
+        assert!(spec.fg() == Some(&Color::Red));
+        assert!(spec.bg() == Some(&Color::White));
+        assert!(super::parse_spec("").unwrap().is_none());
+        assert!(super::parse_spec("purple").is_err());
+    }
+
+    #[test]
+    fn built_in_themes_are_valid() {
+        for (name, theme) in super::built_in_themes() {
+            for role in [Role::Error, Role::Warn, Role::Info, Role::Leaked, Role::DueForRotation] {
+                assert!(super::parse_spec(theme.spec(role)).is_ok(), "Invalid {:?} of theme {}", role, name);
+            }
+        }
+    }
+
+    #[test]
+    fn color_choice() {
+        assert!(super::color_choice("auto", None, Some("xterm".to_string()), true) == ColorChoice::Always);
+        assert!(super::color_choice("auto", Some("1".to_string()), None, true) == ColorChoice::Never);
+        assert!(super::color_choice("auto", Some("".to_string()), None, true) == ColorChoice::Always);
+        assert!(super::color_choice("auto", None, Some("dumb".to_string()), true) == ColorChoice::Never);
+        assert!(super::color_choice("auto", None, None, false) == ColorChoice::Never);
+        assert!(super::color_choice("always", Some("1".to_string()), None, false) == ColorChoice::Always);
+        assert!(super::color_choice("never", None, None, true) == ColorChoice::Never);
+    }
+
+    #[test]
+    fn style_from_settings() {
+        let mut settings = Settings { theme: "mine".to_string(), ..Default::default() };
+        settings.themes.insert("mine".to_string(), ThemeDefinition { leaked: "magenta".to_string(), ..Default::default() });
+        let style = Style::new(&settings, ColorChoice::Always);
+        assert!(style.theme.leaked == "magenta");
+        assert!(!style.uses_markers() && style.clears_screen());
+
+        settings.theme = "missing".to_string();
+        settings.accessible = true;
+        let style = Style::new(&settings, ColorChoice::Always);
+        assert!(style.theme.error == "red");
+        assert!(style.uses_markers() && !style.clears_screen());
+        assert!(Style::new(&Settings::default(), ColorChoice::Never).uses_markers());
+    }
+}
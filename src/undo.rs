@@ -0,0 +1,218 @@
+// Copyright 2017 astonbitecode
+// This file is part of rust-keylock password manager.
+//
+// rust-keylock is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// rust-keylock is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with rust-keylock.  If not, see <http://www.gnu.org/licenses/>.
+use rust_keylock::{Entry, UserSelection};
+
+use crate::bulk::EntryKey;
+use crate::i18n;
+
+/// A change of the Entries that was done in the current session.
+///
+/// The Entries are kept decrypted, so that they can be given back to the library as they are.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub(crate) enum Mutation {
+    Added(Entry),
+    Deleted(Entry),
+    Replaced { old: Entry, new: Entry },
+}
+
+impl Mutation {
+    pub(crate) fn inverse(&self) -> Mutation {
+        match self {
+            Mutation::Added(entry) => Mutation::Deleted(entry.clone()),
+            Mutation::Deleted(entry) => Mutation::Added(entry.clone()),
+            Mutation::Replaced { old, new } => Mutation::Replaced { old: new.clone(), new: old.clone() },
+        }
+    }
+
+    /// Returns the selection that applies the mutation on the presented `entries`.
+    ///
+    /// Returns None if the Entry that should be deleted or replaced cannot be found.
+    pub(crate) fn selection(&self, entries: &[Entry]) -> Option<UserSelection> {
+        match self {
+            Mutation::Added(entry) => Some(UserSelection::NewEntry(entry.clone())),
+            Mutation::Deleted(entry) => EntryKey::new(entry).find_in(entries).map(UserSelection::DeleteEntry),
+            Mutation::Replaced { old, new } => EntryKey::new(old).find_in(entries).map(|index| UserSelection::ReplaceEntry(index, new.clone())),
+        }
+    }
+
+    pub(crate) fn description(&self) -> String {
+        match self {
+            Mutation::Added(entry) => i18n::tr_with("mutation-added", &[("name", &entry.name)]),
+            Mutation::Deleted(entry) => i18n::tr_with("mutation-deleted", &[("name", &entry.name)]),
+            Mutation::Replaced { new, .. } => i18n::tr_with("mutation-replaced", &[("name", &new.name)]),
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+enum Direction {
+    Do,
+    Undo,
+    Redo,
+}
+
+/// The undo and redo stacks of the session.
+///
+/// A mutation is not pushed in the stacks when its selection is returned to the library, because the library may still
+/// reject it (e.g. when the User does not accept a leaked password). It stays pending until the Entries list is shown again.
+#[derive(Debug, Default)]
+pub(crate) struct UndoHistory {
+    undo: Vec<Mutation>,
+    redo: Vec<Mutation>,
+    // A mutation that should be applied the next time the Entries are presented
+    scheduled: Option<(Mutation, Direction)>,
+    // A mutation that was handed to the library and waits to be confirmed
+    pending: Option<(Mutation, Direction)>,
+    // The Entry as it was before an edit that asked for a generated passphrase
+    original: Option<Entry>,
+}
+
+impl UndoHistory {
+    /// Records a mutation that was done by the User
+    pub(crate) fn record(&mut self, mutation: Mutation) {
+        self.pending = Some((mutation, Direction::Do));
+    }
+
+    /// The library applied the pending mutation
+    pub(crate) fn confirm(&mut self) {
+        match self.pending.take() {
+            Some((mutation, Direction::Do)) => {
+                self.undo.push(mutation);
+                self.redo.clear();
+            }
+            Some((mutation, Direction::Undo)) => self.redo.push(mutation),
+            Some((mutation, Direction::Redo)) => self.undo.push(mutation),
+            None => { /* ignore */ }
+        }
+    }
+
+    /// The library rejected the pending mutation
+    pub(crate) fn discard(&mut self) {
+        match self.pending.take() {
+            Some((mutation, Direction::Undo)) => self.undo.push(mutation),
+            Some((mutation, Direction::Redo)) => self.redo.push(mutation),
+            _ => { /* ignore */ }
+        }
+    }
+
+    /// Keeps the Entry that is being edited, because the library presents it again with a generated passphrase
+    pub(crate) fn remember_original(&mut self, entry: &Entry) {
+        if self.original.is_none() {
+            self.original = Some(entry.clone());
+        }
+    }
+
+    pub(crate) fn take_original(&mut self) -> Option<Entry> {
+        self.original.take()
+    }
+
+    pub(crate) fn schedule_undo(&mut self) {
+        if let Some(mutation) = self.undo.pop() {
+            self.scheduled = Some((mutation, Direction::Undo));
+        }
+    }
+
+    pub(crate) fn schedule_redo(&mut self) {
+        if let Some(mutation) = self.redo.pop() {
+            self.scheduled = Some((mutation, Direction::Redo));
+        }
+    }
+
+    /// Returns the selection for a scheduled undo or redo, applied on the presented `entries`.
+    ///
+    /// Returns an error with a message for the User if the Entry of the scheduled mutation cannot be found.
+    pub(crate) fn scheduled_selection(&mut self, entries: &[Entry]) -> Option<Result<UserSelection, String>> {
+        let (mutation, direction) = self.scheduled.take()?;
+        let to_apply = if direction == Direction::Undo {
+            mutation.inverse()
+        } else {
+            mutation.clone()
+        };
+        match to_apply.selection(entries) {
+            Some(selection) => {
+                self.pending = Some((mutation, direction));
+                Some(Ok(selection))
+            }
+            None => Some(Err(i18n::tr_with("undo-not-found", &[("mutation", &mutation.description())]))),
+        }
+    }
+
+    pub(crate) fn has_scheduled(&self) -> bool {
+        self.scheduled.is_some()
+    }
+
+    pub(crate) fn next_undo(&self) -> Option<&Mutation> {
+        self.undo.last()
+    }
+
+    pub(crate) fn next_redo(&self) -> Option<&Mutation> {
+        self.redo.last()
+    }
+
+    pub(crate) fn clear(&mut self) {
+        *self = UndoHistory::default();
+    }
+}
+
+#[cfg(test)]
+mod test_undo {
+    use rust_keylock::{Entry, EntryMeta, UserSelection};
+
+    use super::{Mutation, UndoHistory};
+
+    fn entry(name: &str, pass: &str) -> Entry {
+        Entry::new(name.to_string(), "url".to_string(), "user".to_string(), pass.to_string(), "desc".to_string(), EntryMeta::default())
+    }
+
+    #[test]
+    fn mutation_inverse_selection() {
+        let entries = vec![entry("a", "encrypted"), entry("b", "encrypted")];
+        assert!(Mutation::Added(entry("b", "pass")).inverse().selection(&entries) == Some(UserSelection::DeleteEntry(1)));
+        assert!(Mutation::Deleted(entry("c", "pass")).inverse().selection(&entries) == Some(UserSelection::NewEntry(entry("c", "pass"))));
+        let replaced = Mutation::Replaced { old: entry("c", "old"), new: entry("b", "new") };
+        assert!(replaced.inverse().selection(&entries) == Some(UserSelection::ReplaceEntry(1, entry("c", "old"))));
+        assert!(Mutation::Added(entry("x", "pass")).inverse().selection(&entries).is_none());
+    }
+
+    #[test]
+    fn undo_redo() {
+        let mut history = UndoHistory::default();
+        history.record(Mutation::Added(entry("a", "pass")));
+        assert!(history.next_undo().is_none());
+        history.confirm();
+        assert!(history.next_undo() == Some(&Mutation::Added(entry("a", "pass"))));
+
+        history.schedule_undo();
+        let entries = vec![entry("a", "encrypted")];
+        assert!(history.scheduled_selection(&entries) == Some(Ok(UserSelection::DeleteEntry(0))));
+        history.confirm();
+        assert!(history.next_undo().is_none());
+        assert!(history.next_redo() == Some(&Mutation::Added(entry("a", "pass"))));
+
+        history.schedule_redo();
+        assert!(history.scheduled_selection(&[]) == Some(Ok(UserSelection::NewEntry(entry("a", "pass")))));
+        // Rejected by the library
+        history.discard();
+        assert!(history.next_redo() == Some(&Mutation::Added(entry("a", "pass"))));
+
+        history.record(Mutation::Deleted(entry("b", "pass")));
+        history.confirm();
+        assert!(history.next_redo().is_none());
+
+        history.clear();
+        assert!(history.next_undo().is_none());
+    }
+}